@@ -0,0 +1,468 @@
+use serde::Serialize;
+
+use crate::entropy;
+use crate::ihdr::ColourType;
+use crate::png::Png;
+use crate::text;
+
+// Payloads shorter than this are too small for an entropy estimate to mean
+// much.
+const MIN_ANOMALY_LEN: usize = 64;
+// Chunks whose payload is deflate or JPEG data by design and so always looks
+// random.
+const COMPRESSED_CHUNK_TYPES: [&str; 3] = ["zTXt", "iCCP", "eXIf"];
+const TEXT_ENTROPY_CEILING: f64 = 5.5;
+const BINARY_ENTROPY_CEILING: f64 = 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Likelihood {
+    Unlikely,
+    Possible,
+    Likely,
+}
+
+impl std::fmt::Display for Likelihood {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Unlikely => "unlikely",
+            Self::Possible => "possible",
+            Self::Likely => "likely",
+        })
+    }
+}
+
+// An ancillary chunk whose payload is more random than its type explains.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntropyAnomaly {
+    pub index: usize,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub length: usize,
+    pub entropy: f64,
+    pub ceiling: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Analysis {
+    // Probability from the chi-square attack that pixel LSBs were overwritten
+    // sequentially, taken over the worst prefix of the image.
+    pub chi_square: Option<f64>,
+    // Fraction of samples RS analysis estimates carry payload bits.
+    pub rs_rate: Option<f64>,
+    // Why the pixel tests did not run, if they did not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pixels_skipped: Option<String>,
+    pub anomalies: Vec<EntropyAnomaly>,
+    pub likelihood: Likelihood,
+}
+
+pub fn analyze(png: &Png) -> Analysis {
+    let (chi_square, rs_rate, pixels_skipped) = match colour_planes(png) {
+        Ok((planes, width)) => {
+            // The chi-square prefixes follow pixel order, the order a
+            // sequential embedder writes in.
+            let samples: Vec<u16> = (0..planes[0].len())
+                .flat_map(|i| planes.iter().map(move |plane| plane[i]))
+                .collect();
+            // RS groups are runs of horizontally adjacent samples of one
+            // channel, never spanning two rows.
+            let groups: Vec<[i32; 4]> = planes
+                .iter()
+                .flat_map(|plane| plane.chunks_exact(width))
+                .flat_map(|row| row.chunks_exact(4))
+                .map(|group| [group[0], group[1], group[2], group[3]].map(i32::from))
+                .collect();
+            (chi_square(&samples), rs_rate(&groups), None)
+        }
+        Err(reason) => (None, None, Some(reason)),
+    };
+    let anomalies = entropy_anomalies(png);
+    // Images with smooth histograms pass the chi-square attack's test for
+    // evened-out pairs without any payload, so on its own it only makes a
+    // payload possible.
+    let chi_square_fits = chi_square >= Some(0.95);
+    let likelihood = if rs_rate >= Some(0.15)
+        || (chi_square_fits && rs_rate >= Some(0.05))
+        || !anomalies.is_empty()
+    {
+        Likelihood::Likely
+    } else if chi_square_fits || rs_rate >= Some(0.05) {
+        Likelihood::Possible
+    } else {
+        Likelihood::Unlikely
+    };
+    Analysis {
+        chi_square,
+        rs_rate,
+        pixels_skipped,
+        anomalies,
+        likelihood,
+    }
+}
+
+// One plane of row-major samples per colour channel, and the image width.
+// Alpha is left out as it is mostly constant and would swamp the statistics.
+// Palette indices and packed samples have no meaningful least significant
+// bit, so those images are skipped, as are 16-bit images whose low bits are
+// sensor noise rather than image.
+fn colour_planes(png: &Png) -> Result<(Vec<Vec<u16>>, usize), String> {
+    let pixels = png.decode_pixels().map_err(|error| error.to_string())?;
+    let header = pixels.header;
+    if header.colour_type == ColourType::Indexed || header.bit_depth < 8 {
+        return Err(format!(
+            "{}-bit {} images have no LSB plane to test",
+            header.bit_depth, header.colour_type
+        ));
+    }
+    let channels = header.colour_type.channels();
+    let colour_channels = match header.colour_type {
+        ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha => channels - 1,
+        _ => channels,
+    };
+    let mut samples = pixels.samples();
+    if header.bit_depth == 16 {
+        // An 8-bit image widened to 16 bits repeats each high byte in the low
+        // one, so its low bytes keep the image's structure and a payload in
+        // their LSBs shows up just as in an 8-bit image. Anything else has
+        // low bits the tests cannot tell from a payload.
+        if !samples
+            .iter()
+            .all(|&sample| sample >> 9 == (sample & 0xFF) >> 1)
+        {
+            return Err(
+                "16-bit samples with noisy low bytes give the LSB tests nothing to go on"
+                    .to_string(),
+            );
+        }
+        samples.iter_mut().for_each(|sample| *sample &= 0xFF);
+    }
+    let planes = (0..colour_channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect()
+        })
+        .collect();
+    Ok((planes, header.width as usize))
+}
+
+// Westfeld and Pfitzmann's chi-square attack: overwriting LSBs with random
+// bits evens out the counts of each pair of values 2k and 2k+1, so a good fit
+// to the evened-out histogram means a payload. Sequential embedding only
+// evens out the start of the image, hence the prefixes.
+fn chi_square(samples: &[u16]) -> Option<f64> {
+    (1..=10)
+        .filter_map(|tenth| chi_square_probability(&samples[..samples.len() * tenth / 10]))
+        .reduce(f64::max)
+}
+
+fn chi_square_probability(samples: &[u16]) -> Option<f64> {
+    let mut counts = vec![0u64; 1 << 16];
+    for &sample in samples {
+        counts[usize::from(sample)] += 1;
+    }
+    let mut statistic = 0.0;
+    let mut categories = 0;
+    for pair in counts.chunks_exact(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        // The approximation needs at least five expected observations.
+        if expected < 5.0 {
+            continue;
+        }
+        statistic += (pair[0] as f64 - expected).powi(2) / expected;
+        categories += 1;
+    }
+    if categories < 2 {
+        return None;
+    }
+    Some(chi_square_upper_tail(statistic, f64::from(categories - 1)))
+}
+
+// P(X > statistic) for a chi-square distribution, by the Wilson-Hilferty
+// normal approximation.
+fn chi_square_upper_tail(statistic: f64, freedom: f64) -> f64 {
+    let variance = 2.0 / (9.0 * freedom);
+    let z = ((statistic / freedom).cbrt() - (1.0 - variance)) / variance.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function, accurate to about 1e-7 (Numerical Recipes).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, coefficient| coefficient + t * sum);
+    let result = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+// Fridrich's RS analysis. Groups of four samples are classed as regular or
+// singular by whether flipping LSBs under a mask makes them noisier or
+// smoother; in a clean image flipping with the mask and with its negative
+// behave alike, and embedding pulls them apart in a way that gives away the
+// embedding rate.
+fn rs_rate(groups: &[[i32; 4]]) -> Option<f64> {
+    const MASK: [bool; 4] = [false, true, true, false];
+    if groups.len() < 16 {
+        return None;
+    }
+    let smoothness =
+        |group: &[i32; 4]| -> i32 { group.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum() };
+    let flip = |value: i32| value ^ 1;
+    let flip_negative = |value: i32| ((value + 1) ^ 1) - 1;
+    // The differences R - S for the mask and its negative.
+    let differences = |invert: bool| -> (f64, f64) {
+        let (mut positive, mut negative) = (0i64, 0i64);
+        for group in groups {
+            let group = group.map(|value| if invert { flip(value) } else { value });
+            let before = smoothness(&group);
+            let mut masked = group;
+            let mut negated = group;
+            for i in 0..4 {
+                if MASK[i] {
+                    masked[i] = flip(masked[i]);
+                    negated[i] = flip_negative(negated[i]);
+                }
+            }
+            positive += i64::from(smoothness(&masked).cmp(&before) as i8);
+            negative += i64::from(smoothness(&negated).cmp(&before) as i8);
+        }
+        let count = groups.len() as f64;
+        (positive as f64 / count, negative as f64 / count)
+    };
+    let (d0, d_negative0) = differences(false);
+    let (d1, d_negative1) = differences(true);
+    let a = 2.0 * (d1 + d0);
+    let b = d_negative0 - d_negative1 - d1 - 3.0 * d0;
+    let c = d0 - d_negative0;
+    let x = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON {
+            return Some(0.0);
+        }
+        -c / b
+    } else {
+        let discriminant = (b * b - 4.0 * a * c).max(0.0).sqrt();
+        let roots = [
+            (-b + discriminant) / (2.0 * a),
+            (-b - discriminant) / (2.0 * a),
+        ];
+        if roots[0].abs() < roots[1].abs() {
+            roots[0]
+        } else {
+            roots[1]
+        }
+    };
+    Some((x / (x - 0.5)).clamp(0.0, 1.0))
+}
+
+fn entropy_anomalies(png: &Png) -> Vec<EntropyAnomaly> {
+    let mut anomalies = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type().to_string();
+        if chunk.chunk_type().is_critical()
+            || chunk.data().len() < MIN_ANOMALY_LEN
+            || COMPRESSED_CHUNK_TYPES.contains(&chunk_type.as_str())
+        {
+            continue;
+        }
+        let ceiling = match text::raw_keyword(chunk) {
+            // A compressed iTXt has its flag byte set right after the keyword.
+            Some(keyword)
+                if chunk_type == "iTXt" && chunk.data().get(keyword.len() + 1) == Some(&1) =>
+            {
+                continue
+            }
+            Some(_) => TEXT_ENTROPY_CEILING,
+            None => BINARY_ENTROPY_CEILING,
+        };
+        let entropy = entropy::shannon(chunk.data());
+        if entropy > ceiling {
+            anomalies.push(EntropyAnomaly {
+                index,
+                chunk_type,
+                length: chunk.data().len(),
+                entropy,
+                ceiling,
+            });
+        }
+    }
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use crate::chunk::Chunk;
+    use std::io::Write;
+
+    // Deterministic noise so the statistics do not vary between runs.
+    fn noise(seed: &mut u32) -> u32 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 17;
+        *seed ^= *seed << 5;
+        *seed
+    }
+
+    // A smooth greyscale gradient with a little noise, like a photo, rounded
+    // to even values so the value pairs start out unbalanced.
+    fn photo() -> Vec<u8> {
+        let mut seed = 7;
+        (0..128 * 128)
+            .map(|i| {
+                let (x, y) = ((i % 128) as f64, (i / 128) as f64);
+                let base = 128.0 + 50.0 * (x / 19.0).sin() + 40.0 * (y / 23.0).cos();
+                (base as i32 + (noise(&mut seed) % 5) as i32 - 2) as u8 & !1
+            })
+            .collect()
+    }
+
+    fn embed(samples: &mut [u8]) {
+        let mut seed = 99;
+        for sample in samples {
+            *sample = (*sample & !1) | (noise(&mut seed) & 1) as u8;
+        }
+    }
+
+    fn png(samples: &[u8], extra: Vec<Chunk>) -> Png {
+        greyscale(8, samples, extra)
+    }
+
+    // A 128x128 greyscale image from big-endian 16-bit samples.
+    fn png16(samples: &[u16]) -> Png {
+        let data: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes())
+            .collect();
+        greyscale(16, &data, vec![])
+    }
+
+    fn greyscale(depth: u8, data: &[u8], extra: Vec<Chunk>) -> Png {
+        let mut header = 128u32.to_be_bytes().to_vec();
+        header.extend(128u32.to_be_bytes());
+        header.extend([depth, 0, 0, 0, 0]);
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        for row in data.chunks(128 * usize::from(depth / 8)) {
+            encoder.write_all(&[0]).unwrap();
+            encoder.write_all(row).unwrap();
+        }
+        let mut chunks = vec![chunk("IHDR", &header)];
+        chunks.extend(extra);
+        chunks.push(chunk("IDAT", &encoder.finish().unwrap()));
+        chunks.push(chunk("IEND", b""));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_clean_image() {
+        let analysis = analyze(&png(&photo(), vec![]));
+        assert!(analysis.chi_square.unwrap() < 0.05);
+        assert!(analysis.rs_rate.unwrap() < 0.05);
+        assert!(analysis.anomalies.is_empty());
+        assert_eq!(analysis.likelihood, Likelihood::Unlikely);
+    }
+
+    #[test]
+    fn test_lsb_embedding_is_detected() {
+        let mut samples = photo();
+        embed(&mut samples);
+        let analysis = analyze(&png(&samples, vec![]));
+        assert!(analysis.chi_square.unwrap() > 0.95);
+        assert!(analysis.rs_rate.unwrap() > 0.5);
+        assert_eq!(analysis.likelihood, Likelihood::Likely);
+    }
+
+    #[test]
+    fn test_entropy_anomalies() {
+        let mut seed = 3;
+        let random: Vec<u8> = (0..512).map(|_| noise(&mut seed) as u8).collect();
+        let mut text = b"Comment\0".to_vec();
+        text.extend(random.iter().map(|byte| b'!' + byte % 90));
+        let analysis = analyze(&png(
+            &photo(),
+            vec![
+                chunk("ruSt", &random),
+                chunk("zTXt", &random),
+                chunk("tEXt", &text),
+                chunk("tEXt", &[b"Title\0".as_slice(), &[b'a'; 100]].concat()),
+            ],
+        ));
+        let flagged: Vec<usize> = analysis.anomalies.iter().map(|a| a.index).collect();
+        assert_eq!(flagged, [1, 3]);
+        assert_eq!(analysis.likelihood, Likelihood::Likely);
+    }
+
+    #[test]
+    fn test_pixel_tests_skip_unsupported_images() {
+        let analysis = analyze(&Png::from_chunks(vec![chunk("IEND", b"")]));
+        assert!(analysis.chi_square.is_none());
+        assert!(analysis.pixels_skipped.is_some());
+    }
+
+    #[test]
+    fn test_widened_sixteen_bit_images() {
+        let widen = |samples: &[u8]| -> Vec<u16> {
+            samples
+                .iter()
+                .map(|&sample| u16::from(sample) * 257)
+                .collect()
+        };
+        let clean = analyze(&png16(&widen(&photo())));
+        assert!(clean.pixels_skipped.is_none());
+        assert_eq!(clean.likelihood, Likelihood::Unlikely);
+
+        let mut samples = photo();
+        embed(&mut samples);
+        // Embedding only touches the low byte, leaving the high byte even.
+        let embedded: Vec<u16> = widen(&photo())
+            .iter()
+            .zip(&samples)
+            .map(|(&wide, &narrow)| (wide & !1) | u16::from(narrow & 1))
+            .collect();
+        let analysis = analyze(&png16(&embedded));
+        assert!(analysis.rs_rate.unwrap() > 0.5);
+        assert_eq!(analysis.likelihood, Likelihood::Likely);
+    }
+
+    #[test]
+    fn test_noisy_sixteen_bit_images_are_skipped() {
+        let mut seed = 11;
+        let samples: Vec<u16> = photo()
+            .iter()
+            .map(|&sample| (u16::from(sample) << 8) | (noise(&mut seed) & 0xFF) as u16)
+            .collect();
+        let analysis = analyze(&png16(&samples));
+        assert!(analysis.rs_rate.is_none());
+        assert!(analysis.pixels_skipped.is_some());
+        assert_eq!(analysis.likelihood, Likelihood::Unlikely);
+    }
+
+    #[test]
+    fn test_erfc() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157_299_2).abs() < 1e-6);
+        assert!((erfc(-1.0) - 1.842_700_8).abs() < 1e-6);
+    }
+}
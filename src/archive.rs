@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use crate::error::{PngmeError, Result};
+
+pub const MIME_TYPE: &str = "application/x-tar";
+
+pub fn pack_dir(dir: &Path) -> Result<Vec<u8>> {
+    if !dir.is_dir() {
+        return Err(PngmeError::Usage(format!(
+            "{} is not a directory",
+            dir.display()
+        )));
+    }
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    Ok(builder.into_inner()?)
+}
+
+pub fn unpack(data: &[u8], dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    // `unpack` refuses entries that would escape `dest`.
+    Ok(tar::Archive::new(data).unpack(dest)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir(source.path().join("nested")).unwrap();
+        fs::write(source.path().join("top.txt"), b"top").unwrap();
+        fs::write(source.path().join("nested/inner.bin"), [0, 1, 2]).unwrap();
+
+        let packed = pack_dir(source.path()).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        unpack(&packed, &dest.path().join("out")).unwrap();
+        let out = dest.path().join("out");
+        assert_eq!(fs::read(out.join("top.txt")).unwrap(), b"top");
+        assert_eq!(fs::read(out.join("nested/inner.bin")).unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_pack_requires_directory() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert!(pack_dir(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_unpack_garbage() {
+        let dest = tempfile::tempdir().unwrap();
+        assert!(unpack(&[1u8; 1024], dest.path()).is_err());
+    }
+}
@@ -0,0 +1,1078 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use pngme::compression::Codec;
+use pngme::crypto::{CipherKind, KdfParams, RecipientKey};
+use pngme::extensions::Equation;
+use pngme::lint::{self, Rule};
+use pngme::pattern::ChunkPattern;
+use pngme::pixels::Filter;
+use pngme::signing::VerifyingKey;
+use pngme::stego::Method;
+use pngme::template::Template;
+use pngme::text::Keyword;
+use pngme::timestamp::Timestamp;
+use pngme::transfer::Prefer;
+
+use crate::style::ColorChoice;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Hide messages in PNG chunks",
+    after_help = "Commands that take a PNG path also accept a directory or a quoted glob \
+                  such as 'assets/**/*.png' or '{a,b}.png', and run once per file. \
+                  Use --recursive, --max-depth, --include and --exclude to choose \
+                  which files inside a directory are processed; paths listed in a \
+                  .pngmeignore file (gitignore syntax) are always skipped.\n\n\
+                  Exit status: 0 success, 1 other failure, 2 usage error, 3 I/O error, \
+                  4 parse error, 5 CRC mismatch, 6 chunk not found, \
+                  7 lint or policy failure"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Output format: text, json, yaml, or csv/tsv for list and print
+    #[arg(long, global = true, default_value = "text")]
+    pub format: OutputFormat,
+    /// Colour terminal output: auto, always or never
+    #[arg(long, global = true, default_value = "auto")]
+    pub color: ColorChoice,
+    /// Report failures on stderr as text or as a JSON object
+    #[arg(long, global = true, default_value = "text")]
+    pub errors: ErrorFormat,
+    /// Process up to this many files of a batch at once (0 uses every CPU);
+    /// text output from different files may interleave
+    #[arg(long, short = 'j', global = true, default_value_t = 1)]
+    pub threads: usize,
+    #[command(flatten)]
+    pub walk: WalkArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct WalkArgs {
+    /// Descend into subdirectories of a directory input
+    #[arg(long, short = 'r', global = true)]
+    pub recursive: bool,
+    /// Descend at most this many directories below a directory input
+    #[arg(long, global = true, requires = "recursive")]
+    pub max_depth: Option<usize>,
+    /// Only process files whose path below the input matches this glob (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Skip files whose path below the input matches this glob (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+    Csv,
+    Tsv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(format!("unknown output format {:?}", string)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown error format {:?}", string)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    Encode(EncodeArgs),
+    Decode(DecodeArgs),
+    Remove(RemoveArgs),
+    Print(PrintArgs),
+    Info(InfoArgs),
+    List(ListArgs),
+    Verify(VerifyArgs),
+    Repair(RepairArgs),
+    Salvage(SalvageArgs),
+    Lint(LintArgs),
+    Check(CheckArgs),
+    Filter(FilterArgs),
+    CheckHidden(CheckHiddenArgs),
+    Scan(ScanArgs),
+    Analyze(AnalyzeArgs),
+    Capacity(CapacityArgs),
+    StripTrailing(StripTrailingArgs),
+    Strip(StripArgs),
+    Anonymize(AnonymizeArgs),
+    Reorder(ReorderArgs),
+    Dedupe(DedupeArgs),
+    Optimize(OptimizeArgs),
+    Deinterlace(DeinterlaceArgs),
+    CloneMetadata(CloneMetadataArgs),
+    Diff(DiffArgs),
+    Merge(MergeArgs),
+    Copy(CopyArgs),
+    Keygen(KeygenArgs),
+    Fs(FsArgs),
+    ExtractAll(ExtractAllArgs),
+    Assemble(AssembleArgs),
+    Text(TextArgs),
+    Exif(ExifArgs),
+    Xmp(XmpArgs),
+    Time(TimeArgs),
+    Dpi(DpiArgs),
+    Offset(OffsetArgs),
+    Scale(ScaleArgs),
+    Calibration(CalibrationArgs),
+    Dsig(DsigArgs),
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct ParseArgs {
+    /// Keep going past bad CRCs, unknown chunks and trailing junk, printing warnings instead
+    #[arg(long, conflicts_with = "strict")]
+    pub lenient: bool,
+    /// Fail unless the file follows the PNG spec's chunk ordering and uniqueness rules
+    #[arg(long)]
+    pub strict: bool,
+    /// Read chunks whose CRC does not match their data instead of failing
+    #[arg(long, conflicts_with = "lenient")]
+    pub ignore_crc: bool,
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct TouchArgs {
+    /// Set the tIME chunk to the current time when writing the PNG
+    #[arg(long, global = true, overrides_with = "no_touch")]
+    pub update_time: bool,
+    /// Leave the tIME chunk as it is so output stays deterministic (the default)
+    #[arg(long, global = true)]
+    pub no_touch: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct EncodeArgs {
+    pub path: PathBuf,
+    /// Chunk type to store the payload in; left out with the other methods
+    pub chunk_type: Option<String>,
+    pub data: Option<String>,
+    /// Hide the payload in a chunk, in the least significant bits of the pixels (lsb), in the
+    /// alpha channel of opaque pixels (alpha), or in stored deflate blocks after the IDAT
+    /// stream (stored)
+    #[arg(long, default_value_t)]
+    pub method: Method,
+    /// Scanline filter for re-encoded pixels: none, sub, up, average, paeth or adaptive (the
+    /// default, chosen per scanline)
+    #[arg(long)]
+    pub filter: Option<Filter>,
+    /// Re-encode the pixels Adam7-interlaced (lsb or alpha)
+    #[arg(long)]
+    pub interlace: bool,
+    /// Read the payload as raw bytes from this file
+    #[arg(long, conflicts_with = "data")]
+    pub input_file: Option<PathBuf>,
+    /// Embed this directory tree as a tar archive
+    #[arg(long, conflicts_with_all = ["data", "input_file", "no_envelope"])]
+    pub dir: Option<PathBuf>,
+    /// Compress the payload before embedding it
+    #[arg(long, value_name = "CODEC")]
+    pub compress: Option<Codec>,
+    /// Compression level for the chosen codec
+    #[arg(long, requires = "compress", allow_negative_numbers = true)]
+    pub compress_level: Option<i32>,
+    /// Write the modified PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    /// Encrypt the payload with a password-derived key, prompting unless
+    /// --password, --keyring or $PNGME_PASSWORD supply one
+    #[arg(long)]
+    pub encrypt: bool,
+    /// Password used to derive the encryption key
+    #[arg(long, requires = "encrypt")]
+    pub password: Option<String>,
+    /// Fetch the password from the OS keyring entry with this name, storing it there once it works
+    #[arg(long, requires = "encrypt")]
+    pub keyring: Option<String>,
+    /// Encrypt the payload to this X25519 public key (repeatable)
+    #[arg(long = "recipient", conflicts_with_all = ["encrypt", "password"])]
+    pub recipients: Vec<RecipientKey>,
+    /// Encrypt the payload in the age format to this age1... recipient (repeatable)
+    #[arg(
+        long = "age-recipient",
+        conflicts_with_all = ["encrypt", "password", "recipients"]
+    )]
+    pub age_recipients: Vec<age::x25519::Recipient>,
+    /// Cipher used when encrypting (aes-256-gcm or chacha20-poly1305)
+    #[arg(long, default_value_t)]
+    pub cipher: CipherKind,
+    /// Argon2id parameters for --encrypt as m=<KiB>,t=<iterations>,p=<lanes>
+    #[arg(long, default_value_t, requires = "encrypt")]
+    pub kdf_params: KdfParams,
+    /// Sign the chunk with the Ed25519 private key in this file
+    #[arg(long, value_name = "KEYFILE")]
+    pub sign: Option<PathBuf>,
+    /// Sign the whole file with a dSIG chunk pair instead of per-chunk siGn chunks
+    #[arg(long, requires = "sign")]
+    pub dsig: bool,
+    /// Store the payload bare instead of wrapping it in a pngme envelope
+    #[arg(long)]
+    pub no_envelope: bool,
+    /// Media type of the payload, recorded in the envelope
+    #[arg(long, conflicts_with = "no_envelope")]
+    pub mime: Option<String>,
+    #[command(flatten)]
+    pub position: PositionArgs,
+    /// Overwrite existing chunks of the same type in place instead of adding another
+    #[arg(long, conflicts_with = "position")]
+    pub replace: bool,
+    /// Allow adding a second IHDR, PLTE or IEND chunk
+    #[arg(long)]
+    pub force: bool,
+    /// Split payloads larger than this many bytes across several chunks (1 MiB by default)
+    #[arg(long)]
+    pub max_chunk_size: Option<usize>,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PositionArgs {
+    /// Insert the chunk before the first chunk of this type
+    #[arg(long, value_name = "TYPE", group = "position")]
+    pub before: Option<String>,
+    /// Insert the chunk after the last chunk of this type
+    #[arg(long, value_name = "TYPE", group = "position")]
+    pub after: Option<String>,
+    /// Insert the chunk at this index in the chunk list
+    #[arg(long, value_name = "INDEX", group = "position")]
+    pub at: Option<usize>,
+    /// Append the chunk after IEND instead of just before it
+    #[arg(long, group = "position")]
+    pub after_iend: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DecodeArgs {
+    pub path: PathBuf,
+    /// Chunk types, globs (ru??) or /regexes/; patterns decode every matching chunk
+    #[arg(required_unless_present_any = ["nth", "method"])]
+    pub chunk_types: Vec<ChunkPattern>,
+    /// Read the payload from a chunk, the least significant bits of the pixels (lsb), the
+    /// alpha channel of opaque pixels (alpha), or stored blocks after the IDAT stream (stored)
+    #[arg(long, default_value_t, conflicts_with_all = ["chunk_types", "nth", "all"])]
+    pub method: Method,
+    /// Decode the Nth (0-based) chunk of the type, or the Nth chunk overall without a type
+    #[arg(long, conflicts_with = "all")]
+    pub nth: Option<usize>,
+    /// Write the raw chunk data to this file instead of stdout; without an extension one is
+    /// picked from the sniffed content type
+    #[arg(long, short, conflicts_with = "unpack")]
+    pub output: Option<PathBuf>,
+    /// Restore a directory embedded with `encode --dir` into this directory; a batch unpacks
+    /// each file into a subdirectory named after it
+    #[arg(long, value_name = "DEST")]
+    pub unpack: Option<PathBuf>,
+    /// Decode every chunk of the type separately, labelled with its index; with --output each
+    /// is written to <stem>_<index>.<ext>
+    #[arg(long, conflicts_with = "unpack", requires = "chunk_types")]
+    pub all: bool,
+    /// Decrypt a bare payload; payloads in a pngme envelope are decrypted automatically
+    #[arg(long)]
+    pub decrypt: bool,
+    /// Password used to derive the decryption key
+    #[arg(long, group = "key_source")]
+    pub password: Option<String>,
+    /// Fetch the password from the OS keyring entry with this name, storing it there once it works
+    #[arg(long)]
+    pub keyring: Option<String>,
+    /// File holding the X25519 private key for recipient-encrypted payloads
+    #[arg(long, group = "key_source")]
+    pub identity: Option<PathBuf>,
+    /// age identity file for payloads written with `encode --age-recipient`
+    #[arg(long, group = "key_source")]
+    pub age_identity: Option<PathBuf>,
+    /// Output the payload as stored, without detecting and undoing compression
+    #[arg(long)]
+    pub no_decompress: bool,
+    /// Print an offset/hex/ASCII dump of the payload instead of the payload itself
+    #[arg(long, conflicts_with_all = ["output", "unpack"])]
+    pub hexdump: bool,
+    /// Only dump the first N bytes
+    #[arg(long, value_name = "N", requires = "hexdump")]
+    pub head: Option<usize>,
+    /// Print each payload with this template, e.g. '{index}: {text}'. Placeholders: type, index
+    /// (empty for a payload joined from several chunks), data (base64) and text (empty unless
+    /// the payload is UTF-8)
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["output", "unpack", "hexdump"])]
+    pub format_template: Option<Template>,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RemoveArgs {
+    pub path: PathBuf,
+    /// Chunk types, globs (ru??) or /regexes/, all removed in a single pass
+    #[arg(required_unless_present = "nth")]
+    pub chunk_types: Vec<ChunkPattern>,
+    /// Remove every chunk of each type instead of only the first
+    #[arg(long, conflicts_with = "nth", requires = "chunk_types")]
+    pub all: bool,
+    /// Remove the Nth (0-based) chunk of the type, or the Nth chunk overall without a type
+    #[arg(long)]
+    pub nth: Option<usize>,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PrintArgs {
+    pub path: PathBuf,
+    /// Maximum number of palette entries to show per chunk
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Show chunk data as a hex dump instead of decoding it
+    #[arg(long)]
+    pub hex: bool,
+    /// Show undecoded chunk data in full instead of truncating it
+    #[arg(long)]
+    pub full: bool,
+    /// Only dump the first N bytes of each chunk
+    #[arg(long, value_name = "N", requires = "hex")]
+    pub head: Option<usize>,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct InfoArgs {
+    pub path: PathBuf,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ListArgs {
+    pub path: PathBuf,
+    /// Only list chunks matching this type, glob (ru??) or /regex/
+    pub chunk_type: Option<ChunkPattern>,
+    /// Maximum number of palette entries to show per chunk
+    #[arg(long, default_value_t = 8)]
+    pub limit: usize,
+    /// Print each chunk with this template instead of a table, e.g. '{offset}\t{type}\t{length}'.
+    /// Placeholders: index, offset, type, length, crc, critical, public, safe_to_copy, entropy,
+    /// known_as, details; {name:x} prints hex and {name:.N} rounds to N decimals
+    #[arg(long, value_name = "TEMPLATE")]
+    pub format_template: Option<Template>,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExtractAllArgs {
+    pub path: PathBuf,
+    /// Directory to write the chunk files and manifest.json into; a batch writes each file's
+    /// into a subdirectory named after it
+    pub outdir: PathBuf,
+    /// Also extract standard PNG chunks such as IHDR and IDAT
+    #[arg(long)]
+    pub include_standard: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AssembleArgs {
+    /// Directory written by extract-all
+    pub dir: PathBuf,
+    /// Path of the rebuilt PNG
+    #[arg(long, short)]
+    pub output: PathBuf,
+    /// Take standard chunks from this PNG and splice the dumped chunks in at their indices
+    #[arg(long)]
+    pub base: Option<PathBuf>,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct VerifyArgs {
+    pub path: PathBuf,
+    /// Also check the siGn signature of this chunk type
+    #[arg(requires = "pubkey")]
+    pub chunk_type: Option<String>,
+    /// Ed25519 public key of the signer
+    #[arg(long, requires = "chunk_type")]
+    pub pubkey: Option<VerifyingKey>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SalvageArgs {
+    pub path: PathBuf,
+    /// Write the complete chunks, plus an IEND if the file had none, to this path
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RepairArgs {
+    pub path: PathBuf,
+    /// Write the repaired PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LintArgs {
+    pub path: PathBuf,
+    /// Fail on findings of this rule (repeatable)
+    #[arg(long, value_name = "RULE")]
+    pub deny: Vec<Rule>,
+    /// Ignore findings of this rule (repeatable)
+    #[arg(long, value_name = "RULE")]
+    pub allow: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CheckArgs {
+    /// TOML file listing the allowed chunk types and size limits
+    #[arg(long)]
+    pub policy: PathBuf,
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CheckHiddenArgs {
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+    /// Also flag these standard chunk types, globs (t?Xt) or /regexes/ (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub deny: Vec<ChunkPattern>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ScanArgs {
+    /// Directory, glob or file to search; directories are always walked recursively
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+    /// Flag ancillary chunks with payloads larger than this many bytes
+    #[arg(long, value_name = "BYTES", default_value_t = lint::MAX_ANCILLARY_LEN)]
+    pub max_chunk_size: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AnalyzeArgs {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CapacityArgs {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FilterArgs {
+    /// Strip the chunks from the PNG on stdin, for `git add`
+    #[arg(long, conflicts_with = "smudge", required_unless_present = "smudge")]
+    pub clean: bool,
+    /// Put previously stripped chunks back into the PNG on stdin, for checkout
+    #[arg(long)]
+    pub smudge: bool,
+    /// Chunk types, globs (ru??) or /regexes/ to strip when cleaning
+    #[arg(required_unless_present = "smudge")]
+    pub chunk_types: Vec<ChunkPattern>,
+    /// Directory where stripped chunks are kept between clean and smudge
+    #[arg(long, default_value = ".git/pngme-filter")]
+    pub store: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct StripArgs {
+    pub path: PathBuf,
+    /// Keep ancillary chunks of this type, glob (t?Xt) or /regex/ (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub keep: Vec<ChunkPattern>,
+    /// Write the stripped PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OptimizeArgs {
+    pub path: PathBuf,
+    /// Keep ancillary chunks of this type, glob (t?Xt) or /regex/ (repeatable); chunks that
+    /// affect rendering are always kept
+    #[arg(long, value_name = "PATTERN")]
+    pub keep: Vec<ChunkPattern>,
+    /// Narrow the colour type or bit depth when no sample changes
+    #[arg(long)]
+    pub reduce: bool,
+    /// Scanline filter: none, sub, up, average, paeth or adaptive
+    #[arg(long, default_value_t)]
+    pub filter: Filter,
+    /// Re-encode the image data Adam7-interlaced
+    #[arg(long)]
+    pub interlace: bool,
+    /// Write the optimized PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DeinterlaceArgs {
+    pub path: PathBuf,
+    /// Scanline filter: none, sub, up, average, paeth or adaptive
+    #[arg(long, default_value_t)]
+    pub filter: Filter,
+    /// Write the de-interlaced PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AnonymizeArgs {
+    pub path: PathBuf,
+    /// Write the anonymized PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReorderArgs {
+    pub path: PathBuf,
+    /// Write the reordered PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DedupeArgs {
+    pub path: PathBuf,
+    /// Write the deduplicated PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CloneMetadataArgs {
+    /// PNG to copy the metadata from
+    pub source: PathBuf,
+    /// PNG to copy the metadata into
+    pub destination: PathBuf,
+    /// Copy these chunk types, globs (t?Xt) or /regexes/ instead of text, iCCP, pHYs and eXIf
+    #[arg(long = "chunk", value_name = "PATTERN")]
+    pub chunk_types: Vec<ChunkPattern>,
+    /// Copy chunks that are not safe to copy even when the images differ
+    #[arg(long)]
+    pub force: bool,
+    /// Write the result to this path, leaving the destination untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the destination PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    /// Also show which payload bytes of modified chunks changed
+    #[arg(long)]
+    pub bytes: bool,
+    /// List unchanged chunks too
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct MergeArgs {
+    /// PNG whose image data and critical chunks are kept
+    pub base: PathBuf,
+    /// PNGs whose ancillary chunks are merged in, in order
+    #[arg(required = true)]
+    pub others: Vec<PathBuf>,
+    /// Which chunk wins when both have one for the same slot: base or other
+    #[arg(long, default_value = "base")]
+    pub prefer: Prefer,
+    /// Write the merged PNG to this path, leaving the base untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the base PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CopyArgs {
+    /// PNG to copy the chunk from
+    pub source: PathBuf,
+    /// PNG to copy the chunk into
+    pub destination: PathBuf,
+    pub chunk_type: String,
+    /// Copy the Nth (0-based) chunk of the type instead of the first
+    #[arg(long, default_value_t = 0)]
+    pub nth: usize,
+    #[command(flatten)]
+    pub position: PositionArgs,
+    /// Remove the destination's chunks of the same type first
+    #[arg(long)]
+    pub replace: bool,
+    /// Write the result to this path, leaving the destination untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the destination PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct StripTrailingArgs {
+    pub path: PathBuf,
+    /// Write the stripped PNG to this path, leaving the input untouched
+    #[arg(long, short, conflicts_with = "in_place")]
+    pub output: Option<PathBuf>,
+    /// Rewrite the input PNG in place
+    #[arg(long)]
+    pub in_place: bool,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FsArgs {
+    #[command(subcommand)]
+    pub command: FsCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum FsCommand {
+    /// Store a file in the PNG under a name
+    Add {
+        path: PathBuf,
+        name: String,
+        /// File to store (defaults to <name>)
+        file: Option<PathBuf>,
+        /// Replace an existing file with the same name
+        #[arg(long)]
+        force: bool,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+    /// Write a stored file out
+    Extract {
+        path: PathBuf,
+        name: String,
+        /// Destination (defaults to <name>, `-` for stdout)
+        file: Option<PathBuf>,
+    },
+    /// List stored files
+    Ls { path: PathBuf },
+    /// Delete a stored file
+    Rm {
+        path: PathBuf,
+        name: String,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TextArgs {
+    #[command(subcommand)]
+    pub command: TextCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum TextCommand {
+    /// Set a text keyword, replacing any existing value
+    Set {
+        path: PathBuf,
+        keyword: Keyword,
+        value: String,
+        /// Store the value deflate-compressed (zTXt, or iTXt with --lang)
+        #[arg(long)]
+        compressed: bool,
+        /// Store UTF-8 text in an iTXt chunk tagged with this language (e.g. en-GB)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Keyword translated into the --lang language
+        #[arg(long, requires = "lang")]
+        translated_keyword: Option<String>,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+    /// Print the value of a text keyword
+    Get { path: PathBuf, keyword: String },
+    /// List all text keywords and values
+    List { path: PathBuf },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExifArgs {
+    #[command(subcommand)]
+    pub command: ExifCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ExifCommand {
+    /// Write the eXIf chunk to a sidecar file
+    Extract {
+        path: PathBuf,
+        /// Destination file (defaults to stdout)
+        file: Option<PathBuf>,
+    },
+    /// Store EXIF data from a file in the eXIf chunk, replacing any existing one
+    Inject {
+        path: PathBuf,
+        file: PathBuf,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+    /// Print camera, timestamp and GPS tags
+    Show {
+        path: PathBuf,
+        /// Print every tag instead of the common ones
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct XmpArgs {
+    #[command(subcommand)]
+    pub command: XmpCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum XmpCommand {
+    /// Print the XMP packet
+    Get {
+        path: PathBuf,
+        /// Indent the XML
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Store the XMP packet from a file, replacing any existing one
+    Set {
+        path: PathBuf,
+        file: PathBuf,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+    /// Remove the XMP packet
+    Strip {
+        path: PathBuf,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TimeArgs {
+    #[command(subcommand)]
+    pub command: TimeCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum TimeCommand {
+    /// Print the last-modification time from the tIME chunk
+    Get { path: PathBuf },
+    /// Write the tIME chunk
+    Set {
+        path: PathBuf,
+        /// UTC time as YYYY-MM-DDTHH:MM:SS, or now
+        #[arg(default_value = "now")]
+        time: Timestamp,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DpiArgs {
+    #[command(subcommand)]
+    pub command: DpiCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum DpiCommand {
+    /// Print the resolution from the pHYs chunk
+    Get { path: PathBuf },
+    /// Write the resolution to the pHYs chunk
+    Set {
+        path: PathBuf,
+        dpi: f64,
+        /// Vertical resolution, if it differs from the horizontal one
+        #[arg(long)]
+        y_dpi: Option<f64>,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OffsetArgs {
+    #[command(subcommand)]
+    pub command: OffsetCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum OffsetCommand {
+    /// Print the image offset from the oFFs chunk
+    Get { path: PathBuf },
+    /// Write the image offset to the oFFs chunk
+    Set {
+        path: PathBuf,
+        #[arg(allow_negative_numbers = true)]
+        x: i32,
+        #[arg(allow_negative_numbers = true)]
+        y: i32,
+        /// The offset is in micrometres rather than pixels
+        #[arg(long)]
+        micrometres: bool,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ScaleArgs {
+    #[command(subcommand)]
+    pub command: ScaleCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ScaleCommand {
+    /// Print the pixel size from the sCAL chunk
+    Get { path: PathBuf },
+    /// Write the pixel size to the sCAL chunk
+    Set {
+        path: PathBuf,
+        /// Pixel width in metres (or radians)
+        width: f64,
+        /// Pixel height, if it differs from the width
+        height: Option<f64>,
+        /// The size is an angle in radians rather than a length in metres
+        #[arg(long)]
+        radians: bool,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CalibrationArgs {
+    #[command(subcommand)]
+    pub command: CalibrationCommand,
+    #[command(flatten)]
+    pub touch: TouchArgs,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CalibrationCommand {
+    /// Print the calibration from the pCAL chunk
+    Get { path: PathBuf },
+    /// Write the pCAL chunk
+    Set {
+        path: PathBuf,
+        /// Name of the calibration, e.g. temperature
+        name: Keyword,
+        /// Stored sample value that maps to the first parameter
+        #[arg(allow_negative_numbers = true)]
+        original_zero: i32,
+        /// Stored sample value that maps to the last value of the range
+        #[arg(allow_negative_numbers = true)]
+        original_max: i32,
+        /// Equation parameters p0, p1, ...
+        #[arg(required = true, allow_negative_numbers = true)]
+        parameters: Vec<f64>,
+        /// linear, exponential, arbitrary-exponential or hyperbolic
+        #[arg(long, default_value = "linear")]
+        equation: Equation,
+        /// Physical unit of the calibrated values
+        #[arg(long, default_value = "")]
+        unit: String,
+        /// Write the modified PNG to this path, leaving the input untouched
+        #[arg(long, short, conflicts_with = "in_place")]
+        output: Option<PathBuf>,
+        /// Rewrite the input PNG in place
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DsigArgs {
+    #[command(subcommand)]
+    pub command: DsigCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum DsigCommand {
+    /// List the dSIG chunk pairs and what they cover
+    List { path: PathBuf },
+    /// Write the signature from a dSIG pair to a file
+    Extract {
+        path: PathBuf,
+        /// Destination file (defaults to stdout)
+        file: Option<PathBuf>,
+        /// Which pair to extract, counting from 0
+        #[arg(long, default_value_t = 0)]
+        nth: usize,
+    },
+    /// Check every dSIG pair against an Ed25519 public key
+    Verify {
+        path: PathBuf,
+        /// Ed25519 public key of the signer
+        #[arg(long)]
+        pubkey: VerifyingKey,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct KeygenArgs {
+    /// Key directory (defaults to $PNGME_KEY_DIR or ~/.pngme/keys)
+    #[arg(long, global = true)]
+    pub dir: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: KeygenCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum KeygenCommand {
+    /// Generate a signing and an encryption keypair
+    New {
+        name: String,
+        /// Overwrite an existing keypair with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// List keypairs in the key directory
+    List,
+    /// Print the public keys of a keypair
+    Export { name: String },
+}
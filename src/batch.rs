@@ -0,0 +1,262 @@
+use std::path::{Component, Path, PathBuf};
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+use crate::error::{PngmeError, Result};
+
+const GLOB_CHARS: [char; 4] = ['*', '?', '[', '{'];
+
+// Gitignore-style file listing paths every batch command skips, read from the
+// walked directory, its subdirectories and its parents.
+pub const IGNORE_FILE: &str = ".pngmeignore";
+
+// Whether a path argument names several files: a directory or a glob such as
+// `assets/**/*.png` or `{a,b}.png`, quoted so the shell leaves it alone.
+pub fn is_batch_input(path: &Path) -> bool {
+    path.is_dir() || path.to_string_lossy().contains(GLOB_CHARS)
+}
+
+// How directory and glob inputs are walked. Include and exclude globs are
+// matched against paths relative to the input, so `*.png` matches at any
+// depth and `vendor/**` skips a whole subtree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Walk {
+    pub recursive: bool,
+    pub max_depth: Option<usize>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Walk {
+    // The walkdir depth for a directory input: its own files are at depth 1.
+    fn dir_depth(&self) -> Option<usize> {
+        match (self.recursive, self.max_depth) {
+            (false, _) => Some(1),
+            (true, Some(depth)) => Some(depth + 1),
+            (true, None) => None,
+        }
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|error| PngmeError::Usage(error.to_string()))?);
+    }
+    builder
+        .build()
+        .map_err(|error| PngmeError::Usage(error.to_string()))
+}
+
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+// The files a batch input stands for, sorted so runs are repeatable: the PNGs
+// inside a directory, or every file matching a glob, after the include and
+// exclude filters.
+pub fn expand(input: &Path, options: &Walk) -> Result<Vec<PathBuf>> {
+    let (root, files) = if input.is_dir() {
+        let files = walk(input, options.dir_depth())?
+            .into_iter()
+            .filter(|path| is_png(path))
+            .collect();
+        (input.to_path_buf(), files)
+    } else {
+        expand_glob(input)?
+    };
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| {
+            let relative = path.strip_prefix(&root).unwrap_or(path);
+            (options.include.is_empty() || include.is_match(relative))
+                && !exclude.is_match(relative)
+        })
+        .collect();
+    if files.is_empty() {
+        return Err(PngmeError::Usage(format!(
+            "no files match {}",
+            input.display()
+        )));
+    }
+    Ok(files)
+}
+
+fn expand_glob(pattern: &Path) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let glob = GlobBuilder::new(&pattern.to_string_lossy())
+        .literal_separator(true)
+        .build()
+        .map_err(|error| PngmeError::Usage(error.to_string()))?
+        .compile_matcher();
+    // Walk from the last directory before the first wildcard, only as deep as
+    // the pattern reaches unless it has a `**`.
+    let mut root = PathBuf::new();
+    let mut depth = 0;
+    let mut recursive = false;
+    for component in pattern.components() {
+        let text = component.as_os_str().to_string_lossy();
+        if depth == 0 && !text.contains(GLOB_CHARS) {
+            root.push(component);
+            continue;
+        }
+        recursive |= text.contains("**");
+        depth += 1;
+    }
+    let prefix_is_current = root.as_os_str().is_empty();
+    if prefix_is_current {
+        root.push(Component::CurDir);
+    }
+    let files = walk(&root, (!recursive).then_some(depth))?
+        .into_iter()
+        .map(|path| {
+            if prefix_is_current {
+                path.strip_prefix(".")
+                    .map_or(path.clone(), Path::to_path_buf)
+            } else {
+                path
+            }
+        })
+        .filter(|path| glob.is_match(path))
+        .collect();
+    if prefix_is_current {
+        root = PathBuf::new();
+    }
+    Ok((root, files))
+}
+
+fn walk(root: &Path, max_depth: Option<usize>) -> Result<Vec<PathBuf>> {
+    let walker = WalkBuilder::new(root)
+        .standard_filters(false)
+        .parents(true)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .max_depth(max_depth)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(std::io::Error::other)?;
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["a.png", "b.PNG", "notes.txt", "sub/c.png", "sub/deep/d.png"] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+        dir
+    }
+
+    fn names(dir: &Path, files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.strip_prefix(dir).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_directory_lists_its_pngs() {
+        let dir = tree();
+        assert!(is_batch_input(dir.path()));
+        assert_eq!(
+            names(dir.path(), expand(dir.path(), &Walk::default()).unwrap()),
+            ["a.png", "b.PNG"]
+        );
+    }
+
+    #[test]
+    fn test_globs() {
+        let dir = tree();
+        let glob = |pattern: &str| {
+            let pattern = dir.path().join(pattern);
+            assert!(is_batch_input(&pattern));
+            names(dir.path(), expand(&pattern, &Walk::default()).unwrap())
+        };
+        assert_eq!(glob("*.png"), ["a.png"]);
+        assert_eq!(glob("*/*.png"), ["sub/c.png"]);
+        assert_eq!(glob("**/*.png"), ["a.png", "sub/c.png", "sub/deep/d.png"]);
+        assert_eq!(glob("{a.png,notes.txt}"), ["a.png", "notes.txt"]);
+        assert!(expand(&dir.path().join("*.gif"), &Walk::default()).is_err());
+    }
+
+    #[test]
+    fn test_plain_file_is_not_batch_input() {
+        assert!(!is_batch_input(Path::new("image.png")));
+    }
+
+    #[test]
+    fn test_recursive_walk_with_filters() {
+        let dir = tree();
+        let walk = |options: Walk| names(dir.path(), expand(dir.path(), &options).unwrap());
+        let recursive = Walk {
+            recursive: true,
+            ..Walk::default()
+        };
+        assert_eq!(
+            walk(recursive.clone()),
+            ["a.png", "b.PNG", "sub/c.png", "sub/deep/d.png"]
+        );
+        assert_eq!(
+            walk(Walk {
+                max_depth: Some(1),
+                ..recursive.clone()
+            }),
+            ["a.png", "b.PNG", "sub/c.png"]
+        );
+        assert_eq!(
+            walk(Walk {
+                include: vec!["*.png".into()],
+                exclude: vec!["sub/deep/**".into()],
+                ..recursive.clone()
+            }),
+            ["a.png", "sub/c.png"]
+        );
+        assert!(expand(
+            dir.path(),
+            &Walk {
+                include: vec!["*.gif".into()],
+                ..recursive
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pngmeignore() {
+        let dir = tree();
+        fs::write(dir.path().join(IGNORE_FILE), "deep/\nb.*\n").unwrap();
+        fs::write(dir.path().join("sub").join(IGNORE_FILE), "!../b.PNG\n").unwrap();
+        let recursive = Walk {
+            recursive: true,
+            ..Walk::default()
+        };
+        assert_eq!(
+            names(dir.path(), expand(dir.path(), &recursive).unwrap()),
+            ["a.png", "sub/c.png"]
+        );
+        assert_eq!(
+            names(
+                dir.path(),
+                expand(&dir.path().join("**/*.png"), &Walk::default()).unwrap()
+            ),
+            ["a.png", "sub/c.png"]
+        );
+    }
+}
@@ -1,20 +1,210 @@
-#![allow(unused_variables, dead_code)]
-
 use crate::chunk_type::ChunkType;
+use crate::entropy;
+use crate::error::{PngmeError, Result};
+
+pub const CRC_32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+pub const PREVIEW_LEN: usize = 64;
 
-struct Chunk {
+#[derive(Debug, Clone)]
+pub struct Chunk {
     length: u32,
     r#type: ChunkType,
     crc: u32,
     data: Vec<u8>,
+    offset: Option<u64>,
+    stored_crc: Option<u32>,
+}
+
+// Where a chunk was read from says nothing about its contents, so two chunks
+// with the same bytes compare equal wherever they came from.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.r#type == other.r#type && self.data == other.data
+    }
+}
+
+impl Eq for Chunk {}
+
+impl Chunk {
+    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Self {
+        let crc = calculate_crc(&chunk_type, &data);
+        Self {
+            length: data.len() as u32,
+            r#type: chunk_type,
+            crc,
+            data,
+            offset: None,
+            stored_crc: None,
+        }
+    }
+
+    pub(crate) fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.r#type
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    // The CRC found in the file, when it did not match the data and the chunk
+    // was read with `try_from_ignoring_crc`. `crc()` is always the correct one.
+    pub fn stored_crc(&self) -> Option<u32> {
+        self.stored_crc
+    }
+
+    pub fn entropy(&self) -> f64 {
+        entropy::shannon(&self.data)
+    }
+
+    pub fn data_as_string(&self) -> Result<String> {
+        Ok(String::from_utf8(self.data.clone())?)
+    }
+
+    // Quoted text when the data is UTF-8, space-separated hex otherwise, cut
+    // off after `limit` characters or bytes with an ellipsis.
+    pub fn preview(&self, limit: Option<usize>) -> String {
+        let limit = limit.unwrap_or(usize::MAX);
+        match std::str::from_utf8(&self.data) {
+            Ok(text) => {
+                let shown: String = text.chars().take(limit).collect();
+                let ellipsis = if shown.len() < text.len() { "…" } else { "" };
+                format!("{:?}{}", shown, ellipsis)
+            }
+            Err(_) => {
+                let shown = &self.data[..limit.min(self.data.len())];
+                let hex: Vec<String> = shown.iter().map(|b| format!("{:02x}", b)).collect();
+                let ellipsis = if shown.len() < self.data.len() {
+                    " …"
+                } else {
+                    ""
+                };
+                format!("[{}{}]", hex.join(" "), ellipsis)
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.length
+            .to_be_bytes()
+            .iter()
+            .chain(self.r#type.bytes().iter())
+            .chain(self.data.iter())
+            .chain(self.crc.to_be_bytes().iter())
+            .copied()
+            .collect()
+    }
+
+    pub fn try_from_ignoring_crc(value: &[u8]) -> std::result::Result<Self, PngmeError> {
+        let (mut chunk, crc) = Self::read_unchecked(value)?;
+        if chunk.crc != crc {
+            chunk.stored_crc = Some(crc);
+        }
+        Ok(chunk)
+    }
+
+    // Parses a chunk without checking its CRC, returning the CRC stored in
+    // the file alongside it. The chunk itself always carries the correct CRC.
+    pub(crate) fn read_unchecked(value: &[u8]) -> std::result::Result<(Chunk, u32), PngmeError> {
+        if value.len() < 12 {
+            return Err(PngmeError::Truncated(format!(
+                "expected at least 12 bytes, got {}",
+                value.len()
+            )));
+        }
+        let length = u32::from_be_bytes(value[0..4].try_into().unwrap());
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&value[4..8]).unwrap())?;
+        let data_end = 8 + length as usize;
+        if value.len() < data_end + 4 {
+            return Err(PngmeError::Truncated(format!(
+                "declared length {} but only {} bytes remain",
+                length,
+                value.len() - 8
+            )));
+        }
+        let data = value[8..data_end].to_vec();
+        let crc = u32::from_be_bytes(value[data_end..data_end + 4].try_into().unwrap());
+        Ok((Self::new(chunk_type, data), crc))
+    }
+}
+
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn calculate_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+    let mut digest = CRC_32.digest();
+    digest.update(&chunk_type.bytes());
+    digest.update(data);
+    digest.finalize()
+}
+
+impl TryFrom<&[u8]> for Chunk {
+    type Error = PngmeError;
+
+    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+        let (chunk, crc) = Chunk::read_unchecked(value)?;
+        if chunk.crc != crc {
+            return Err(PngmeError::CrcMismatch {
+                expected: chunk.crc,
+                found: crc,
+            });
+        }
+        Ok(chunk)
+    }
+}
+
+impl std::fmt::Display for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The alternate form `{:#}` shows the data in full.
+        let limit = (!f.alternate()).then_some(PREVIEW_LEN);
+        write!(
+            f,
+            "{} ({}, crc {:#010x}): {}",
+            self.r#type,
+            human_size(self.length.into()),
+            self.crc,
+            self.preview(limit)
+        )
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
     use std::str::FromStr;
 
+    pub(crate) fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
     fn testing_chunk() -> Chunk {
         let data_length: u32 = 42;
         let chunk_type = "RuSt".as_bytes();
@@ -138,4 +328,83 @@ mod tests {
 
         let _chunk_string = format!("{}", chunk);
     }
+
+    #[test]
+    fn test_offset_ignored_by_eq() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.offset(), None);
+        let placed = chunk.clone().with_offset(33);
+        assert_eq!(placed.offset(), Some(33));
+        assert_eq!(placed, chunk);
+    }
+
+    #[test]
+    fn test_chunk_display() {
+        let chunk = testing_chunk();
+        assert_eq!(
+            chunk.to_string(),
+            "RuSt (42 B, crc 0xabd1d84e): \"This is where your secret message will be!\""
+        );
+        let binary = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0xff; 100]);
+        let short = binary.to_string();
+        assert!(short.ends_with("ff ff …]"));
+        assert_eq!(short.matches("ff").count(), PREVIEW_LEN);
+        assert!(format!("{:#}", binary).ends_with("ff ff]"));
+    }
+
+    #[test]
+    fn test_preview_truncates_text() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("RuSt").unwrap(),
+            b"hello world".to_vec(),
+        );
+        assert_eq!(chunk.preview(Some(5)), "\"hello\"…");
+        assert_eq!(chunk.preview(None), "\"hello world\"");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_chunk_errors_are_matchable() {
+        let mut bytes = testing_chunk().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(
+            Chunk::try_from(bytes.as_ref()),
+            Err(PngmeError::CrcMismatch {
+                expected: 2882656334,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Chunk::try_from(&bytes[..20]),
+            Err(PngmeError::Truncated(_))
+        ));
+        bytes[4] = b'1';
+        assert!(matches!(
+            Chunk::try_from(bytes.as_ref()),
+            Err(PngmeError::InvalidChunkType(_))
+        ));
+    }
+
+    #[test]
+    fn test_try_from_ignoring_crc() {
+        let mut bytes = testing_chunk().as_bytes();
+        assert_eq!(
+            Chunk::try_from_ignoring_crc(&bytes).unwrap().stored_crc(),
+            None
+        );
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let chunk = Chunk::try_from_ignoring_crc(&bytes).unwrap();
+        assert_eq!(chunk, testing_chunk());
+        assert_eq!(chunk.crc(), 2882656334);
+        assert_eq!(chunk.stored_crc(), Some(2882656334 ^ 0xff));
+    }
 }
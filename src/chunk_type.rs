@@ -1,4 +1,12 @@
-#[derive(PartialEq, Eq, Debug)]
+use crate::error::PngmeError;
+
+pub const STANDARD_TYPES: [&[u8; 4]; 25] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"acTL", b"bKGD", b"cHRM", b"cICP", b"cLLI", b"eXIf",
+    b"fcTL", b"fdAT", b"gAMA", b"hIST", b"iCCP", b"iTXt", b"mDCV", b"pHYs", b"sBIT", b"sPLT",
+    b"sRGB", b"tEXt", b"tIME", b"tRNS", b"zTXt",
+];
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct ChunkType([u8; 4]);
 
 impl ChunkType {
@@ -7,7 +15,7 @@ impl ChunkType {
     }
 
     pub fn is_valid(&self) -> bool {
-        if let false = self.is_reserved_bit_valid() {
+        if !self.is_reserved_bit_valid() {
             return false;
         }
         self.0
@@ -30,6 +38,10 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         !is_upper(self.0[3])
     }
+
+    pub fn is_standard(&self) -> bool {
+        STANDARD_TYPES.contains(&&self.0)
+    }
 }
 
 fn is_upper(byte: u8) -> bool {
@@ -41,27 +53,32 @@ fn is_alpha(byte: u8) -> bool {
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = ();
+    type Error = PngmeError;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        Ok(Self(value))
+        if value.into_iter().all(is_alpha) {
+            Ok(Self(value))
+        } else {
+            Err(PngmeError::InvalidChunkType(format!(
+                "expected four ASCII letters, found \"{}\"",
+                value.escape_ascii()
+            )))
+        }
     }
 }
 
 impl std::str::FromStr for ChunkType {
-    type Err = ();
+    type Err = PngmeError;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let bytes: Result<[u8; 4], _> = string.bytes().collect::<Vec<u8>>().try_into();
         match bytes {
-            Ok(b) => {
-                if b.into_iter().all(is_alpha) {
-                    Ok(Self(b))
-                } else {
-                    Err(())
-                }
-            }
-            _ => Err(()),
+            Ok(b) => Self::try_from(b),
+            _ => Err(PngmeError::InvalidChunkType(format!(
+                "expected 4 bytes, found {} in {:?}",
+                string.len(),
+                string
+            ))),
         }
     }
 }
@@ -80,7 +97,7 @@ mod tests {
 
     #[test]
     fn test_is_upper() {
-        let tests = [(0b0010_0100 as u8, false), (0b0000_0100 as u8, true)];
+        let tests = [(0b0010_0100_u8, false), (0b0000_0100_u8, true)];
         for (byte, exp) in tests.iter() {
             assert_eq!(*exp, is_upper(*byte));
         }
@@ -177,4 +194,11 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    #[test]
+    pub fn test_chunk_type_is_standard() {
+        assert!(ChunkType::from_str("IHDR").unwrap().is_standard());
+        assert!(ChunkType::from_str("tEXt").unwrap().is_standard());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_standard());
+    }
 }
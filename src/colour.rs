@@ -0,0 +1,250 @@
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{self, ColourType, ImageHeader};
+use crate::png::Png;
+
+pub const GAMMA_CHUNK_TYPE: &str = "gAMA";
+pub const CHROMATICITIES_CHUNK_TYPE: &str = "cHRM";
+pub const SRGB_CHUNK_TYPE: &str = "sRGB";
+pub const SIGNIFICANT_BITS_CHUNK_TYPE: &str = "sBIT";
+const SCALE: f64 = 100_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gamma(pub f64);
+
+impl Gamma {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self(
+            f64::from(read_u32s::<1>(GAMMA_CHUNK_TYPE, bytes)?[0]) / SCALE,
+        ))
+    }
+}
+
+impl std::fmt::Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gamma {:.5}", self.0)?;
+        if self.0 > 0.0 {
+            write!(f, " (display exponent {:.2})", 1.0 / self.0)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticities {
+    pub white: (f64, f64),
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+}
+
+impl Chromaticities {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let values =
+            read_u32s::<8>(CHROMATICITIES_CHUNK_TYPE, bytes)?.map(|v| f64::from(v) / SCALE);
+        Ok(Self {
+            white: (values[0], values[1]),
+            red: (values[2], values[3]),
+            green: (values[4], values[5]),
+            blue: (values[6], values[7]),
+        })
+    }
+}
+
+impl std::fmt::Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = [
+            ("white", self.white),
+            ("red", self.red),
+            ("green", self.green),
+            ("blue", self.blue),
+        ];
+        for (i, (name, (x, y))) in points.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({:.5}, {:.5})", name, x, y)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl RenderingIntent {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes {
+            [0] => Ok(Self::Perceptual),
+            [1] => Ok(Self::RelativeColorimetric),
+            [2] => Ok(Self::Saturation),
+            [3] => Ok(Self::AbsoluteColorimetric),
+            [intent] => Err(PngmeError::Malformed(format!(
+                "unknown rendering intent {}",
+                intent
+            ))),
+            _ => Err(PngmeError::Malformed(format!(
+                "sRGB chunk should be 1 byte, got {}",
+                bytes.len()
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for RenderingIntent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Perceptual => "perceptual",
+            Self::RelativeColorimetric => "relative colorimetric",
+            Self::Saturation => "saturation",
+            Self::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "sRGB, {} rendering intent", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignificantBits {
+    Grey(u8),
+    Rgb([u8; 3]),
+    GreyAlpha([u8; 2]),
+    Rgba([u8; 4]),
+}
+
+impl SignificantBits {
+    pub fn from_bytes(bytes: &[u8], header: &ImageHeader) -> Result<Self> {
+        let max = match header.colour_type {
+            ColourType::Indexed => 8,
+            _ => header.bit_depth,
+        };
+        if let Some(bits) = bytes.iter().find(|&&bits| bits == 0 || bits > max) {
+            return Err(PngmeError::Malformed(format!(
+                "sBIT value {} is outside 1 to {}",
+                bits, max
+            )));
+        }
+        let bits = match (header.colour_type, bytes) {
+            (ColourType::Greyscale, &[grey]) => Self::Grey(grey),
+            (ColourType::Truecolour | ColourType::Indexed, &[r, g, b]) => Self::Rgb([r, g, b]),
+            (ColourType::GreyscaleAlpha, &[grey, a]) => Self::GreyAlpha([grey, a]),
+            (ColourType::TruecolourAlpha, &[r, g, b, a]) => Self::Rgba([r, g, b, a]),
+            (colour_type, _) => {
+                return Err(PngmeError::Malformed(format!(
+                    "sBIT chunk of {} bytes does not match {} images",
+                    bytes.len(),
+                    colour_type
+                )))
+            }
+        };
+        Ok(bits)
+    }
+}
+
+impl std::fmt::Display for SignificantBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Grey(grey) => write!(f, "significant bits: grey {}", grey),
+            Self::Rgb([r, g, b]) => {
+                write!(f, "significant bits: red {}, green {}, blue {}", r, g, b)
+            }
+            Self::GreyAlpha([grey, a]) => {
+                write!(f, "significant bits: grey {}, alpha {}", grey, a)
+            }
+            Self::Rgba([r, g, b, a]) => write!(
+                f,
+                "significant bits: red {}, green {}, blue {}, alpha {}",
+                r, g, b, a
+            ),
+        }
+    }
+}
+
+pub fn significant_bits(png: &Png) -> Option<SignificantBits> {
+    let header = ihdr::get(png)?;
+    png.chunk_by_type(SIGNIFICANT_BITS_CHUNK_TYPE)
+        .and_then(|chunk| SignificantBits::from_bytes(chunk.data(), &header).ok())
+}
+
+fn read_u32s<const N: usize>(chunk_type: &str, bytes: &[u8]) -> Result<[u32; N]> {
+    if bytes.len() != N * 4 {
+        return Err(PngmeError::Malformed(format!(
+            "{} chunk should be {} bytes, got {}",
+            chunk_type,
+            N * 4,
+            bytes.len()
+        )));
+    }
+    let mut values = [0; N];
+    for (value, word) in values.iter_mut().zip(bytes.chunks_exact(4)) {
+        *value = u32::from_be_bytes(word.try_into().unwrap());
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma() {
+        let gamma = Gamma::from_bytes(&45455u32.to_be_bytes()).unwrap();
+        assert_eq!(gamma, Gamma(0.45455));
+        assert_eq!(gamma.to_string(), "gamma 0.45455 (display exponent 2.20)");
+        assert!(Gamma::from_bytes(&[0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_chromaticities() {
+        let bytes: Vec<u8> = [31270u32, 32900, 64000, 33000, 30000, 60000, 15000, 6000]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        let chromaticities = Chromaticities::from_bytes(&bytes).unwrap();
+        assert_eq!(chromaticities.white, (0.3127, 0.329));
+        assert_eq!(chromaticities.blue, (0.15, 0.06));
+        assert!(chromaticities
+            .to_string()
+            .starts_with("white (0.31270, 0.32900), red"));
+        assert!(Chromaticities::from_bytes(&bytes[..28]).is_err());
+    }
+
+    #[test]
+    fn test_rendering_intent() {
+        assert_eq!(
+            RenderingIntent::from_bytes(&[0]).unwrap(),
+            RenderingIntent::Perceptual
+        );
+        assert_eq!(
+            RenderingIntent::from_bytes(&[3]).unwrap().to_string(),
+            "sRGB, absolute colorimetric rendering intent"
+        );
+        assert!(RenderingIntent::from_bytes(&[4]).is_err());
+        assert!(RenderingIntent::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_significant_bits() {
+        let rgb = ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]).unwrap();
+        let bits = SignificantBits::from_bytes(&[5, 6, 5], &rgb).unwrap();
+        assert_eq!(bits, SignificantBits::Rgb([5, 6, 5]));
+        assert_eq!(bits.to_string(), "significant bits: red 5, green 6, blue 5");
+        assert!(SignificantBits::from_bytes(&[5, 6], &rgb).is_err());
+        assert!(SignificantBits::from_bytes(&[5, 6, 9], &rgb).is_err());
+        assert!(SignificantBits::from_bytes(&[0, 6, 5], &rgb).is_err());
+    }
+
+    #[test]
+    fn test_significant_bits_grey_alpha() {
+        let grey = ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 16, 4, 0, 0, 0]).unwrap();
+        assert_eq!(
+            SignificantBits::from_bytes(&[12, 16], &grey)
+                .unwrap()
+                .to_string(),
+            "significant bits: grey 12, alpha 16"
+        );
+    }
+}
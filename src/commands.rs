@@ -0,0 +1,2481 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use pngme::analyze::{self, Likelihood};
+use pngme::archive;
+use pngme::batch;
+use pngme::chunk::{human_size, PREVIEW_LEN};
+use pngme::compression;
+use pngme::crypto::{self, IdentityKey};
+use pngme::describe::{self, describe};
+use pngme::diff;
+use pngme::dsig;
+use pngme::dump;
+use pngme::envelope::{Encryption, Envelope};
+use pngme::exif;
+use pngme::extensions::{self, Calibration, Offset, Scale};
+use pngme::filter;
+use pngme::hexdump::hexdump;
+use pngme::ihdr;
+use pngme::info::{is_pngme_chunk, Summary};
+use pngme::integrity::Integrity;
+use pngme::keys::{KeyStore, PublicKeys};
+use pngme::lint;
+use pngme::optimize;
+use pngme::passphrase;
+use pngme::pattern::ChunkPattern;
+use pngme::phys::{self, PhysicalDimensions};
+use pngme::policy::{self, Policy};
+use pngme::registry;
+use pngme::repair;
+use pngme::report::{ChunkReport, DecodedPayload, ErrorReport};
+use pngme::scan;
+use pngme::signing::{self, SigningKey};
+use pngme::sniff;
+use pngme::split;
+use pngme::stego::{self, Method};
+use pngme::strip;
+use pngme::template::Template;
+use pngme::text::{self, International, TextEntry};
+use pngme::timestamp::{self, Timestamp};
+use pngme::transfer;
+use pngme::validate;
+use pngme::vfs::FileSystem;
+use pngme::xmp;
+use pngme::{Chunk, ChunkType, Png, PngmeError};
+use rayon::prelude::*;
+
+use crate::args::{
+    AnalyzeArgs, AnonymizeArgs, AssembleArgs, CalibrationArgs, CalibrationCommand, CapacityArgs,
+    CheckArgs, CheckHiddenArgs, CloneMetadataArgs, Command, CopyArgs, DecodeArgs, DedupeArgs,
+    DeinterlaceArgs, DiffArgs, DpiArgs, DpiCommand, DsigArgs, DsigCommand, EncodeArgs, ExifArgs,
+    ExifCommand, ExtractAllArgs, FilterArgs, FsArgs, FsCommand, InfoArgs, KeygenArgs,
+    KeygenCommand, LintArgs, ListArgs, MergeArgs, OffsetArgs, OffsetCommand, OptimizeArgs,
+    OutputFormat, ParseArgs, PositionArgs, PrintArgs, RemoveArgs, ReorderArgs, RepairArgs,
+    SalvageArgs, ScaleArgs, ScaleCommand, ScanArgs, StripArgs, StripTrailingArgs, TextArgs,
+    TextCommand, TimeArgs, TimeCommand, TouchArgs, VerifyArgs, XmpArgs, XmpCommand,
+};
+use crate::style::Style;
+
+// Commands also fail on serializers, templates and the terminal, so unlike the
+// library they return any error boxed.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Runs the command once per file when its path names a directory or glob,
+// carrying on past failures and summarising the results at the end.
+pub fn run(
+    mut command: Command,
+    walk: &batch::Walk,
+    threads: usize,
+    format: OutputFormat,
+    style: Style,
+) -> Result<()> {
+    if let Command::Scan(args) = command {
+        return scan(args, walk, format, style);
+    }
+    let files = match target_path(&mut command) {
+        Some(path) if !path.is_file() && batch::is_batch_input(path) => batch::expand(path, walk)?,
+        _ => return run_one(command, format, style),
+    };
+    if let Some(output) = output_file(&command) {
+        return Err(PngmeError::Usage(format!(
+            "every one of the {} files would be written to {}; batches write each file in place (--in-place) or to stdout instead",
+            files.len(),
+            output.display()
+        ))
+        .into());
+    }
+    if output_dir(&mut command).is_some() {
+        let mut names = HashSet::new();
+        if let Some(file) = files.iter().find(|file| !names.insert(file.file_name())) {
+            return Err(PngmeError::Usage(format!(
+                "{} shares its name with another file of the batch, and each file is written to an output subdirectory named after it",
+                file.display()
+            ))
+            .into());
+        }
+    }
+    // A single-threaded pool still walks the files in order, so the default
+    // output is the same as a plain loop.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|error| PngmeError::Usage(error.to_string()))?;
+    // Tables from every file go under one header with a path column, and
+    // documents into one list keyed by path, so they are collected rather than
+    // printed file by file.
+    let tabular = matches!(format, OutputFormat::Csv | OutputFormat::Tsv);
+    let structured = matches!(format, OutputFormat::Json | OutputFormat::Yaml);
+    let results: Vec<Result<Collected>> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|file| {
+                let mut command = command.clone();
+                if let Some(path) = target_path(&mut command) {
+                    *path = file.clone();
+                }
+                if let Some(dir) = output_dir(&mut command) {
+                    *dir = dir.join(file.file_name().unwrap_or_default());
+                }
+                if format == OutputFormat::Text {
+                    println!("==> {} <==", file.display());
+                }
+                let result = match tabular.then(|| chunk_rows(&command)).flatten() {
+                    Some(rows) => rows.map(|rows| Collected {
+                        rows,
+                        ..Collected::default()
+                    }),
+                    None if structured => capture_documents(|| run_one(command, format, style))
+                        .map(|documents| Collected {
+                            documents,
+                            ..Collected::default()
+                        }),
+                    None => run_one(command, format, style).map(|()| Collected::default()),
+                };
+                if let Err(error) = &result {
+                    eprintln!(
+                        "{}: error: {}",
+                        file.display(),
+                        ErrorReport::new(error.as_ref()).message
+                    );
+                }
+                result
+            })
+            .collect()
+    });
+    if tabular {
+        let rows: Vec<(PathColumn, &ChunkReport)> = files
+            .iter()
+            .zip(&results)
+            .filter_map(|(path, result)| Some((path, result.as_ref().ok()?)))
+            .flat_map(|(path, collected)| {
+                collected
+                    .rows
+                    .iter()
+                    .map(move |report| (PathColumn { path }, report))
+            })
+            .collect();
+        print_rows(&rows, format)?;
+    }
+    if structured {
+        let entries: Vec<BatchEntry> = files
+            .iter()
+            .zip(&results)
+            .map(|(path, result)| match result {
+                Ok(collected) => BatchEntry {
+                    path,
+                    output: match collected.documents.as_slice() {
+                        [] => None,
+                        [document] => Some(document.clone()),
+                        documents => Some(serde_json::Value::Array(documents.to_vec())),
+                    },
+                    error: None,
+                },
+                Err(error) => BatchEntry {
+                    path,
+                    output: None,
+                    error: Some(ErrorReport::new(error.as_ref()).message),
+                },
+            })
+            .collect();
+        print_structured(&entries, format)?;
+    }
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    eprintln!();
+    for (file, result) in files.iter().zip(&results) {
+        let status = match result {
+            Ok(_) => style.good("ok"),
+            Err(_) => style.bad("FAILED"),
+        };
+        eprintln!("{:<6}  {}", status, file.display());
+    }
+    eprintln!("{} file(s), {} failed", files.len(), failed);
+    match failed {
+        0 => Ok(()),
+        failed => Err(PngmeError::BatchFailed {
+            failed,
+            total: files.len(),
+        }
+        .into()),
+    }
+}
+
+// What one file of a batch produced for the combined output.
+#[derive(Default)]
+struct Collected {
+    rows: Vec<ChunkReport>,
+    documents: Vec<serde_json::Value>,
+}
+
+#[derive(serde::Serialize)]
+struct PathColumn<'a> {
+    path: &'a Path,
+}
+
+#[derive(serde::Serialize)]
+struct BatchEntry<'a> {
+    path: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+thread_local! {
+    // Set while a JSON or YAML batch runs one file, so `print_structured`
+    // hands its document to the batch instead of printing it. Each file runs
+    // start to finish on one pool thread.
+    static CAPTURED: RefCell<Option<Vec<serde_json::Value>>> = const { RefCell::new(None) };
+}
+
+fn capture_documents(run: impl FnOnce() -> Result<()>) -> Result<Vec<serde_json::Value>> {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+    let result = run();
+    let documents = CAPTURED.with(|captured| captured.borrow_mut().take());
+    result.map(|()| documents.unwrap_or_default())
+}
+
+// The table `print` or `list` would write for CSV or TSV output.
+fn chunk_rows(command: &Command) -> Option<Result<Vec<ChunkReport>>> {
+    match command {
+        Command::Print(args) => Some(print_reports(args)),
+        Command::List(args) if args.format_template.is_none() => Some(list_reports(args)),
+        _ => None,
+    }
+}
+
+// The PNG a command reads, or rewrites for commands that copy into one, for
+// every command that works on a single file.
+fn target_path(command: &mut Command) -> Option<&mut PathBuf> {
+    Some(match command {
+        Command::Encode(EncodeArgs { path, .. })
+        | Command::Decode(DecodeArgs { path, .. })
+        | Command::Remove(RemoveArgs { path, .. })
+        | Command::Print(PrintArgs { path, .. })
+        | Command::Info(InfoArgs { path, .. })
+        | Command::List(ListArgs { path, .. })
+        | Command::Verify(VerifyArgs { path, .. })
+        | Command::Repair(RepairArgs { path, .. })
+        | Command::Salvage(SalvageArgs { path, .. })
+        | Command::Analyze(AnalyzeArgs { path })
+        | Command::Capacity(CapacityArgs { path })
+        | Command::Lint(LintArgs { path, .. })
+        | Command::StripTrailing(StripTrailingArgs { path, .. })
+        | Command::Strip(StripArgs { path, .. })
+        | Command::Anonymize(AnonymizeArgs { path, .. })
+        | Command::Reorder(ReorderArgs { path, .. })
+        | Command::Dedupe(DedupeArgs { path, .. })
+        | Command::Optimize(OptimizeArgs { path, .. })
+        | Command::Deinterlace(DeinterlaceArgs { path, .. })
+        | Command::ExtractAll(ExtractAllArgs { path, .. }) => path,
+        Command::CloneMetadata(CloneMetadataArgs { destination, .. })
+        | Command::Copy(CopyArgs { destination, .. }) => destination,
+        Command::Merge(MergeArgs { base, .. }) => base,
+        Command::Fs(args) => match &mut args.command {
+            FsCommand::Add { path, .. }
+            | FsCommand::Extract { path, .. }
+            | FsCommand::Ls { path }
+            | FsCommand::Rm { path, .. } => path,
+        },
+        Command::Text(args) => match &mut args.command {
+            TextCommand::Set { path, .. }
+            | TextCommand::Get { path, .. }
+            | TextCommand::List { path } => path,
+        },
+        Command::Exif(args) => match &mut args.command {
+            ExifCommand::Extract { path, .. }
+            | ExifCommand::Inject { path, .. }
+            | ExifCommand::Show { path, .. } => path,
+        },
+        Command::Xmp(args) => match &mut args.command {
+            XmpCommand::Get { path, .. }
+            | XmpCommand::Set { path, .. }
+            | XmpCommand::Strip { path, .. } => path,
+        },
+        Command::Time(args) => match &mut args.command {
+            TimeCommand::Get { path } | TimeCommand::Set { path, .. } => path,
+        },
+        Command::Dpi(args) => match &mut args.command {
+            DpiCommand::Get { path } | DpiCommand::Set { path, .. } => path,
+        },
+        Command::Offset(args) => match &mut args.command {
+            OffsetCommand::Get { path } | OffsetCommand::Set { path, .. } => path,
+        },
+        Command::Scale(args) => match &mut args.command {
+            ScaleCommand::Get { path } | ScaleCommand::Set { path, .. } => path,
+        },
+        Command::Calibration(args) => match &mut args.command {
+            CalibrationCommand::Get { path } | CalibrationCommand::Set { path, .. } => path,
+        },
+        Command::Dsig(args) => match &mut args.command {
+            DsigCommand::List { path }
+            | DsigCommand::Extract { path, .. }
+            | DsigCommand::Verify { path, .. } => path,
+        },
+        _ => return None,
+    })
+}
+
+// The directory a command writes several files into. A batch gives every
+// input its own subdirectory there so their outputs cannot collide.
+fn output_dir(command: &mut Command) -> Option<&mut PathBuf> {
+    match command {
+        Command::ExtractAll(ExtractAllArgs { outdir, .. }) => Some(outdir),
+        Command::Decode(DecodeArgs { unpack, .. }) => unpack.as_mut(),
+        _ => None,
+    }
+}
+
+// The single file a command writes to, if it was given one. In a batch every
+// file would overwrite it in turn.
+fn output_file(command: &Command) -> Option<&PathBuf> {
+    match command {
+        Command::Encode(EncodeArgs { output, .. })
+        | Command::Decode(DecodeArgs { output, .. })
+        | Command::Repair(RepairArgs { output, .. })
+        | Command::Salvage(SalvageArgs { output, .. })
+        | Command::StripTrailing(StripTrailingArgs { output, .. })
+        | Command::Strip(StripArgs { output, .. })
+        | Command::Anonymize(AnonymizeArgs { output, .. })
+        | Command::Reorder(ReorderArgs { output, .. })
+        | Command::Dedupe(DedupeArgs { output, .. })
+        | Command::Optimize(OptimizeArgs { output, .. })
+        | Command::Deinterlace(DeinterlaceArgs { output, .. })
+        | Command::CloneMetadata(CloneMetadataArgs { output, .. })
+        | Command::Merge(MergeArgs { output, .. })
+        | Command::Copy(CopyArgs { output, .. }) => output.as_ref(),
+        Command::Fs(args) => match &args.command {
+            FsCommand::Add { output, .. } | FsCommand::Rm { output, .. } => output.as_ref(),
+            FsCommand::Extract { file, .. } => file.as_ref(),
+            FsCommand::Ls { .. } => None,
+        },
+        Command::Text(args) => match &args.command {
+            TextCommand::Set { output, .. } => output.as_ref(),
+            TextCommand::Get { .. } | TextCommand::List { .. } => None,
+        },
+        Command::Exif(args) => match &args.command {
+            ExifCommand::Extract { file, .. } => file.as_ref(),
+            ExifCommand::Inject { output, .. } => output.as_ref(),
+            ExifCommand::Show { .. } => None,
+        },
+        Command::Xmp(args) => match &args.command {
+            XmpCommand::Set { output, .. } | XmpCommand::Strip { output, .. } => output.as_ref(),
+            XmpCommand::Get { .. } => None,
+        },
+        Command::Time(args) => match &args.command {
+            TimeCommand::Set { output, .. } => output.as_ref(),
+            TimeCommand::Get { .. } => None,
+        },
+        Command::Dpi(args) => match &args.command {
+            DpiCommand::Set { output, .. } => output.as_ref(),
+            DpiCommand::Get { .. } => None,
+        },
+        Command::Offset(args) => match &args.command {
+            OffsetCommand::Set { output, .. } => output.as_ref(),
+            OffsetCommand::Get { .. } => None,
+        },
+        Command::Scale(args) => match &args.command {
+            ScaleCommand::Set { output, .. } => output.as_ref(),
+            ScaleCommand::Get { .. } => None,
+        },
+        Command::Calibration(args) => match &args.command {
+            CalibrationCommand::Set { output, .. } => output.as_ref(),
+            CalibrationCommand::Get { .. } => None,
+        },
+        Command::Dsig(args) => match &args.command {
+            DsigCommand::Extract { file, .. } => file.as_ref(),
+            DsigCommand::List { .. } | DsigCommand::Verify { .. } => None,
+        },
+        _ => None,
+    }
+}
+
+fn run_one(command: Command, format: OutputFormat, style: Style) -> Result<()> {
+    match command {
+        Command::Encode(args) => encode(args),
+        Command::Decode(args) => match format {
+            _ if args.format_template.is_some() => decode_structured(args, format),
+            OutputFormat::Text => decode(args),
+            OutputFormat::Json | OutputFormat::Yaml => decode_structured(args, format),
+            _ => Err(unsupported_format("decode")),
+        },
+        Command::Remove(args) => remove(args),
+        Command::Print(args) => print(args, format, style),
+        Command::Info(args) => info(args, format),
+        Command::List(args) => list(args, format, style),
+        Command::Verify(args) => verify(args, style),
+        Command::Repair(args) => repair(args),
+        Command::Salvage(args) => salvage(args),
+        Command::Lint(args) => lint(args, format, style),
+        Command::Check(args) => check(args, style),
+        Command::Filter(args) => filter(args),
+        Command::CheckHidden(args) => check_hidden(args, style),
+        Command::Scan(args) => scan(args, &batch::Walk::default(), format, style),
+        Command::Analyze(args) => analyze(args, format, style),
+        Command::Capacity(args) => capacity(args, format),
+        Command::StripTrailing(args) => strip_trailing(args),
+        Command::Strip(args) => strip(args),
+        Command::Anonymize(args) => anonymize(args),
+        Command::Reorder(args) => reorder(args),
+        Command::Dedupe(args) => dedupe(args),
+        Command::Optimize(args) => optimize(args),
+        Command::Deinterlace(args) => deinterlace(args),
+        Command::CloneMetadata(args) => clone_metadata(args),
+        Command::Diff(args) => diff(args, format, style),
+        Command::Merge(args) => merge(args),
+        Command::Copy(args) => copy(args),
+        Command::Keygen(args) => keygen(args),
+        Command::Fs(args) => fs_command(args),
+        Command::ExtractAll(args) => extract_all(args),
+        Command::Assemble(args) => assemble(args),
+        Command::Text(args) => text_command(args),
+        Command::Exif(args) => exif_command(args),
+        Command::Xmp(args) => xmp_command(args),
+        Command::Time(args) => time_command(args),
+        Command::Dpi(args) => dpi_command(args),
+        Command::Offset(args) => offset_command(args),
+        Command::Scale(args) => scale_command(args),
+        Command::Calibration(args) => calibration_command(args),
+        Command::Dsig(args) => dsig_command(args),
+    }
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn output_path<'a>(path: &'a Path, output: Option<&'a Path>, in_place: bool) -> Result<&'a Path> {
+    match output {
+        Some(output) => Ok(output),
+        None if in_place || is_stdio(path) => Ok(path),
+        None => Err(PngmeError::Usage(
+            "pass --output <path> or --in-place to choose where to write the PNG".into(),
+        )
+        .into()),
+    }
+}
+
+fn read_png(path: &Path) -> Result<Png> {
+    read_png_with(path, &ParseArgs::default())
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    let read = || -> pngme::Result<Vec<u8>> {
+        if is_stdio(path) {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            Ok(bytes)
+        } else {
+            Ok(fs::read(path)?)
+        }
+    };
+    read().map_err(|error| PngmeError::in_file(path, error).into())
+}
+
+fn read_png_with(path: &Path, parse: &ParseArgs) -> Result<Png> {
+    let bytes = read_bytes(path)?;
+    let read = || -> pngme::Result<Png> {
+        if parse.lenient {
+            let (png, warnings) = Png::try_from_lossy(&bytes)?;
+            for warning in warnings {
+                eprintln!("warning: {}: {}", path.display(), warning);
+            }
+            return Ok(png);
+        }
+        let png = if parse.ignore_crc {
+            Png::try_from_ignoring_crc(&bytes)?
+        } else {
+            Png::try_from(bytes.as_ref())?
+        };
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            if let Some(found) = chunk.stored_crc() {
+                eprintln!(
+                    "warning: {}: chunk #{} ({}): crc mismatch: expected {:#010x}, found {:#010x}",
+                    path.display(),
+                    index,
+                    chunk.chunk_type(),
+                    chunk.crc(),
+                    found
+                );
+            }
+        }
+        if parse.strict {
+            validate::check(&png)?;
+        }
+        Ok(png)
+    };
+    read().map_err(|error| PngmeError::in_file(path, error).into())
+}
+
+fn write_png(path: &Path, png: &mut Png, touch: &TouchArgs) -> Result<()> {
+    if touch.update_time {
+        timestamp::set(png, Timestamp::now())?;
+    }
+    let write = || -> pngme::Result<()> {
+        if is_stdio(path) {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&png.as_bytes())?;
+            Ok(stdout.flush()?)
+        } else {
+            Ok(fs::write(path, png.as_bytes())?)
+        }
+    };
+    write().map_err(|error| PngmeError::in_file(path, error).into())
+}
+
+fn encode(mut args: EncodeArgs) -> Result<()> {
+    let mut png = read_png(&args.path)?;
+    if args.method != Method::Chunk {
+        return encode_in_image_data(args, png);
+    }
+    if args.filter.is_some() || args.interlace {
+        return Err(PngmeError::Usage(
+            "--filter and --interlace only apply to --method lsb or alpha, which re-encode the pixels"
+                .to_string(),
+        )
+        .into());
+    }
+    let chunk_type = ChunkType::from_str(
+        args.chunk_type
+            .as_deref()
+            .ok_or_else(|| PngmeError::Usage("encode needs a chunk type".to_string()))?,
+    )?;
+    let existing = png
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.chunk_type() == &chunk_type);
+    let first = insertion_index(&png, &args.position)?;
+    if !args.force && !args.replace {
+        png.check_unique(&chunk_type)?;
+    }
+    if existing.is_some() && !args.replace {
+        eprintln!(
+            "warning: the file already has a {} chunk, adding another (pass --replace to overwrite it)",
+            chunk_type
+        );
+    }
+    let data = encode_payload(&mut args)?;
+    let max_chunk_size = args.max_chunk_size.unwrap_or(split::DEFAULT_MAX_CHUNK_LEN);
+    let (first, count) = if args.replace {
+        signing::remove_signatures_for(&mut png, &chunk_type);
+        png.replace_payload(chunk_type, &data, max_chunk_size)?
+    } else {
+        let count = png.insert_payload(first, chunk_type, &data, max_chunk_size)?;
+        (first, count)
+    };
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    if let (Some(keyfile), true) = (&args.sign, args.dsig) {
+        let key: SigningKey = fs::read_to_string(keyfile)?.parse()?;
+        // Touch before signing, since a later tIME update would break the dSIG pair.
+        if args.touch.update_time {
+            timestamp::set(&mut png, Timestamp::now())?;
+        }
+        dsig::sign(&mut png, &key)?;
+        return write_png(output, &mut png, &TouchArgs::default());
+    }
+    if let Some(keyfile) = &args.sign {
+        let key: SigningKey = fs::read_to_string(keyfile)?.parse()?;
+        let signatures: Vec<Chunk> = png.chunks()[first..first + count]
+            .iter()
+            .map(|chunk| key.sign_chunk(chunk))
+            .collect();
+        for signature in signatures {
+            png.append_chunk(signature)?;
+        }
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+// Methods that hide in the image data have no chunk type, so a payload given
+// on the command line arrives in the chunk type's slot.
+fn encode_in_image_data(mut args: EncodeArgs, mut png: Png) -> Result<()> {
+    if args.data.is_some() {
+        return Err(PngmeError::Usage(format!(
+            "--method {} takes no chunk type, only the data",
+            args.method
+        ))
+        .into());
+    }
+    let position = &args.position;
+    let positioned = position.before.is_some()
+        || position.after.is_some()
+        || position.at.is_some()
+        || position.after_iend;
+    if args.replace
+        || args.force
+        || args.sign.is_some()
+        || positioned
+        || args.max_chunk_size.is_some()
+    {
+        return Err(PngmeError::Usage(format!(
+            "--replace, --force, --sign, --before, --after, --at, --after-iend and \
+             --max-chunk-size only apply to --method chunk, not {}",
+            args.method
+        ))
+        .into());
+    }
+    if args.method == Method::Stored && (args.filter.is_some() || args.interlace) {
+        return Err(PngmeError::Usage(
+            "--filter and --interlace do not apply to --method stored, which leaves the pixels as they are"
+                .to_string(),
+        )
+        .into());
+    }
+    args.data = args.chunk_type.take();
+    let data = encode_payload(&mut args)?;
+    if args.method == Method::Stored {
+        stego::embed_stored(&mut png, &data)?;
+    } else {
+        let mut pixels = png.decode_pixels()?;
+        stego::embed(&mut pixels, args.method, &data)?;
+        pixels.header.interlaced |= args.interlace;
+        png.encode_pixels(&pixels, args.filter.unwrap_or_default())?;
+    }
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    write_png(output, &mut png, &args.touch)
+}
+
+// The bytes `encode` hides: the data, compressed, encrypted and wrapped in an
+// envelope as the arguments ask.
+fn encode_payload(args: &mut EncodeArgs) -> Result<Vec<u8>> {
+    let (mut data, filename) = match (args.data.take(), &args.input_file, &args.dir) {
+        (_, _, Some(dir)) => (
+            archive::pack_dir(dir)?,
+            dir.canonicalize()?
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+        ),
+        (_, Some(input_file), None) => (
+            fs::read(input_file)?,
+            input_file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+        ),
+        (Some(data), None, None) => (data.into_bytes(), None),
+        (None, None, None) => {
+            return Err(PngmeError::Usage(
+                "encode needs the data, --input-file or --dir".to_string(),
+            )
+            .into())
+        }
+    };
+    let original_len = data.len() as u64;
+    if let Some(codec) = args.compress {
+        data = if args.no_envelope {
+            compression::compress(codec, args.compress_level, &data)?
+        } else {
+            codec.compress_raw(
+                args.compress_level.unwrap_or_else(|| codec.default_level()),
+                &data,
+            )?
+        };
+    }
+    let mut encryption = None;
+    if args.encrypt {
+        let passphrase = passphrase::resolve(args.password.take(), args.keyring.as_deref(), true)?;
+        data = crypto::encrypt(args.cipher, args.kdf_params, &passphrase.password, &data)?;
+        passphrase.remember()?;
+        encryption = Some(Encryption::Password);
+    } else if !args.recipients.is_empty() {
+        data = crypto::encrypt_to_recipients(args.cipher, &args.recipients, &data)?;
+        encryption = Some(Encryption::Recipients);
+    } else if !args.age_recipients.is_empty() {
+        data = crypto::encrypt_age(&args.age_recipients, &data)?;
+        encryption = Some(Encryption::Age);
+    }
+    if !args.no_envelope {
+        data = Envelope {
+            codec: args.compress,
+            encryption,
+            cipher_id: match encryption {
+                Some(Encryption::Password | Encryption::Recipients) => args.cipher.cipher().id(),
+                _ => 0,
+            },
+            filename,
+            mime: args
+                .mime
+                .take()
+                .or_else(|| args.dir.as_ref().map(|_| archive::MIME_TYPE.to_string())),
+            original_len,
+            ..Envelope::new(data)
+        }
+        .to_bytes()?;
+    }
+    Ok(data)
+}
+
+fn insertion_index(png: &Png, position: &PositionArgs) -> Result<usize> {
+    Ok(match (&position.before, &position.after, position.at) {
+        (Some(before), _, _) => png
+            .chunks()
+            .iter()
+            .position(|chunk| chunk.chunk_type().to_string() == *before)
+            .ok_or_else(|| PngmeError::ChunkNotFound(format!("no chunk of type {}", before)))?,
+        (_, Some(after), _) => {
+            png.chunks()
+                .iter()
+                .rposition(|chunk| chunk.chunk_type().to_string() == *after)
+                .ok_or_else(|| PngmeError::ChunkNotFound(format!("no chunk of type {}", after)))?
+                + 1
+        }
+        (_, _, Some(at)) => at,
+        (None, None, None) if position.after_iend => png.chunks().len(),
+        (None, None, None) => png.append_index(),
+    })
+}
+
+fn decode(args: DecodeArgs) -> Result<()> {
+    let png = read_png_with(&args.path, &args.parse)?;
+    match args.method {
+        Method::Chunk => {}
+        Method::Stored => return output_decoded(stego::extract_stored(&png)?, &args),
+        method => return output_decoded(stego::extract(&png.decode_pixels()?, method)?, &args),
+    }
+    if args.chunk_types.len() > 1 && (args.nth.is_some() || args.unpack.is_some()) {
+        return Err(PngmeError::Usage("--nth and --unpack take a single chunk type".into()).into());
+    }
+    if let Some(nth) = args.nth {
+        let index = nth_index(&png, args.chunk_types.first(), nth)?;
+        return output_decoded(png.chunks()[index].data().to_vec(), &args);
+    }
+    if let [pattern] = args.chunk_types.as_slice() {
+        return match pattern.exact() {
+            Some(chunk_type) if !args.all => {
+                output_decoded(exact_payload(&png, chunk_type)?, &args)
+            }
+            _ => decode_all(&png, pattern, &args),
+        };
+    }
+    for pattern in &args.chunk_types {
+        match pattern.exact() {
+            Some(chunk_type) if !args.all => {
+                let (data, _) = decode_payload(exact_payload(&png, chunk_type)?, &args)?;
+                write_labelled(chunk_type, data, args.output.as_deref())?;
+            }
+            _ => decode_all(&png, pattern, &args)?,
+        }
+    }
+    Ok(())
+}
+
+fn decode_structured(args: DecodeArgs, format: OutputFormat) -> Result<()> {
+    if args.output.is_some() || args.unpack.is_some() || args.hexdump {
+        return Err(PngmeError::Usage(
+            "--output, --unpack and --hexdump only work with --format text".into(),
+        )
+        .into());
+    }
+    let png = read_png_with(&args.path, &args.parse)?;
+    let mut decoded = Vec::new();
+    let decode_index = |index: usize| -> Result<DecodedPayload> {
+        let chunk = &png.chunks()[index];
+        let (data, _) = decode_payload(chunk.data().to_vec(), &args)?;
+        Ok(DecodedPayload::new(
+            chunk.chunk_type().to_string(),
+            Some(index),
+            &data,
+        ))
+    };
+    if let Some(nth) = args.nth {
+        decoded.push(decode_index(nth_index(
+            &png,
+            args.chunk_types.first(),
+            nth,
+        )?)?);
+    } else {
+        for pattern in &args.chunk_types {
+            match pattern.exact() {
+                Some(chunk_type) if !args.all => {
+                    let (data, _) = decode_payload(exact_payload(&png, chunk_type)?, &args)?;
+                    decoded.push(DecodedPayload::new(chunk_type, None, &data));
+                }
+                _ => {
+                    let indices: Vec<usize> = png
+                        .chunks()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, chunk)| pattern.matches(chunk.chunk_type()))
+                        .map(|(index, _)| index)
+                        .collect();
+                    if indices.is_empty() {
+                        return Err(PngmeError::ChunkNotFound(format!(
+                            "no chunk of type {}",
+                            pattern
+                        ))
+                        .into());
+                    }
+                    for index in indices {
+                        decoded.push(decode_index(index)?);
+                    }
+                }
+            }
+        }
+    }
+    match &args.format_template {
+        Some(template) => print_templated(template, &decoded),
+        None => print_structured(&decoded, format),
+    }
+}
+
+fn print_templated(template: &Template, rows: &[impl serde::Serialize]) -> Result<()> {
+    for row in rows {
+        println!("{}", template.render(row)?);
+    }
+    Ok(())
+}
+
+fn print_structured(value: &impl serde::Serialize, format: OutputFormat) -> Result<()> {
+    let captured = CAPTURED.with(|captured| {
+        captured
+            .borrow_mut()
+            .as_mut()
+            .map(|documents| serde_json::to_value(value).map(|value| documents.push(value)))
+    });
+    if let Some(result) = captured {
+        return Ok(result?);
+    }
+    match format {
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        _ => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+fn print_rows(rows: &[impl serde::Serialize], format: OutputFormat) -> Result<()> {
+    let delimiter = match format {
+        OutputFormat::Csv => b',',
+        OutputFormat::Tsv => b'\t',
+        _ => return print_structured(&rows, format),
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(io::stdout().lock());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    Ok(writer.flush()?)
+}
+
+fn list_reports(args: &ListArgs) -> Result<Vec<ChunkReport>> {
+    let png = read_png_with(&args.path, &args.parse)?;
+    let context = describe::Context::for_png(&png, Some(args.limit));
+    Ok(png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            args.chunk_type
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(chunk.chunk_type()))
+        })
+        .map(|(index, chunk)| ChunkReport::new(index, chunk, &context, false))
+        .collect())
+}
+
+fn unsupported_format(command: &str) -> Error {
+    PngmeError::Usage(format!(
+        "{} only supports --format text, json or yaml",
+        command
+    ))
+    .into()
+}
+
+fn exact_payload(png: &Png, chunk_type: &str) -> Result<Vec<u8>> {
+    Ok(png
+        .payload(chunk_type)?
+        .ok_or_else(|| PngmeError::ChunkNotFound(format!("no chunk of type {}", chunk_type)))?)
+}
+
+fn output_decoded(payload: Vec<u8>, args: &DecodeArgs) -> Result<()> {
+    let (data, _) = decode_payload(payload, args)?;
+    if args.hexdump {
+        print!("{}", hexdump(&data, args.head));
+        return Ok(());
+    }
+    match &args.unpack {
+        Some(dest) => {
+            archive::unpack(&data, dest)?;
+            eprintln!("Unpacked into {}", dest.display());
+            Ok(())
+        }
+        None => write_decoded(data, args.output.as_deref()),
+    }
+}
+
+fn nth_index(png: &Png, chunk_type: Option<&ChunkPattern>, nth: usize) -> Result<usize> {
+    let Some(chunk_type) = chunk_type else {
+        return match png.chunk_at(nth) {
+            Some(_) => Ok(nth),
+            None => Err(PngmeError::ChunkNotFound(format!(
+                "chunk index {} is out of range, the file has {} chunks",
+                nth,
+                png.chunks().len()
+            ))
+            .into()),
+        };
+    };
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| chunk_type.matches(chunk.chunk_type()))
+        .nth(nth)
+        .map(|(index, _)| index)
+        .ok_or_else(|| {
+            PngmeError::ChunkNotFound(format!("no {} chunk at occurrence {}", chunk_type, nth))
+                .into()
+        })
+}
+
+fn decode_all(png: &Png, chunk_type: &ChunkPattern, args: &DecodeArgs) -> Result<()> {
+    let matches: Vec<(usize, &Chunk)> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| chunk_type.matches(chunk.chunk_type()))
+        .collect();
+    if matches.is_empty() {
+        return Err(PngmeError::ChunkNotFound(format!("no chunk of type {}", chunk_type)).into());
+    }
+    for (index, chunk) in matches {
+        let (data, _) = decode_payload(chunk.data().to_vec(), args)?;
+        if args.hexdump {
+            println!("{}:", index);
+            print!("{}", hexdump(&data, args.head));
+            continue;
+        }
+        write_labelled(&index.to_string(), data, args.output.as_deref())?;
+    }
+    Ok(())
+}
+
+fn write_labelled(label: &str, data: Vec<u8>, output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(output) => {
+            let mut name = output.file_stem().unwrap_or_default().to_os_string();
+            name.push(format!("_{}", label));
+            if let Some(extension) = output.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            write_decoded(data, Some(&output.with_file_name(name)))
+        }
+        None => {
+            match String::from_utf8(data) {
+                Ok(text) => println!("{}: {}", label, text),
+                Err(e) => println!("{}: <{} bytes of binary data>", label, e.as_bytes().len()),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn decode_payload(payload: Vec<u8>, args: &DecodeArgs) -> Result<(Vec<u8>, Option<bool>)> {
+    Ok(match Envelope::parse(&payload)? {
+        Some(envelope) => {
+            let is_text = envelope.is_text();
+            (open_envelope(envelope, args)?, is_text)
+        }
+        None => (decode_bare(payload, args)?, None),
+    })
+}
+
+fn decode_bare(payload: Vec<u8>, args: &DecodeArgs) -> Result<Vec<u8>> {
+    let data = match (args.decrypt, &args.identity, &args.age_identity) {
+        (true, Some(identity), _) => {
+            let identity: IdentityKey = fs::read_to_string(identity)?.parse()?;
+            crypto::decrypt_with_identity(&identity, &payload)?
+        }
+        (true, None, Some(age_identity)) => {
+            crypto::decrypt_age(&fs::read_to_string(age_identity)?, &payload)?
+        }
+        (true, None, None) => {
+            let passphrase =
+                passphrase::resolve(args.password.clone(), args.keyring.as_deref(), false)?;
+            let data = crypto::decrypt(&passphrase.password, &payload)?;
+            passphrase.remember()?;
+            data
+        }
+        (false, _, _) => payload,
+    };
+    Ok(if args.no_decompress {
+        data
+    } else {
+        compression::decompress(&data)?.unwrap_or(data)
+    })
+}
+
+fn open_envelope(envelope: Envelope, args: &DecodeArgs) -> Result<Vec<u8>> {
+    if let Some(filename) = &envelope.filename {
+        eprintln!("Original filename: {}", filename);
+    }
+    if let Some(mime) = &envelope.mime {
+        eprintln!("Media type: {}", mime);
+    }
+    let body = match envelope.encryption {
+        None => envelope.body,
+        Some(Encryption::Password) => {
+            let passphrase =
+                passphrase::resolve(args.password.clone(), args.keyring.as_deref(), false)?;
+            let body = crypto::decrypt(&passphrase.password, &envelope.body)?;
+            passphrase.remember()?;
+            body
+        }
+        Some(Encryption::Recipients) => {
+            let identity = args.identity.as_ref().ok_or_else(|| {
+                PngmeError::Usage(
+                    "payload is encrypted to X25519 recipients, pass --identity".into(),
+                )
+            })?;
+            let identity: IdentityKey = fs::read_to_string(identity)?.parse()?;
+            crypto::decrypt_with_identity(&identity, &envelope.body)?
+        }
+        Some(Encryption::Age) => {
+            let age_identity = args.age_identity.as_ref().ok_or_else(|| {
+                PngmeError::Usage("payload is age-encrypted, pass --age-identity".into())
+            })?;
+            crypto::decrypt_age(&fs::read_to_string(age_identity)?, &envelope.body)?
+        }
+    };
+    let data = match envelope.codec {
+        Some(_) if args.no_decompress => return Ok(body),
+        Some(codec) => codec.decompress_raw(&body, envelope.original_len)?,
+        None => body,
+    };
+    if data.len() as u64 != envelope.original_len {
+        return Err(PngmeError::Malformed(format!(
+            "payload is {} bytes but the envelope declares {}",
+            data.len(),
+            envelope.original_len
+        ))
+        .into());
+    }
+    Ok(data)
+}
+
+fn write_decoded(data: Vec<u8>, output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(output) if output.extension().is_none() => match sniff::sniff(&data) {
+            Some(file_type) => {
+                let output = output.with_extension(file_type.extension());
+                eprintln!("Detected {}, writing {}", file_type, output.display());
+                fs::write(output, data)?
+            }
+            None => fs::write(output, data)?,
+        },
+        Some(output) => fs::write(output, data)?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&data)?;
+            stdout.flush()?
+        }
+    }
+    Ok(())
+}
+
+fn remove(args: RemoveArgs) -> Result<()> {
+    let mut png = read_png(&args.path)?;
+    if let Some(nth) = args.nth {
+        if args.chunk_types.len() > 1 {
+            return Err(PngmeError::Usage("--nth takes a single chunk type".into()).into());
+        }
+        let index = nth_index(&png, args.chunk_types.first(), nth)?;
+        let removed = vec![png.remove_chunk_at(index)?];
+        return finish_remove(&args.path, &mut png, removed, &args.touch);
+    }
+    let mut removed = Vec::new();
+    for chunk_type in &args.chunk_types {
+        if args.all {
+            let matches = png.remove_chunks_where(|chunk| chunk_type.matches(chunk.chunk_type()));
+            if matches.is_empty() {
+                return Err(
+                    PngmeError::ChunkNotFound(format!("no chunk of type {}", chunk_type)).into(),
+                );
+            }
+            removed.extend(matches);
+        } else {
+            let index = nth_index(&png, Some(chunk_type), 0)?;
+            removed.push(png.remove_chunk_at(index)?);
+        }
+    }
+    finish_remove(&args.path, &mut png, removed, &args.touch)
+}
+
+fn finish_remove(
+    path: &Path,
+    png: &mut Png,
+    mut removed: Vec<Chunk>,
+    touch: &TouchArgs,
+) -> Result<()> {
+    removed.extend(signing::remove_orphaned_signatures(png));
+    write_png(path, png, touch)?;
+    for chunk in removed {
+        if is_stdio(path) {
+            eprintln!("Removed {}", chunk);
+        } else {
+            println!("Removed {}", chunk);
+        }
+    }
+    Ok(())
+}
+
+fn print(args: PrintArgs, format: OutputFormat, style: Style) -> Result<()> {
+    if format != OutputFormat::Text {
+        return print_rows(&print_reports(&args)?, format);
+    }
+    let png = read_png_with(&args.path, &args.parse)?;
+    let context = describe::Context::for_png(&png, args.limit);
+    let preview_len = (!args.full).then_some(PREVIEW_LEN);
+    for chunk in png.chunks() {
+        let chunk_type = styled_type(&style, chunk, &chunk.chunk_type().to_string());
+        if args.hex {
+            println!(
+                "{} ({}, crc {:#010x}) [{}]:",
+                chunk_type,
+                human_size(chunk.length().into()),
+                chunk.crc(),
+                describe::label(chunk)
+            );
+            print!("{}", hexdump(chunk.data(), args.head));
+            continue;
+        }
+        println!(
+            "{} ({}, crc {:#010x}) [{}]: {}",
+            chunk_type,
+            human_size(chunk.length().into()),
+            chunk.crc(),
+            describe::label(chunk),
+            describe(chunk, &context).unwrap_or_else(|| chunk.preview(preview_len))
+        );
+    }
+    Ok(())
+}
+
+fn info(args: InfoArgs, format: OutputFormat) -> Result<()> {
+    let summary = Summary::of(&read_png_with(&args.path, &args.parse)?);
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json | OutputFormat::Yaml => return print_structured(&summary, format),
+        _ => return Err(unsupported_format("info")),
+    }
+    match &summary.header {
+        Some(header) => {
+            println!("Dimensions:  {}x{}", header.width, header.height);
+            println!(
+                "Colour:      {}-bit {}{}",
+                header.bit_depth,
+                header.colour_type,
+                if header.interlaced {
+                    ", interlaced"
+                } else {
+                    ""
+                }
+            );
+        }
+        None => println!("Dimensions:  unknown (no valid IHDR)"),
+    }
+    println!("File size:   {} bytes", summary.file_size);
+    if summary.trailing_bytes > 0 {
+        println!("Trailing:    {} bytes after IEND", summary.trailing_bytes);
+    }
+    println!(
+        "Chunks:      {} ({} critical, {} ancillary, {} unknown)",
+        summary.chunks, summary.critical, summary.ancillary, summary.unknown
+    );
+    let mut metadata = Vec::new();
+    if summary.text_entries > 0 {
+        metadata.push(format!("text ({} entries)", summary.text_entries));
+    }
+    for (present, name) in [
+        (summary.has_exif, "EXIF"),
+        (summary.has_xmp, "XMP"),
+        (summary.has_icc, "ICC profile"),
+    ] {
+        if present {
+            metadata.push(name.to_string());
+        }
+    }
+    println!("Metadata:    {}", list_or_none(&metadata));
+    let pngme: Vec<String> = summary
+        .pngme_chunks
+        .iter()
+        .map(|counted| format!("{} x{}", counted.chunk_type, counted.count))
+        .collect();
+    println!("pngme:       {}", list_or_none(&pngme));
+    Ok(())
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn print_reports(args: &PrintArgs) -> Result<Vec<ChunkReport>> {
+    let png = read_png_with(&args.path, &args.parse)?;
+    let context = describe::Context::for_png(&png, args.limit);
+    Ok(png
+        .chunks()
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| ChunkReport::new(index, chunk, &context, true))
+        .collect())
+}
+
+fn list(args: ListArgs, format: OutputFormat, style: Style) -> Result<()> {
+    if format != OutputFormat::Text || args.format_template.is_some() {
+        let reports = list_reports(&args)?;
+        return match &args.format_template {
+            Some(template) => print_templated(template, &reports),
+            None => print_rows(&reports, format),
+        };
+    }
+    let png = read_png_with(&args.path, &args.parse)?;
+    let context = describe::Context::for_png(&png, Some(args.limit));
+    let mut unknown = 0;
+    println!(
+        "{}",
+        style.dim(&format!(
+            "{:>5}  {:<10}  {:<4}  {:>10}  {:<10}  {:<8}  {:<7}  {:<9}  {:>7}  DETAILS",
+            "INDEX",
+            "OFFSET",
+            "TYPE",
+            "LENGTH",
+            "CRC",
+            "CRITICAL",
+            "PUBLIC",
+            "SAFE COPY",
+            "ENTROPY"
+        ))
+    );
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        if let Some(pattern) = &args.chunk_type {
+            if !pattern.matches(chunk_type) {
+                continue;
+            }
+        }
+        // Show the CRC as it is in the file, flagged when it does not match.
+        let crc = match chunk.stored_crc() {
+            Some(found) => style.bad(&format!("{:#010x}", found)),
+            None => format!("{:#010x}", chunk.crc()),
+        };
+        let row = format!(
+            "{:>5}  {:#010x}  {:<4}  {:>10}  {}  {:<8}  {:<7}  {:<9}  {:>7.3}  {}",
+            index,
+            chunk.offset().unwrap_or_default(),
+            styled_type(&style, chunk, &chunk_type.to_string()),
+            chunk.length(),
+            crc,
+            yes_no(chunk_type.is_critical()),
+            yes_no(chunk_type.is_public()),
+            yes_no(chunk_type.is_safe_to_copy()),
+            chunk.entropy(),
+            describe::annotate(chunk, &context),
+        );
+        println!("{}", row);
+        if registry::lookup(chunk_type).is_none() {
+            unknown += 1;
+        }
+    }
+    // Lenient parsing has already warned about each unknown chunk.
+    if unknown > 0 && !args.parse.lenient {
+        eprintln!("warning: {} chunk(s) of unknown type", unknown);
+    }
+    Ok(())
+}
+
+fn styled_type(style: &Style, chunk: &Chunk, text: &str) -> String {
+    if is_pngme_chunk(chunk) {
+        style.payload(text)
+    } else if registry::lookup(chunk.chunk_type()).is_none() {
+        style.unknown(text)
+    } else if chunk.chunk_type().is_critical() {
+        style.critical(text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn extract_all(args: ExtractAllArgs) -> Result<()> {
+    let png = read_png(&args.path)?;
+    let manifest = dump::extract(&png, &args.outdir, args.include_standard)?;
+    for entry in &manifest.chunks {
+        println!("{}", args.outdir.join(&entry.file).display());
+    }
+    eprintln!(
+        "Extracted {} chunks, manifest in {}",
+        manifest.chunks.len(),
+        args.outdir.join(dump::MANIFEST_FILE).display()
+    );
+    Ok(())
+}
+
+fn assemble(args: AssembleArgs) -> Result<()> {
+    let base = args.base.as_deref().map(read_png).transpose()?;
+    let mut png = dump::assemble(&args.dir, base.as_ref())?;
+    write_png(&args.output, &mut png, &args.touch)
+}
+
+fn verify(args: VerifyArgs, style: Style) -> Result<()> {
+    let bytes = read_bytes(&args.path)?;
+    let integrity = Integrity::check(&bytes);
+    let status = |error: Option<&dyn std::fmt::Display>| match error {
+        None => style.good("ok"),
+        Some(error) => format!("{}  {}", style.bad("FAIL"), error),
+    };
+    let signature = (!integrity.signature_valid).then_some(&PngmeError::InvalidSignature);
+    println!(
+        "PNG signature                        {}",
+        status(signature.map(|e| e as _))
+    );
+    for chunk in &integrity.chunks {
+        println!(
+            "{:>5}  {:<4}  {:#010x}  {:>9}  {}",
+            chunk.index,
+            chunk.chunk_type,
+            chunk.offset,
+            human_size(chunk.length.into()),
+            status(chunk.error.as_ref().map(|e| e as _))
+        );
+    }
+    if integrity.signature_valid {
+        println!(
+            "Chunk layout                         {}",
+            status(integrity.violations.first().map(|v| v as _))
+        );
+        for violation in integrity.violations.iter().skip(1) {
+            println!("{:>37}{}", "", violation);
+        }
+    }
+    if integrity.trailing_bytes > 0 {
+        println!(
+            "Trailing data                        {} after IEND",
+            human_size(integrity.trailing_bytes as u64)
+        );
+    }
+    integrity
+        .into_result()
+        .map_err(|error| PngmeError::in_file(&args.path, error))?;
+
+    let (Some(chunk_type), Some(pubkey)) = (args.chunk_type, args.pubkey) else {
+        return Ok(());
+    };
+    let png = Png::try_from(bytes.as_ref())?;
+    if let Err(e) = pubkey.verify_in_png(&png, &chunk_type) {
+        println!(
+            "{}",
+            style.bad(&format!("Signature for {} is invalid", chunk_type))
+        );
+        return Err(e.into());
+    }
+    println!(
+        "{}",
+        style.good(&format!("Signature for {} is valid", chunk_type))
+    );
+    Ok(())
+}
+
+fn repair(args: RepairArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let bytes = read_bytes(&args.path)?;
+    let (mut png, fixes) =
+        repair::repair(&bytes).map_err(|error| PngmeError::in_file(&args.path, error))?;
+    for fix in &fixes {
+        eprintln!("fixed: {}", fix);
+    }
+    if fixes.is_empty() {
+        eprintln!("nothing to repair");
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+fn salvage(args: SalvageArgs) -> Result<()> {
+    let bytes = read_bytes(&args.path)?;
+    let (mut png, salvage) =
+        Png::salvage(&bytes).map_err(|error| PngmeError::in_file(&args.path, error))?;
+    println!("{}", salvage);
+    println!("Kept {} complete chunk(s)", png.chunks().len());
+    let Some(output) = args.output else {
+        return Ok(());
+    };
+    if salvage.missing_iend {
+        png.push_chunk(Chunk::new(ChunkType::from_str("IEND")?, Vec::new()));
+    }
+    write_png(&output, &mut png, &args.touch)
+}
+
+fn lint(args: LintArgs, format: OutputFormat, style: Style) -> Result<()> {
+    let png = read_png(&args.path)?;
+    let config = lint::LintConfig {
+        allow: args.allow,
+        deny: args.deny,
+    };
+    let findings = lint::lint(&png, &config);
+    match format {
+        OutputFormat::Text => {
+            for finding in &findings {
+                let line = finding.to_string();
+                match finding.level {
+                    lint::Level::Deny => println!("{}", style.bad(&line)),
+                    _ => println!("{}", line),
+                }
+            }
+            if findings.is_empty() {
+                println!("no findings");
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => print_structured(&findings, format)?,
+        _ => return Err(unsupported_format("lint")),
+    }
+    match lint::denied(&findings) {
+        0 => Ok(()),
+        denied => Err(PngmeError::in_file(&args.path, PngmeError::LintDenied(denied)).into()),
+    }
+}
+
+fn check(args: CheckArgs, style: Style) -> Result<()> {
+    let text = String::from_utf8(read_bytes(&args.policy)?)?;
+    let policy =
+        Policy::from_str(&text).map_err(|error| PngmeError::in_file(&args.policy, error))?;
+    let mut failed = 0;
+    for path in &args.paths {
+        let violations = match read_png(path) {
+            Ok(png) => policy.violations(&png),
+            Err(error) => {
+                failed += 1;
+                println!("{}", style.bad(&error.to_string()));
+                continue;
+            }
+        };
+        if violations.is_empty() {
+            println!("{}: ok", path.display());
+            continue;
+        }
+        failed += 1;
+        for violation in violations {
+            println!(
+                "{}",
+                style.bad(&format!("{}: {}", path.display(), violation))
+            );
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        failed => Err(PngmeError::PolicyFailed {
+            failed,
+            total: args.paths.len(),
+        }
+        .into()),
+    }
+}
+
+// A file that cannot be read is not evidence of hidden chunks, so read
+// failures are reported apart and only decide the exit status when nothing was
+// found.
+fn check_hidden(args: CheckHiddenArgs, style: Style) -> Result<()> {
+    let mut files = 0;
+    let mut chunks = 0;
+    let mut unreadable = Vec::new();
+    for path in &args.paths {
+        let png = match read_png(path) {
+            Ok(png) => png,
+            Err(error) => {
+                unreadable.push(error);
+                continue;
+            }
+        };
+        let hidden = policy::hidden_chunks(&png, &args.deny);
+        if hidden.is_empty() {
+            continue;
+        }
+        files += 1;
+        chunks += hidden.len();
+        for (index, chunk) in hidden {
+            println!(
+                "{}: chunk #{} {} ({})",
+                path.display(),
+                index,
+                style.bad(&chunk.chunk_type().to_string()),
+                human_size(chunk.length().into())
+            );
+        }
+    }
+    // With nothing hidden, the last read failure becomes the error itself.
+    let unread = unreadable.len();
+    let failure = if files == 0 { unreadable.pop() } else { None };
+    for error in &unreadable {
+        eprintln!("error: {}", error);
+    }
+    if unread > 1 || files > 0 && unread > 0 {
+        eprintln!(
+            "{} of {} file(s) could not be read",
+            unread,
+            args.paths.len()
+        );
+    }
+    match (files, failure) {
+        (0, None) => Ok(()),
+        (0, Some(error)) => Err(error),
+        (files, _) => Err(PngmeError::HiddenChunks { chunks, files }.into()),
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ScannedFile {
+    path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    findings: Vec<scan::Finding>,
+}
+
+// CSV and TSV have no room for nesting, so each read error and each finding
+// becomes its own row.
+#[derive(serde::Serialize)]
+struct ScanRow<'a> {
+    path: &'a Path,
+    kind: String,
+    detail: String,
+}
+
+impl ScannedFile {
+    fn rows(&self) -> Vec<ScanRow<'_>> {
+        let error = self.error.iter().map(|error| ScanRow {
+            path: &self.path,
+            kind: "error".to_string(),
+            detail: error.clone(),
+        });
+        let findings = self.findings.iter().map(|finding| ScanRow {
+            path: &self.path,
+            kind: finding.suspicion.to_string(),
+            detail: match (finding.index, &finding.chunk_type) {
+                (Some(index), Some(chunk_type)) => {
+                    format!("chunk #{} {}, {} bytes", index, chunk_type, finding.bytes)
+                }
+                _ => format!("{} bytes", finding.bytes),
+            },
+        });
+        error.chain(findings).collect()
+    }
+}
+
+// Files are parsed leniently: a broken CRC or stray bytes are exactly what a
+// review should see, not a reason to skip the file.
+fn scan(args: ScanArgs, walk: &batch::Walk, format: OutputFormat, style: Style) -> Result<()> {
+    let files = if args.path.is_file() {
+        vec![args.path.clone()]
+    } else {
+        let walk = batch::Walk {
+            recursive: true,
+            ..walk.clone()
+        };
+        batch::expand(&args.path, &walk)?
+    };
+    let mut flagged = Vec::new();
+    for path in &files {
+        let scanned = match read_bytes(path).and_then(|bytes| Ok(Png::try_from_lossy(&bytes)?.0)) {
+            Ok(png) => ScannedFile {
+                path: path.clone(),
+                error: None,
+                findings: scan::scan(&png, args.max_chunk_size),
+            },
+            Err(error) => ScannedFile {
+                path: path.clone(),
+                error: Some(ErrorReport::new(error.as_ref()).message),
+                findings: Vec::new(),
+            },
+        };
+        if scanned.error.is_some() || !scanned.findings.is_empty() {
+            flagged.push(scanned);
+        }
+    }
+    match format {
+        OutputFormat::Text => {
+            for file in &flagged {
+                if let Some(error) = &file.error {
+                    println!("{}: {}", file.path.display(), style.bad(error));
+                }
+                for finding in &file.findings {
+                    println!(
+                        "{}: {}",
+                        file.path.display(),
+                        style.bad(&finding.to_string())
+                    );
+                }
+            }
+            println!("{} file(s) scanned, {} flagged", files.len(), flagged.len());
+        }
+        OutputFormat::Json | OutputFormat::Yaml => print_structured(&flagged, format)?,
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let rows: Vec<ScanRow> = flagged.iter().flat_map(ScannedFile::rows).collect();
+            print_rows(&rows, format)?;
+        }
+    }
+    match flagged.len() {
+        0 => Ok(()),
+        files_flagged => Err(PngmeError::SuspiciousFiles {
+            findings: flagged
+                .iter()
+                .map(|file| file.findings.len() + usize::from(file.error.is_some()))
+                .sum(),
+            files: files_flagged,
+            total: files.len(),
+        }
+        .into()),
+    }
+}
+
+fn analyze(args: AnalyzeArgs, format: OutputFormat, style: Style) -> Result<()> {
+    let bytes = read_bytes(&args.path)?;
+    let (png, _) =
+        Png::try_from_lossy(&bytes).map_err(|error| PngmeError::in_file(&args.path, error))?;
+    let analysis = analyze::analyze(&png);
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json | OutputFormat::Yaml => return print_structured(&analysis, format),
+        _ => return Err(unsupported_format("analyze")),
+    }
+    let percent = |value: Option<f64>| {
+        value.map_or_else(
+            || "n/a".to_string(),
+            |value| format!("{:.1}%", value * 100.0),
+        )
+    };
+    println!(
+        "Chi-square:  {} probability of LSB embedding",
+        percent(analysis.chi_square)
+    );
+    println!(
+        "RS analysis: {} of samples estimated to carry payload",
+        percent(analysis.rs_rate)
+    );
+    if let Some(reason) = &analysis.pixels_skipped {
+        println!("             pixel tests skipped: {}", reason);
+    }
+    for anomaly in &analysis.anomalies {
+        println!(
+            "Entropy:     chunk #{} {} is {:.2} bits/byte over {}, expected at most {:.1}",
+            anomaly.index,
+            anomaly.chunk_type,
+            anomaly.entropy,
+            human_size(anomaly.length as u64),
+            anomaly.ceiling
+        );
+    }
+    let likelihood = analysis.likelihood.to_string();
+    println!(
+        "Likelihood:  {}",
+        match analysis.likelihood {
+            Likelihood::Unlikely => style.good(&likelihood),
+            Likelihood::Possible => style.unknown(&likelihood),
+            Likelihood::Likely => style.bad(&likelihood),
+        }
+    );
+    Ok(())
+}
+
+fn capacity(args: CapacityArgs, format: OutputFormat) -> Result<()> {
+    let png = read_png(&args.path)?;
+    let capacities = stego::capacities(&png);
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json | OutputFormat::Yaml => return print_structured(&capacities, format),
+        _ => return Err(unsupported_format("capacity")),
+    }
+    if let Some(header) = ihdr::get(&png) {
+        println!(
+            "Image:   {}x{}, {}-bit {}, {}",
+            header.width,
+            header.height,
+            header.bit_depth,
+            header.colour_type,
+            human_size(png.as_bytes().len() as u64)
+        );
+    }
+    for capacity in &capacities {
+        let room = match (capacity.bytes, &capacity.unavailable) {
+            (_, Some(reason)) => format!("unavailable: {}", reason),
+            (Some(bytes), None) => format!("{} ({} bytes)", human_size(bytes as u64), bytes),
+            (None, None) if capacity.method == Method::Stored => {
+                "unlimited, the file grows by the payload plus 5 bytes per 64 KiB".to_string()
+            }
+            (None, None) => {
+                "unlimited, the file grows by the payload plus 12 bytes per chunk".to_string()
+            }
+        };
+        println!("{:<8} {}", capacity.method.to_string(), room);
+    }
+    println!(
+        "Payloads are wrapped in an envelope of at least {} bytes unless --no-envelope is given.",
+        Envelope::new(Vec::new()).to_bytes()?.len()
+    );
+    Ok(())
+}
+
+// Git runs the filter on every matching path, so input that is not a PNG is
+// passed through untouched rather than failing the checkout or commit. Clean
+// and smudge must give the same bytes every time, so tIME is never updated.
+fn filter(args: FilterArgs) -> Result<()> {
+    let stdin = Path::new("-");
+    let bytes = read_bytes(stdin)?;
+    let Ok(mut png) = Png::try_from(bytes.as_ref()) else {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&bytes)?;
+        return Ok(stdout.flush()?);
+    };
+    if args.clean {
+        let stash = filter::clean(&mut png, &args.chunk_types);
+        if !stash.is_empty() {
+            let path = args.store.join(filter::stash_key(&png.as_bytes()));
+            let store = || -> pngme::Result<()> {
+                fs::create_dir_all(&args.store)?;
+                Ok(fs::write(&path, stash.to_bytes())?)
+            };
+            store().map_err(|error| PngmeError::in_file(&path, error))?;
+        }
+    } else {
+        let path = args.store.join(filter::stash_key(&bytes));
+        if path.exists() {
+            let stash = filter::Stash::from_bytes(&read_bytes(&path)?)
+                .map_err(|error| PngmeError::in_file(&path, error))?;
+            filter::smudge(&mut png, stash)?;
+        }
+    }
+    write_png(stdin, &mut png, &TouchArgs::default())
+}
+
+fn strip(args: StripArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let removed = strip::strip(&mut png, &args.keep);
+    for chunk in &removed {
+        eprintln!(
+            "removed {} ({})",
+            chunk.chunk_type(),
+            human_size(chunk.length().into())
+        );
+    }
+    let saved: u64 = removed.iter().map(|chunk| chunk.length() as u64 + 12).sum();
+    eprintln!(
+        "stripped {} chunk(s), saving {}",
+        removed.len(),
+        human_size(saved)
+    );
+    write_png(output, &mut png, &args.touch)
+}
+
+fn optimize(args: OptimizeArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let options = optimize::Options {
+        keep: args.keep,
+        reduce: args.reduce,
+        filter: args.filter,
+        interlace: args.interlace,
+    };
+    let report = optimize::optimize(&mut png, &options)?;
+    for chunk in &report.removed {
+        eprintln!(
+            "removed {} ({})",
+            chunk.chunk_type(),
+            human_size(chunk.length().into())
+        );
+    }
+    if let Some((from, to)) = report.reduced {
+        eprintln!(
+            "reduced {}-bit {} to {}-bit {}",
+            from.bit_depth, from.colour_type, to.bit_depth, to.colour_type
+        );
+    }
+    if !report.recompressed {
+        eprintln!("image data is already smaller than a re-deflate");
+    }
+    eprintln!(
+        "{} -> {} bytes, saving {} ({:.1}%)",
+        report.original_len,
+        report.optimized_len,
+        human_size(report.saved() as u64),
+        report.saved() as f64 * 100.0 / report.original_len as f64
+    );
+    write_png(output, &mut png, &args.touch)
+}
+
+fn deinterlace(args: DeinterlaceArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let mut pixels = png.decode_pixels()?;
+    if pixels.header.interlaced {
+        pixels.header.interlaced = false;
+        png.encode_pixels(&pixels, args.filter)?;
+        eprintln!("de-interlaced {}x{} image", pixels.width(), pixels.height());
+    } else {
+        eprintln!("image is not interlaced");
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+fn anonymize(args: AnonymizeArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let context = describe::Context::for_png(&png, None);
+    let removed = strip::anonymize(&mut png);
+    for chunk in &removed {
+        let details = TextEntry::from_chunk(chunk)
+            .map(|entry| format!("keyword {:?}", entry.keyword.as_str()))
+            .ok()
+            .or_else(|| describe(chunk, &context));
+        match details {
+            Some(details) => eprintln!("removed {}: {}", chunk.chunk_type(), details),
+            None => eprintln!("removed {}", chunk.chunk_type()),
+        }
+    }
+    if removed.is_empty() {
+        eprintln!("no metadata to remove");
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+fn reorder(args: ReorderArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    if png.normalize_order() {
+        eprintln!("reordered chunks into canonical order");
+    } else {
+        eprintln!("chunks are already in canonical order");
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+fn dedupe(args: DedupeArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let removed = strip::dedupe(&mut png);
+    for chunk in &removed {
+        eprintln!(
+            "removed duplicate {} ({})",
+            chunk.chunk_type(),
+            human_size(chunk.length().into())
+        );
+    }
+    let saved: u64 = removed.iter().map(|chunk| chunk.length() as u64 + 12).sum();
+    eprintln!(
+        "removed {} duplicate chunk(s), saving {}",
+        removed.len(),
+        human_size(saved)
+    );
+    write_png(output, &mut png, &args.touch)
+}
+
+fn clone_metadata(args: CloneMetadataArgs) -> Result<()> {
+    let output = output_path(&args.destination, args.output.as_deref(), args.in_place)?;
+    let source = read_png(&args.source)?;
+    let mut destination = read_png(&args.destination)?;
+    let transfer =
+        transfer::clone_metadata(&source, &mut destination, &args.chunk_types, args.force)?;
+    for chunk_type in &transfer.copied {
+        eprintln!("copied {}", chunk_type);
+    }
+    for chunk_type in &transfer.skipped_unsafe {
+        eprintln!(
+            "warning: skipped {}, which is not safe to copy between different images (pass --force to copy it anyway)",
+            chunk_type
+        );
+    }
+    if transfer.copied.is_empty() {
+        eprintln!("no metadata copied");
+    }
+    write_png(output, &mut destination, &args.touch)
+}
+
+fn diff(args: DiffArgs, format: OutputFormat, style: Style) -> Result<()> {
+    let old = read_png(&args.old)?;
+    let new = read_png(&args.new)?;
+    let changes = diff::diff(&old, &new, args.bytes);
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json | OutputFormat::Yaml => return print_structured(&changes, format),
+        _ => return Err(unsupported_format("diff")),
+    }
+    let describe_side = |side: Option<diff::ChunkSide>| {
+        side.map_or_else(String::new, |side| {
+            format!(
+                "#{} {} crc {:#010x}",
+                side.index,
+                human_size(side.length.into()),
+                side.crc
+            )
+        })
+    };
+    let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+    for change in &changes {
+        let old = describe_side(change.old);
+        let new = describe_side(change.new);
+        let line = match change.kind {
+            diff::ChangeKind::Unchanged if !args.all => continue,
+            diff::ChangeKind::Unchanged => format!("  {}  {}", change.chunk_type, new),
+            diff::ChangeKind::Added => style.good(&format!("+ {}  {}", change.chunk_type, new)),
+            diff::ChangeKind::Removed => style.bad(&format!("- {}  {}", change.chunk_type, old)),
+            diff::ChangeKind::Modified => format!("~ {}  {} -> {}", change.chunk_type, old, new),
+        };
+        println!("{}", line);
+        for bytes in &change.bytes {
+            println!(
+                "    at {:#x}: {} -> {}",
+                bytes.offset,
+                style.bad(&hex_preview(&bytes.old)),
+                style.good(&hex_preview(&bytes.new))
+            );
+        }
+    }
+    println!(
+        "{} added, {} removed, {} modified, {} unchanged",
+        count(diff::ChangeKind::Added),
+        count(diff::ChangeKind::Removed),
+        count(diff::ChangeKind::Modified),
+        count(diff::ChangeKind::Unchanged)
+    );
+    Ok(())
+}
+
+fn hex_preview(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "(none)".to_string();
+    }
+    let hex: Vec<String> = bytes
+        .iter()
+        .take(PREVIEW_LEN)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let more = if bytes.len() > PREVIEW_LEN {
+        " ..."
+    } else {
+        ""
+    };
+    format!("{}{}", hex.join(" "), more)
+}
+
+fn merge(args: MergeArgs) -> Result<()> {
+    let output = output_path(&args.base, args.output.as_deref(), args.in_place)?;
+    let mut base = read_png(&args.base)?;
+    for path in &args.others {
+        let other = read_png(path)?;
+        let merge = transfer::merge(&mut base, &other, args.prefer)?;
+        let path = path.display();
+        for chunk_type in &merge.added {
+            eprintln!("{}: added {}", path, chunk_type);
+        }
+        for chunk_type in &merge.replaced {
+            eprintln!("{}: replaced {}", path, chunk_type);
+        }
+        for chunk_type in &merge.conflicts {
+            eprintln!(
+                "{}: kept the base {} (pass --prefer other to take it)",
+                path, chunk_type
+            );
+        }
+        for chunk_type in &merge.skipped_unsafe {
+            eprintln!(
+                "warning: {}: skipped {}, which is not safe to copy between different images",
+                path, chunk_type
+            );
+        }
+    }
+    write_png(output, &mut base, &args.touch)
+}
+
+// Without a position the chunk goes where the spec's ordering rules allow,
+// which matters for chunks like iCCP that must come before PLTE.
+fn copy(args: CopyArgs) -> Result<()> {
+    let output = output_path(&args.destination, args.output.as_deref(), args.in_place)?;
+    let source = read_png(&args.source)?;
+    let mut destination = read_png(&args.destination)?;
+    let chunk = transfer::nth_chunk(&source, &args.chunk_type, args.nth)?.clone();
+    if args.replace {
+        destination.remove_chunks(&args.chunk_type);
+    }
+    let position = &args.position;
+    if position.before.is_none()
+        && position.after.is_none()
+        && position.at.is_none()
+        && !position.after_iend
+    {
+        transfer::insert_in_order(&mut destination, chunk)?;
+    } else {
+        let index = insertion_index(&destination, position)?;
+        destination.insert_chunk(index, chunk)?;
+    }
+    eprintln!("copied {} from {}", args.chunk_type, args.source.display());
+    write_png(output, &mut destination, &args.touch)
+}
+
+fn strip_trailing(args: StripTrailingArgs) -> Result<()> {
+    let output = output_path(&args.path, args.output.as_deref(), args.in_place)?;
+    let mut png = read_png(&args.path)?;
+    let stripped = png.strip_trailing_data();
+    if stripped.is_empty() {
+        eprintln!("no data after IEND");
+    } else {
+        eprintln!("stripped {} bytes after IEND", stripped.len());
+    }
+    write_png(output, &mut png, &args.touch)
+}
+
+fn keygen(args: KeygenArgs) -> Result<()> {
+    let dir = args.dir.or_else(KeyStore::default_dir).ok_or_else(|| {
+        PngmeError::Usage("could not determine a key directory, pass --dir".into())
+    })?;
+    let store = KeyStore::new(dir);
+    match args.command {
+        KeygenCommand::New { name, force } => {
+            let keys = store.generate(&name, force)?;
+            println!(
+                "Signing key:    {}",
+                store.signing_key_path(&name).display()
+            );
+            println!(
+                "Encryption key: {}",
+                store.encryption_key_path(&name).display()
+            );
+            print_public_keys(&keys);
+        }
+        KeygenCommand::List => {
+            for name in store.names()? {
+                println!("{}", name);
+            }
+        }
+        KeygenCommand::Export { name } => print_public_keys(&store.public_keys(&name)?),
+    }
+    Ok(())
+}
+
+fn fs_command(args: FsArgs) -> Result<()> {
+    match args.command {
+        FsCommand::Add {
+            path,
+            name,
+            file,
+            force,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            let data = fs::read(file.as_deref().unwrap_or(Path::new(&name)))?;
+            FileSystem::load(&png)?.add(&mut png, &name, &data, force)?;
+            write_png(output, &mut png, &args.touch)
+        }
+        FsCommand::Extract { path, name, file } => {
+            let png = read_png(&path)?;
+            let data = FileSystem::load(&png)?.read(&png, &name)?;
+            match file.as_deref().unwrap_or(Path::new(&name)) {
+                file if is_stdio(file) => {
+                    let mut stdout = io::stdout().lock();
+                    stdout.write_all(&data)?;
+                    Ok(stdout.flush()?)
+                }
+                file => Ok(fs::write(file, data)?),
+            }
+        }
+        FsCommand::Ls { path } => {
+            let png = read_png(&path)?;
+            for entry in FileSystem::load(&png)?.entries() {
+                println!("{:>10}  {}", entry.len, entry.name);
+            }
+            Ok(())
+        }
+        FsCommand::Rm {
+            path,
+            name,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            let entry = FileSystem::load(&png)?.remove(&mut png, &name)?;
+            write_png(output, &mut png, &args.touch)?;
+            eprintln!("Removed {} ({} bytes)", entry.name, entry.len);
+            Ok(())
+        }
+    }
+}
+
+fn text_command(args: TextArgs) -> Result<()> {
+    match args.command {
+        TextCommand::Set {
+            path,
+            keyword,
+            value,
+            compressed,
+            lang,
+            translated_keyword,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            let entry = TextEntry {
+                compressed,
+                international: lang.map(|language| International {
+                    language,
+                    translated_keyword: translated_keyword.unwrap_or_default(),
+                }),
+                ..TextEntry::new(keyword, value)
+            };
+            text::set(&mut png, &entry)?;
+            write_png(output, &mut png, &args.touch)
+        }
+        TextCommand::Get { path, keyword } => {
+            let png = read_png(&path)?;
+            let value = text::get(&png, &keyword)?.ok_or_else(|| {
+                PngmeError::ChunkNotFound(format!("no text keyword {:?}", keyword))
+            })?;
+            println!("{}", value);
+            Ok(())
+        }
+        TextCommand::List { path } => {
+            let png = read_png(&path)?;
+            for entry in text::entries(&png)? {
+                match &entry.international {
+                    Some(International {
+                        language,
+                        translated_keyword,
+                    }) if !language.is_empty() || !translated_keyword.is_empty() => println!(
+                        "{} [{}] {}: {}",
+                        entry.keyword, language, translated_keyword, entry.text
+                    ),
+                    _ => println!("{}: {}", entry.keyword, entry.text),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn exif_command(args: ExifArgs) -> Result<()> {
+    match args.command {
+        ExifCommand::Extract { path, file } => {
+            let png = read_png(&path)?;
+            let data = exif::get(&png)
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no eXIf chunk".into()))?;
+            match file {
+                Some(file) => Ok(fs::write(file, data)?),
+                None => {
+                    let mut stdout = io::stdout().lock();
+                    stdout.write_all(data)?;
+                    Ok(stdout.flush()?)
+                }
+            }
+        }
+        ExifCommand::Inject {
+            path,
+            file,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            exif::set(&mut png, &fs::read(file)?)?;
+            write_png(output, &mut png, &args.touch)
+        }
+        ExifCommand::Show { path, all } => {
+            let png = read_png(&path)?;
+            let parsed =
+                exif::parse(exif::get(&png).ok_or_else(|| {
+                    PngmeError::ChunkNotFound("the file has no eXIf chunk".into())
+                })?)?;
+            if all {
+                for field in parsed.fields() {
+                    println!(
+                        "{} ({}): {}",
+                        field.tag,
+                        field.ifd_num,
+                        field.display_value().with_unit(&parsed)
+                    );
+                }
+            } else {
+                for (label, value) in exif::summary(&parsed) {
+                    println!("{:<13} {}", format!("{}:", label), value);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn xmp_command(args: XmpArgs) -> Result<()> {
+    match args.command {
+        XmpCommand::Get { path, pretty } => {
+            let png = read_png(&path)?;
+            let packet = xmp::get(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no XMP packet".into()))?;
+            match pretty {
+                true => println!("{}", xmp::pretty(&packet)?),
+                false => println!("{}", packet),
+            }
+            Ok(())
+        }
+        XmpCommand::Set {
+            path,
+            file,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            xmp::set(&mut png, &fs::read_to_string(file)?)?;
+            write_png(output, &mut png, &args.touch)
+        }
+        XmpCommand::Strip {
+            path,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            if !xmp::strip(&mut png) {
+                return Err(PngmeError::ChunkNotFound("the file has no XMP packet".into()).into());
+            }
+            write_png(output, &mut png, &args.touch)
+        }
+    }
+}
+
+fn time_command(args: TimeArgs) -> Result<()> {
+    match args.command {
+        TimeCommand::Get { path } => {
+            let png = read_png(&path)?;
+            let time = timestamp::get(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no tIME chunk".into()))?;
+            println!("{}", time);
+            Ok(())
+        }
+        TimeCommand::Set {
+            path,
+            time,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            timestamp::set(&mut png, time)?;
+            write_png(output, &mut png, &TouchArgs::default())
+        }
+    }
+}
+
+fn dpi_command(args: DpiArgs) -> Result<()> {
+    match args.command {
+        DpiCommand::Get { path } => {
+            let png = read_png(&path)?;
+            let dimensions = phys::get(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no pHYs chunk".into()))?;
+            match dimensions.dpi() {
+                Some((x, y)) if x.round() == y.round() => println!("{:.0} DPI", x),
+                Some((x, y)) => println!("{:.0}x{:.0} DPI", x, y),
+                None => println!(
+                    "{}:{} pixel aspect ratio, no physical unit",
+                    dimensions.x, dimensions.y
+                ),
+            }
+            Ok(())
+        }
+        DpiCommand::Set {
+            path,
+            dpi,
+            y_dpi,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            phys::set(
+                &mut png,
+                PhysicalDimensions::from_dpi(dpi, y_dpi.unwrap_or(dpi))?,
+            )?;
+            write_png(output, &mut png, &args.touch)
+        }
+    }
+}
+
+fn offset_command(args: OffsetArgs) -> Result<()> {
+    match args.command {
+        OffsetCommand::Get { path } => {
+            let png = read_png(&path)?;
+            let offset = extensions::offset(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no oFFs chunk".into()))?;
+            println!("{}", offset);
+            Ok(())
+        }
+        OffsetCommand::Set {
+            path,
+            x,
+            y,
+            micrometres,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            extensions::set_offset(
+                &mut png,
+                Offset {
+                    x,
+                    y,
+                    in_micrometres: micrometres,
+                },
+            )?;
+            write_png(output, &mut png, &args.touch)
+        }
+    }
+}
+
+fn scale_command(args: ScaleArgs) -> Result<()> {
+    match args.command {
+        ScaleCommand::Get { path } => {
+            let png = read_png(&path)?;
+            let scale = extensions::scale(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no sCAL chunk".into()))?;
+            println!("{}", scale);
+            Ok(())
+        }
+        ScaleCommand::Set {
+            path,
+            width,
+            height,
+            radians,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            extensions::set_scale(
+                &mut png,
+                Scale {
+                    width,
+                    height: height.unwrap_or(width),
+                    in_radians: radians,
+                },
+            )?;
+            write_png(output, &mut png, &args.touch)
+        }
+    }
+}
+
+fn calibration_command(args: CalibrationArgs) -> Result<()> {
+    match args.command {
+        CalibrationCommand::Get { path } => {
+            let png = read_png(&path)?;
+            let calibration = extensions::calibration(&png)?
+                .ok_or_else(|| PngmeError::ChunkNotFound("the file has no pCAL chunk".into()))?;
+            println!("{}", calibration);
+            Ok(())
+        }
+        CalibrationCommand::Set {
+            path,
+            name,
+            original_zero,
+            original_max,
+            parameters,
+            equation,
+            unit,
+            output,
+            in_place,
+        } => {
+            let output = output_path(&path, output.as_deref(), in_place)?;
+            let mut png = read_png(&path)?;
+            extensions::set_calibration(
+                &mut png,
+                &Calibration {
+                    name,
+                    original_zero,
+                    original_max,
+                    equation,
+                    unit,
+                    parameters,
+                },
+            )?;
+            write_png(output, &mut png, &args.touch)
+        }
+    }
+}
+
+fn dsig_command(args: DsigArgs) -> Result<()> {
+    match args.command {
+        DsigCommand::List { path } => {
+            let png = read_png(&path)?;
+            let blocks = dsig::blocks(&png)?;
+            if blocks.is_empty() {
+                println!("No dSIG chunks");
+            }
+            for (n, block) in blocks.iter().enumerate() {
+                println!(
+                    "{}: chunks {}..={}, {} signature ({} bytes){}",
+                    n,
+                    block.open_index,
+                    block.close_index,
+                    block.kind(),
+                    block.signature.len(),
+                    if block.covers_whole_file(&png) {
+                        ""
+                    } else {
+                        ", does not cover the whole file"
+                    }
+                );
+            }
+            Ok(())
+        }
+        DsigCommand::Extract { path, file, nth } => {
+            let png = read_png(&path)?;
+            let blocks = dsig::blocks(&png)?;
+            let block = blocks.get(nth).ok_or_else(|| {
+                PngmeError::ChunkNotFound(format!("the file has {} dSIG pairs", blocks.len()))
+            })?;
+            match file {
+                Some(file) => Ok(fs::write(file, &block.signature)?),
+                None => {
+                    let mut stdout = io::stdout().lock();
+                    stdout.write_all(&block.signature)?;
+                    Ok(stdout.flush()?)
+                }
+            }
+        }
+        DsigCommand::Verify { path, pubkey } => {
+            let png = read_png(&path)?;
+            let blocks = dsig::blocks(&png)?;
+            if blocks.is_empty() {
+                return Err(PngmeError::ChunkNotFound("the file has no dSIG chunks".into()).into());
+            }
+            for (n, block) in blocks.iter().enumerate() {
+                block.verify(&png, &pubkey)?;
+                if block.covers_whole_file(&png) {
+                    println!("dSIG pair {} is valid", n);
+                } else {
+                    println!(
+                        "dSIG pair {} is valid but only covers chunks {}..={}",
+                        n, block.open_index, block.close_index
+                    );
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_public_keys(keys: &PublicKeys) {
+    println!("{} signing:    {}", keys.name, keys.signing);
+    println!("{} encryption: {}", keys.name, keys.encryption);
+}
@@ -0,0 +1,327 @@
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::error::{PngmeError, Result};
+
+pub const MAGIC: [u8; 3] = *b"PMZ";
+pub const HEADER_LEN: usize = MAGIC.len() + 1;
+pub const BROTLI_WINDOW: u32 = 22;
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+pub const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+const BUFFER_SIZE: usize = 4096;
+// The most a stream with no declared length may inflate to. Anything bigger is
+// far more likely a decompression bomb than a payload.
+pub const MAX_DECOMPRESSED_LEN: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Zstd,
+    Deflate,
+    Brotli,
+    Lz4,
+}
+
+impl Codec {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Zstd => 1,
+            Self::Deflate => 2,
+            Self::Brotli => 3,
+            Self::Lz4 => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Zstd),
+            2 => Some(Self::Deflate),
+            3 => Some(Self::Brotli),
+            4 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::Deflate => "deflate",
+            Self::Brotli => "brotli",
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    pub fn levels(&self) -> RangeInclusive<i32> {
+        match self {
+            Self::Zstd => zstd::compression_level_range(),
+            Self::Deflate => 0..=9,
+            Self::Brotli => 0..=11,
+            Self::Lz4 => 0..=16,
+        }
+    }
+
+    pub fn default_level(&self) -> i32 {
+        match self {
+            Self::Zstd => zstd::DEFAULT_COMPRESSION_LEVEL,
+            Self::Deflate => 6,
+            Self::Brotli => 11,
+            Self::Lz4 => 0,
+        }
+    }
+
+    pub fn compress_raw(&self, level: i32, data: &[u8]) -> Result<Vec<u8>> {
+        if !self.levels().contains(&level) {
+            return Err(PngmeError::Usage(format!(
+                "{} compression level must be in {}..={}, got {}",
+                self,
+                self.levels().start(),
+                self.levels().end(),
+                level
+            )));
+        }
+        match self {
+            Self::Zstd => Ok(zstd::encode_all(data, level)?),
+            Self::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(
+                    Vec::new(),
+                    flate2::Compression::new(level as u32),
+                );
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            Self::Brotli => {
+                let mut compressed = Vec::new();
+                let mut encoder = brotli::CompressorWriter::new(
+                    &mut compressed,
+                    BUFFER_SIZE,
+                    level as u32,
+                    BROTLI_WINDOW,
+                );
+                encoder.write_all(data)?;
+                drop(encoder);
+                Ok(compressed)
+            }
+            Self::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(level as u32)
+                    .build(Vec::new())?;
+                encoder.write_all(data)?;
+                let (compressed, result) = encoder.finish();
+                result?;
+                Ok(compressed)
+            }
+        }
+    }
+
+    // Fails once the output goes past `limit` bytes, having inflated at most
+    // one byte more than that.
+    pub fn decompress_raw(&self, data: &[u8], limit: u64) -> Result<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Self::Zstd => Box::new(zstd::Decoder::new(data)?),
+            Self::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+            Self::Brotli => Box::new(brotli::Decompressor::new(data, BUFFER_SIZE)),
+            Self::Lz4 => Box::new(lz4::Decoder::new(data)?),
+        };
+        let mut decompressed = Vec::new();
+        decoder.take(limit + 1).read_to_end(&mut decompressed)?;
+        if decompressed.len() as u64 > limit {
+            return Err(PngmeError::Malformed(format!(
+                "{} data inflates to more than {} bytes",
+                self, limit
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+impl std::str::FromStr for Codec {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "zstd" => Ok(Self::Zstd),
+            "deflate" | "zlib" => Ok(Self::Deflate),
+            "brotli" | "br" => Ok(Self::Brotli),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(format!("unknown compression codec {:?}", string)),
+        }
+    }
+}
+
+impl std::fmt::Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub fn compress(codec: Codec, level: Option<i32>, data: &[u8]) -> Result<Vec<u8>> {
+    let compressed = codec.compress_raw(level.unwrap_or_else(|| codec.default_level()), data)?;
+    if codec == Codec::Deflate {
+        // A bare zlib stream is what PNG tooling expects from deflate-compressed chunks.
+        return Ok(compressed);
+    }
+    Ok(MAGIC
+        .iter()
+        .copied()
+        .chain([codec.id()])
+        .chain(compressed)
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    Header(Codec),
+    Magic(Codec),
+}
+
+impl Detection {
+    pub fn codec(&self) -> Codec {
+        match self {
+            Self::Header(codec) | Self::Magic(codec) => *codec,
+        }
+    }
+}
+
+pub fn detect(data: &[u8]) -> Option<Detection> {
+    match data {
+        [m0, m1, m2, id, ..] if [*m0, *m1, *m2] == MAGIC => {
+            Codec::from_id(*id).map(Detection::Header)
+        }
+        [a, b, c, d, ..] if [*a, *b, *c, *d] == ZSTD_MAGIC => Some(Detection::Magic(Codec::Zstd)),
+        [a, b, c, d, ..] if [*a, *b, *c, *d] == LZ4_MAGIC => Some(Detection::Magic(Codec::Lz4)),
+        [cmf, flg, ..] if is_zlib_header(*cmf, *flg) => Some(Detection::Magic(Codec::Deflate)),
+        _ => None,
+    }
+}
+
+pub fn decompress(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    match detect(data) {
+        Some(Detection::Header(codec)) => Ok(Some(
+            codec.decompress_raw(&data[HEADER_LEN..], MAX_DECOMPRESSED_LEN)?,
+        )),
+        // Bare streams can be false positives on raw data, so fall back to it on failure.
+        Some(Detection::Magic(codec)) => Ok(codec.decompress_raw(data, MAX_DECOMPRESSED_LEN).ok()),
+        None => Ok(None),
+    }
+}
+
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf & 0x0f == 8 && cmf >> 4 <= 7 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"{\"key\": \"value\", \"key\": \"value\", \"key\": \"value\"}";
+
+    #[test]
+    fn test_zstd_round_trip() {
+        let compressed = compress(Codec::Zstd, None, TEXT).unwrap();
+        assert_eq!(&compressed[..HEADER_LEN], b"PMZ\x01");
+        assert_eq!(decompress(&compressed).unwrap().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_brotli_round_trip() {
+        let compressed = compress(Codec::Brotli, None, TEXT).unwrap();
+        assert_eq!(&compressed[..HEADER_LEN], b"PMZ\x03");
+        assert_eq!(decompress(&compressed).unwrap().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_deflate_is_a_bare_zlib_stream() {
+        let compressed = compress(Codec::Deflate, None, TEXT).unwrap();
+        let mut inflated = Vec::new();
+        flate2::read::ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut inflated)
+            .unwrap();
+        assert_eq!(inflated, TEXT);
+        assert_eq!(decompress(&compressed).unwrap().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_text_that_looks_like_zlib() {
+        assert_eq!(decompress(b"x^ not really zlib").unwrap(), None);
+    }
+
+    #[test]
+    fn test_lz4_round_trip() {
+        let compressed = compress(Codec::Lz4, None, TEXT).unwrap();
+        assert_eq!(&compressed[..HEADER_LEN], b"PMZ\x04");
+        assert_eq!(decompress(&compressed).unwrap().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_levels() {
+        for codec in [Codec::Zstd, Codec::Deflate, Codec::Brotli, Codec::Lz4] {
+            for level in [*codec.levels().start(), *codec.levels().end()] {
+                let compressed = compress(codec, Some(level), TEXT).unwrap();
+                assert_eq!(decompress(&compressed).unwrap().unwrap(), TEXT);
+            }
+        }
+    }
+
+    #[test]
+    fn test_level_out_of_range() {
+        assert!(compress(Codec::Deflate, Some(10), TEXT).is_err());
+        assert!(compress(Codec::Brotli, Some(-1), TEXT).is_err());
+    }
+
+    #[test]
+    fn test_uncompressed_data() {
+        assert_eq!(decompress(TEXT).unwrap(), None);
+        assert_eq!(decompress(b"PM").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_codec_id_is_raw() {
+        assert_eq!(detect(b"PMZ\xffdata"), None);
+        assert_eq!(decompress(b"PMZ\xffdata").unwrap(), None);
+    }
+
+    #[test]
+    fn test_corrupt_payload_with_header() {
+        assert!(decompress(b"PMZ\x01not zstd").is_err());
+    }
+
+    #[test]
+    fn test_detect_bare_streams() {
+        let zstd = zstd::encode_all(TEXT, 3).unwrap();
+        assert_eq!(detect(&zstd), Some(Detection::Magic(Codec::Zstd)));
+        assert_eq!(decompress(&zstd).unwrap().unwrap(), TEXT);
+
+        let lz4 = compress(Codec::Lz4, None, TEXT).unwrap();
+        assert_eq!(detect(&lz4), Some(Detection::Header(Codec::Lz4)));
+        assert_eq!(
+            detect(&lz4[HEADER_LEN..]),
+            Some(Detection::Magic(Codec::Lz4))
+        );
+        assert_eq!(decompress(&lz4[HEADER_LEN..]).unwrap().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_false_positive_magic_falls_back() {
+        let mut data = ZSTD_MAGIC.to_vec();
+        data.extend_from_slice(b"garbage");
+        assert_eq!(decompress(&data).unwrap(), None);
+    }
+
+    #[test]
+    fn test_codec_from_str() {
+        assert_eq!("zstd".parse::<Codec>().unwrap(), Codec::Zstd);
+        assert!("lzma".parse::<Codec>().is_err());
+    }
+
+    #[test]
+    fn test_decompress_stops_at_the_limit() {
+        let zeros = vec![0u8; 10_000];
+        for codec in [Codec::Zstd, Codec::Deflate, Codec::Brotli, Codec::Lz4] {
+            let compressed = codec.compress_raw(codec.default_level(), &zeros).unwrap();
+            assert_eq!(codec.decompress_raw(&compressed, 10_000).unwrap(), zeros);
+            assert!(matches!(
+                codec.decompress_raw(&compressed, 9_999),
+                Err(PngmeError::Malformed(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use std::io::{Read, Write};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::PngmeError;
+
+pub const SALT_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+pub const KEY_LEN: usize = 32;
+pub const KDF_PARAMS_LEN: usize = 12;
+pub const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+// Upper bounds on the Argon2 parameters. Decryption reads them from the
+// payload, so without limits a crafted file could ask for terabytes of memory
+// or billions of passes.
+pub const MAX_KDF_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+pub const MAX_KDF_ITERATIONS: u32 = 256;
+pub const MAX_KDF_PARALLELISM: u32 = 64;
+
+pub trait Cipher {
+    fn id(&self) -> u8;
+    fn name(&self) -> &'static str;
+    fn encrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError>;
+    fn decrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError>;
+}
+
+pub struct Aes256Gcm;
+
+impl Cipher for Aes256Gcm {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "aes-256-gcm"
+    }
+
+    fn encrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError> {
+        aes_gcm::Aes256Gcm::new(key.into())
+            .encrypt(nonce.into(), plaintext)
+            .map_err(|_| PngmeError::Crypto("encryption failed".into()))
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError> {
+        aes_gcm::Aes256Gcm::new(key.into())
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| PngmeError::Crypto("wrong password or corrupted payload".into()))
+    }
+}
+
+pub struct ChaCha20Poly1305;
+
+impl Cipher for ChaCha20Poly1305 {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "chacha20-poly1305"
+    }
+
+    fn encrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError> {
+        chacha20poly1305::ChaCha20Poly1305::new(key.into())
+            .encrypt(nonce.into(), plaintext)
+            .map_err(|_| PngmeError::Crypto("encryption failed".into()))
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, PngmeError> {
+        chacha20poly1305::ChaCha20Poly1305::new(key.into())
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| PngmeError::Crypto("wrong password or corrupted payload".into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CipherKind {
+    #[default]
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl CipherKind {
+    pub fn cipher(&self) -> Box<dyn Cipher> {
+        match self {
+            Self::Aes256Gcm => Box::new(Aes256Gcm),
+            Self::ChaCha20Poly1305 => Box::new(ChaCha20Poly1305),
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, PngmeError> {
+        [Self::Aes256Gcm, Self::ChaCha20Poly1305]
+            .into_iter()
+            .find(|kind| kind.cipher().id() == id)
+            .ok_or_else(|| PngmeError::Crypto(format!("unknown cipher id {}", id)))
+    }
+}
+
+impl std::str::FromStr for CipherKind {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "aes-256-gcm" | "aes" => Ok(Self::Aes256Gcm),
+            "chacha20-poly1305" | "chacha" => Ok(Self::ChaCha20Poly1305),
+            _ => Err(PngmeError::Crypto(format!("unknown cipher {:?}", string))),
+        }
+    }
+}
+
+impl std::fmt::Display for CipherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cipher().name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    pub fn to_bytes(&self) -> [u8; KDF_PARAMS_LEN] {
+        let mut bytes = [0u8; KDF_PARAMS_LEN];
+        bytes[0..4].copy_from_slice(&self.memory_kib.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.iterations.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.parallelism.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; KDF_PARAMS_LEN]) -> Self {
+        Self {
+            memory_kib: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            iterations: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            parallelism: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+
+    pub fn derive_key(&self, password: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], PngmeError> {
+        if self.memory_kib > MAX_KDF_MEMORY_KIB
+            || self.iterations > MAX_KDF_ITERATIONS
+            || self.parallelism > MAX_KDF_PARALLELISM
+        {
+            return Err(PngmeError::Crypto(format!(
+                "kdf params {} exceed the limits m={},t={},p={}",
+                self, MAX_KDF_MEMORY_KIB, MAX_KDF_ITERATIONS, MAX_KDF_PARALLELISM
+            )));
+        }
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| PngmeError::Crypto(format!("invalid kdf params: {}", e)))?;
+        let mut key = [0u8; KEY_LEN];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| PngmeError::Crypto(format!("key derivation failed: {}", e)))?;
+        Ok(key)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl std::str::FromStr for KdfParams {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut params = Self::default();
+        for pair in string.split(',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| PngmeError::Crypto(format!("expected key=value, got {:?}", pair)))?;
+            let value: u32 = value
+                .parse()
+                .map_err(|_| PngmeError::Crypto(format!("invalid number {:?}", value)))?;
+            match key {
+                "m" => params.memory_kib = value,
+                "t" => params.iterations = value,
+                "p" => params.parallelism = value,
+                _ => return Err(PngmeError::Crypto(format!("unknown kdf param {:?}", key))),
+            }
+        }
+        Ok(params)
+    }
+}
+
+impl std::fmt::Display for KdfParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "m={},t={},p={}",
+            self.memory_kib, self.iterations, self.parallelism
+        )
+    }
+}
+
+pub fn encrypt(
+    kind: CipherKind,
+    kdf: KdfParams,
+    password: &str,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, PngmeError> {
+    let cipher = kind.cipher();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    let key = kdf.derive_key(password, &salt)?;
+    let ciphertext = cipher.encrypt(&key, &nonce, plaintext)?;
+    Ok([cipher.id()]
+        .iter()
+        .chain(kdf.to_bytes().iter())
+        .chain(salt.iter())
+        .chain(nonce.iter())
+        .chain(ciphertext.iter())
+        .copied()
+        .collect())
+}
+
+pub fn decrypt(password: &str, payload: &[u8]) -> Result<Vec<u8>, PngmeError> {
+    if payload.len() < 1 + KDF_PARAMS_LEN + SALT_LEN + NONCE_LEN {
+        return Err(PngmeError::Crypto(
+            "payload too short to hold cipher id, kdf params, salt and nonce".into(),
+        ));
+    }
+    let cipher = CipherKind::from_id(payload[0])?.cipher();
+    let (kdf, rest) = payload[1..].split_at(KDF_PARAMS_LEN);
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = KdfParams::from_bytes(kdf.try_into().unwrap()).derive_key(password, salt)?;
+    cipher.decrypt(&key, nonce.try_into().unwrap(), ciphertext)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecipientKey(PublicKey);
+
+impl RecipientKey {
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        self.0.as_bytes()
+    }
+}
+
+impl std::str::FromStr for RecipientKey {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(Self(PublicKey::from(decode_key(string)?)))
+    }
+}
+
+impl std::fmt::Display for RecipientKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", BASE64.encode(self.as_bytes()))
+    }
+}
+
+#[derive(Clone)]
+pub struct IdentityKey(StaticSecret);
+
+impl IdentityKey {
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn recipient(&self) -> RecipientKey {
+        RecipientKey(PublicKey::from(&self.0))
+    }
+
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0.to_bytes())
+    }
+}
+
+impl std::str::FromStr for IdentityKey {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(Self(StaticSecret::from(decode_key(string)?)))
+    }
+}
+
+pub(crate) fn decode_key(string: &str) -> Result<[u8; KEY_LEN], PngmeError> {
+    BASE64
+        .decode(string.trim())
+        .map_err(|e| PngmeError::Crypto(format!("invalid base64 key: {}", e)))?
+        .try_into()
+        .map_err(|_| PngmeError::Crypto(format!("key must be {} bytes", KEY_LEN)))
+}
+
+fn wrapping_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> [u8; KEY_LEN] {
+    let info: Vec<u8> = ephemeral
+        .as_bytes()
+        .iter()
+        .chain(recipient.as_bytes().iter())
+        .copied()
+        .collect();
+    let mut key = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(None, shared)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+pub fn encrypt_to_recipients(
+    kind: CipherKind,
+    recipients: &[RecipientKey],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, PngmeError> {
+    if recipients.is_empty() || recipients.len() > u8::MAX as usize {
+        return Err(PngmeError::Crypto(format!(
+            "expected between 1 and {} recipients, got {}",
+            u8::MAX,
+            recipients.len()
+        )));
+    }
+    let cipher = kind.cipher();
+    let mut content_key = [0u8; KEY_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut content_key);
+    OsRng.fill_bytes(&mut nonce);
+    let ephemeral_secret = StaticSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+
+    let mut payload = vec![cipher.id()];
+    payload.extend_from_slice(ephemeral.as_bytes());
+    payload.push(recipients.len() as u8);
+    for RecipientKey(recipient) in recipients {
+        let shared = ephemeral_secret.diffie_hellman(recipient);
+        let key = wrapping_key(shared.as_bytes(), &ephemeral, recipient);
+        payload.extend(ChaCha20Poly1305.encrypt(&key, &[0u8; NONCE_LEN], &content_key)?);
+    }
+    payload.extend_from_slice(&nonce);
+    payload.extend(cipher.encrypt(&content_key, &nonce, plaintext)?);
+    Ok(payload)
+}
+
+pub fn decrypt_with_identity(
+    identity: &IdentityKey,
+    payload: &[u8],
+) -> Result<Vec<u8>, PngmeError> {
+    let too_short = || PngmeError::Crypto("payload too short for a recipient header".into());
+    if payload.len() < 2 + KEY_LEN {
+        return Err(too_short());
+    }
+    let cipher = CipherKind::from_id(payload[0])?.cipher();
+    let ephemeral = PublicKey::from(<[u8; KEY_LEN]>::try_from(&payload[1..1 + KEY_LEN]).unwrap());
+    let count = payload[1 + KEY_LEN] as usize;
+    let rest = &payload[2 + KEY_LEN..];
+    if rest.len() < count * WRAPPED_KEY_LEN + NONCE_LEN {
+        return Err(too_short());
+    }
+    let (wrapped_keys, rest) = rest.split_at(count * WRAPPED_KEY_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let shared = identity.0.diffie_hellman(&ephemeral);
+    let key = wrapping_key(shared.as_bytes(), &ephemeral, &identity.recipient().0);
+    let content_key: [u8; KEY_LEN] = wrapped_keys
+        .chunks(WRAPPED_KEY_LEN)
+        .find_map(|wrapped| {
+            ChaCha20Poly1305
+                .decrypt(&key, &[0u8; NONCE_LEN], wrapped)
+                .ok()
+        })
+        .ok_or_else(|| PngmeError::Crypto("payload is not encrypted to this identity".into()))?
+        .try_into()
+        .map_err(|_| PngmeError::Crypto("wrapped key has the wrong length".into()))?;
+    cipher.decrypt(&content_key, nonce.try_into().unwrap(), ciphertext)
+}
+
+pub fn encrypt_age(
+    recipients: &[age::x25519::Recipient],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, PngmeError> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?;
+    let mut ciphertext = Vec::with_capacity(plaintext.len());
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?;
+    writer
+        .write_all(plaintext)
+        .and_then(|_| writer.finish())
+        .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?;
+    Ok(ciphertext)
+}
+
+pub fn decrypt_age(identity_file: &str, payload: &[u8]) -> Result<Vec<u8>, PngmeError> {
+    let identities = age::IdentityFile::from_buffer(identity_file.as_bytes())
+        .map_err(|e| PngmeError::Crypto(format!("age identity file: {}", e)))?
+        .into_identities()
+        .map_err(|e| PngmeError::Crypto(format!("age identity file: {}", e)))?;
+    let decryptor = age::Decryptor::new_buffered(payload)
+        .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?;
+    let mut plaintext = Vec::new();
+    decryptor
+        .decrypt(identities.iter().map(|i| i.as_ref()))
+        .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?
+        .read_to_end(&mut plaintext)
+        .map_err(|e| PngmeError::Crypto(format!("age: {}", e)))?;
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST_KDF: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        for kind in [CipherKind::Aes256Gcm, CipherKind::ChaCha20Poly1305] {
+            let payload = encrypt(kind, FAST_KDF, "hunter2", b"secret message").unwrap();
+            assert_eq!(payload[0], kind.cipher().id());
+            assert_eq!(decrypt("hunter2", &payload).unwrap(), b"secret message");
+        }
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let payload = encrypt(
+            CipherKind::Aes256Gcm,
+            FAST_KDF,
+            "hunter2",
+            b"secret message",
+        )
+        .unwrap();
+        assert!(decrypt("hunter3", &payload).is_err());
+    }
+
+    #[test]
+    fn test_salt_and_nonce_are_random() {
+        let a = encrypt(
+            CipherKind::Aes256Gcm,
+            FAST_KDF,
+            "hunter2",
+            b"secret message",
+        )
+        .unwrap();
+        let b = encrypt(
+            CipherKind::Aes256Gcm,
+            FAST_KDF,
+            "hunter2",
+            b"secret message",
+        )
+        .unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_truncated_payload() {
+        assert!(decrypt("hunter2", &[1u8; 10]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_cipher_id() {
+        let mut payload = encrypt(CipherKind::Aes256Gcm, FAST_KDF, "hunter2", b"secret").unwrap();
+        payload[0] = 0xff;
+        assert!(decrypt("hunter2", &payload).is_err());
+    }
+
+    #[test]
+    fn test_cipher_kind_from_str() {
+        assert_eq!(
+            "chacha20-poly1305".parse::<CipherKind>().unwrap(),
+            CipherKind::ChaCha20Poly1305
+        );
+        assert!("rot13".parse::<CipherKind>().is_err());
+    }
+
+    #[test]
+    fn test_kdf_params_are_stored() {
+        let kdf = KdfParams {
+            memory_kib: 128,
+            iterations: 2,
+            parallelism: 1,
+        };
+        let payload = encrypt(CipherKind::Aes256Gcm, kdf, "hunter2", b"secret").unwrap();
+        let stored = KdfParams::from_bytes(payload[1..1 + KDF_PARAMS_LEN].try_into().unwrap());
+        assert_eq!(stored, kdf);
+        assert_eq!(decrypt("hunter2", &payload).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_kdf_params_from_str() {
+        let kdf: KdfParams = "m=1024,t=4".parse().unwrap();
+        assert_eq!(kdf.memory_kib, 1024);
+        assert_eq!(kdf.iterations, 4);
+        assert_eq!(kdf.parallelism, KdfParams::default().parallelism);
+        assert!("m=abc".parse::<KdfParams>().is_err());
+        assert!("x=1".parse::<KdfParams>().is_err());
+        assert!("m".parse::<KdfParams>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_kdf_params() {
+        let kdf = KdfParams {
+            memory_kib: 1,
+            iterations: 0,
+            parallelism: 1,
+        };
+        assert!(encrypt(CipherKind::Aes256Gcm, kdf, "hunter2", b"secret").is_err());
+    }
+
+    #[test]
+    fn test_kdf_limits_are_checked_before_deriving() {
+        let kdf = KdfParams {
+            memory_kib: 128,
+            iterations: 2,
+            parallelism: 1,
+        };
+        let payload = encrypt(CipherKind::Aes256Gcm, kdf, "hunter2", b"secret").unwrap();
+        for (offset, limit) in [
+            (1, MAX_KDF_MEMORY_KIB),
+            (5, MAX_KDF_ITERATIONS),
+            (9, MAX_KDF_PARALLELISM),
+        ] {
+            let mut crafted = payload.clone();
+            crafted[offset..offset + 4].copy_from_slice(&(limit + 1).to_be_bytes());
+            assert!(matches!(
+                decrypt("hunter2", &crafted),
+                Err(PngmeError::Crypto(message)) if message.contains("exceed the limits")
+            ));
+        }
+    }
+
+    #[test]
+    fn test_recipients_round_trip() {
+        let alice = IdentityKey::generate();
+        let bob = IdentityKey::generate();
+        let recipients = [alice.recipient(), bob.recipient()];
+        for kind in [CipherKind::Aes256Gcm, CipherKind::ChaCha20Poly1305] {
+            let payload = encrypt_to_recipients(kind, &recipients, b"for both").unwrap();
+            assert_eq!(
+                decrypt_with_identity(&alice, &payload).unwrap(),
+                b"for both"
+            );
+            assert_eq!(decrypt_with_identity(&bob, &payload).unwrap(), b"for both");
+        }
+    }
+
+    #[test]
+    fn test_non_recipient_cannot_decrypt() {
+        let alice = IdentityKey::generate();
+        let eve = IdentityKey::generate();
+        let payload =
+            encrypt_to_recipients(CipherKind::Aes256Gcm, &[alice.recipient()], b"secret").unwrap();
+        assert!(decrypt_with_identity(&eve, &payload).is_err());
+    }
+
+    #[test]
+    fn test_no_recipients() {
+        assert!(encrypt_to_recipients(CipherKind::Aes256Gcm, &[], b"secret").is_err());
+    }
+
+    #[test]
+    fn test_key_string_round_trip() {
+        let identity = IdentityKey::generate();
+        let parsed: IdentityKey = identity.to_base64().parse().unwrap();
+        assert_eq!(parsed.recipient(), identity.recipient());
+        let recipient: RecipientKey = identity.recipient().to_string().parse().unwrap();
+        assert_eq!(recipient, identity.recipient());
+        assert!("not base64!".parse::<RecipientKey>().is_err());
+        assert!("AAAA".parse::<RecipientKey>().is_err());
+    }
+
+    #[test]
+    fn test_age_round_trip() {
+        use age::secrecy::ExposeSecret;
+        let identity = age::x25519::Identity::generate();
+        let payload = encrypt_age(&[identity.to_public()], b"age secret").unwrap();
+        assert!(payload.starts_with(b"age-encryption.org/v1"));
+        let identity_file = identity.to_string();
+        let identity_file = identity_file.expose_secret();
+        assert_eq!(decrypt_age(identity_file, &payload).unwrap(), b"age secret");
+    }
+
+    #[test]
+    fn test_age_wrong_identity() {
+        use age::secrecy::ExposeSecret;
+        let identity = age::x25519::Identity::generate();
+        let payload = encrypt_age(&[identity.to_public()], b"age secret").unwrap();
+        let other = age::x25519::Identity::generate().to_string();
+        assert!(decrypt_age(other.expose_secret(), &payload).is_err());
+    }
+}
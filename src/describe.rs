@@ -0,0 +1,178 @@
+use crate::chunk::Chunk;
+use crate::colour::{self, Chromaticities, Gamma, RenderingIntent, SignificantBits};
+use crate::error::{PngmeError, Result};
+use crate::extensions::{self, Calibration, Offset, Scale};
+use crate::ihdr::{self, ImageHeader};
+use crate::palette::{self, Background, Histogram, Palette, SuggestedPalette, Transparency};
+use crate::png::Png;
+use crate::registry;
+
+#[derive(Debug, Default)]
+pub struct Context {
+    pub header: Option<ImageHeader>,
+    pub palette: Option<Palette>,
+    pub palette_limit: Option<usize>,
+}
+
+impl Context {
+    pub fn for_png(png: &Png, palette_limit: Option<usize>) -> Self {
+        Self {
+            header: ihdr::get(png),
+            palette: palette::get(png),
+            palette_limit,
+        }
+    }
+
+    fn header(&self, chunk_type: &str) -> Result<&ImageHeader> {
+        self.header.as_ref().ok_or_else(|| {
+            PngmeError::Malformed(format!(
+                "{} cannot be decoded without a valid IHDR",
+                chunk_type
+            ))
+        })
+    }
+}
+
+pub fn describe(chunk: &Chunk, context: &Context) -> Option<String> {
+    let data = chunk.data();
+    let chunk_type = chunk.chunk_type().to_string();
+    let description: Result<String> = match chunk_type.as_str() {
+        ihdr::IHDR_CHUNK_TYPE => ImageHeader::from_bytes(data).map(|header| header.to_string()),
+        colour::GAMMA_CHUNK_TYPE => Gamma::from_bytes(data).map(|gamma| gamma.to_string()),
+        colour::CHROMATICITIES_CHUNK_TYPE => {
+            Chromaticities::from_bytes(data).map(|c| c.to_string())
+        }
+        colour::SRGB_CHUNK_TYPE => {
+            RenderingIntent::from_bytes(data).map(|intent| intent.to_string())
+        }
+        palette::PALETTE_CHUNK_TYPE => {
+            Palette::from_bytes(data).map(|palette| palette.describe(context.palette_limit))
+        }
+        palette::TRANSPARENCY_CHUNK_TYPE => context.header(&chunk_type).and_then(|header| {
+            Transparency::from_bytes(data, header)
+                .map(|trns| trns.describe(header.bit_depth, context.palette_limit))
+        }),
+        palette::BACKGROUND_CHUNK_TYPE => context.header(&chunk_type).and_then(|header| {
+            Background::from_bytes(data, header)
+                .map(|bkgd| bkgd.describe(header.bit_depth, context.palette.as_ref()))
+        }),
+        palette::SUGGESTED_PALETTE_CHUNK_TYPE => {
+            SuggestedPalette::from_bytes(data).map(|splt| splt.describe(context.palette_limit))
+        }
+        palette::HISTOGRAM_CHUNK_TYPE => Histogram::from_bytes(data, context.palette.as_ref())
+            .map(|hist| hist.describe(context.palette_limit)),
+        colour::SIGNIFICANT_BITS_CHUNK_TYPE => context
+            .header(&chunk_type)
+            .and_then(|header| SignificantBits::from_bytes(data, header))
+            .map(|bits| bits.to_string()),
+        extensions::OFFSET_CHUNK_TYPE => Offset::from_bytes(data).map(|offset| offset.to_string()),
+        extensions::CALIBRATION_CHUNK_TYPE => Calibration::from_bytes(data).map(|c| c.to_string()),
+        extensions::SCALE_CHUNK_TYPE => Scale::from_bytes(data).map(|scale| scale.to_string()),
+        _ => return None,
+    };
+    Some(description.unwrap_or_else(|e| format!("malformed: {}", e)))
+}
+
+pub fn label(chunk: &Chunk) -> &'static str {
+    registry::lookup(chunk.chunk_type()).map_or("unknown", |known| known.description)
+}
+
+pub fn annotate(chunk: &Chunk, context: &Context) -> String {
+    match describe(chunk, context) {
+        Some(description) => format!("{}: {}", label(chunk), description),
+        None => label(chunk).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+
+    fn indexed_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 3, 0, 0, 0]),
+            chunk("PLTE", &[255, 0, 0, 0, 255, 0, 0, 0, 255]),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_describe_known_chunks() {
+        let context = Context::default();
+        assert_eq!(
+            describe(&chunk("gAMA", &100_000u32.to_be_bytes()), &context).unwrap(),
+            "gamma 1.00000 (display exponent 1.00)"
+        );
+        assert_eq!(
+            describe(&chunk("sRGB", &[1]), &context).unwrap(),
+            "sRGB, relative colorimetric rendering intent"
+        );
+    }
+
+    #[test]
+    fn test_describe_palette_chunks() {
+        let context = Context::for_png(&indexed_png(), Some(2));
+        assert_eq!(
+            describe(&chunk("PLTE", &[255, 0, 0, 0, 255, 0, 0, 0, 255]), &context).unwrap(),
+            "3 entries: #ff0000, #00ff00, ... (1 more)"
+        );
+        assert_eq!(
+            describe(&chunk("tRNS", &[0, 255]), &context).unwrap(),
+            "alpha for 2 palette entries: 0, 255"
+        );
+        assert_eq!(
+            describe(&chunk("bKGD", &[2]), &context).unwrap(),
+            "background palette index 2 (#0000ff)"
+        );
+    }
+
+    #[test]
+    fn test_describe_palette_extras() {
+        let context = Context::for_png(&indexed_png(), None);
+        assert_eq!(
+            describe(&chunk("hIST", &[0, 1, 0, 2, 0, 3]), &context).unwrap(),
+            "frequencies for 3 palette entries: 1, 2, 3"
+        );
+        assert_eq!(
+            describe(&chunk("sBIT", &[4, 4, 4]), &context).unwrap(),
+            "significant bits: red 4, green 4, blue 4"
+        );
+        assert!(describe(&chunk("hIST", &[0, 1]), &context)
+            .unwrap()
+            .starts_with("malformed: "));
+    }
+
+    #[test]
+    fn test_describe_needs_header() {
+        assert!(describe(&chunk("bKGD", &[2]), &Context::default())
+            .unwrap()
+            .contains("without a valid IHDR"));
+    }
+
+    #[test]
+    fn test_describe_malformed() {
+        assert!(describe(&chunk("sRGB", &[9]), &Context::default())
+            .unwrap()
+            .starts_with("malformed: "));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let context = Context::default();
+        assert_eq!(
+            annotate(&chunk("sRGB", &[0]), &context),
+            "Standard RGB colour space: sRGB, perceptual rendering intent"
+        );
+        assert_eq!(
+            annotate(&chunk("iDOT", &[0; 28]), &context),
+            "Apple multithreaded decoding offsets"
+        );
+        assert_eq!(annotate(&chunk("ruSt", b"data"), &context), "unknown");
+    }
+
+    #[test]
+    fn test_describe_unknown() {
+        assert_eq!(describe(&chunk("ruSt", b"data"), &Context::default()), None);
+    }
+}
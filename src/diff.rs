@@ -0,0 +1,211 @@
+use serde::Serialize;
+
+use crate::chunk::Chunk;
+use crate::png::Png;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Unchanged,
+    Modified,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChunkSide {
+    pub index: usize,
+    pub length: u32,
+    pub crc: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkChange {
+    pub kind: ChangeKind,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub old: Option<ChunkSide>,
+    pub new: Option<ChunkSide>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bytes: Vec<ByteChange>,
+}
+
+// A run of payload bytes that differ between the old and new chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ByteChange {
+    pub offset: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+fn side(index: usize, chunk: &Chunk) -> ChunkSide {
+    ChunkSide {
+        index,
+        length: chunk.length(),
+        crc: chunk.crc(),
+    }
+}
+
+// Pairs the Nth chunk of each type in `old` with the Nth chunk of that type in
+// `new`, so an edited text chunk shows up as modified even if other chunks
+// were inserted before it. Changes are listed in the order of `new`, followed
+// by the chunks that only `old` has. With `payloads`, modified chunks also
+// carry a byte-level diff of their data.
+pub fn diff(old: &Png, new: &Png, payloads: bool) -> Vec<ChunkChange> {
+    let mut matched = vec![false; old.chunks().len()];
+    let mut changes = Vec::new();
+    for (new_index, chunk) in new.chunks().iter().enumerate() {
+        let occurrence = new.chunks()[..new_index]
+            .iter()
+            .filter(|c| c.chunk_type() == chunk.chunk_type())
+            .count();
+        let counterpart = old
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.chunk_type() == chunk.chunk_type())
+            .nth(occurrence);
+        let mut change = ChunkChange {
+            kind: ChangeKind::Added,
+            chunk_type: chunk.chunk_type().to_string(),
+            old: None,
+            new: Some(side(new_index, chunk)),
+            bytes: Vec::new(),
+        };
+        if let Some((old_index, old_chunk)) = counterpart {
+            matched[old_index] = true;
+            change.old = Some(side(old_index, old_chunk));
+            change.kind = if old_chunk.data() == chunk.data() {
+                ChangeKind::Unchanged
+            } else {
+                ChangeKind::Modified
+            };
+            if payloads {
+                change.bytes = byte_diff(old_chunk.data(), chunk.data());
+            }
+        }
+        changes.push(change);
+    }
+    for (old_index, chunk) in old.chunks().iter().enumerate() {
+        if !matched[old_index] {
+            changes.push(ChunkChange {
+                kind: ChangeKind::Removed,
+                chunk_type: chunk.chunk_type().to_string(),
+                old: Some(side(old_index, chunk)),
+                new: None,
+                bytes: Vec::new(),
+            });
+        }
+    }
+    changes
+}
+
+// Compares two payloads position by position, merging adjacent differing
+// bytes into runs. Bytes past the end of the shorter payload form the last run.
+pub fn byte_diff(old: &[u8], new: &[u8]) -> Vec<ByteChange> {
+    let mut changes: Vec<ByteChange> = Vec::new();
+    for offset in 0..old.len().max(new.len()) {
+        let (a, b) = (old.get(offset), new.get(offset));
+        if a == b {
+            continue;
+        }
+        match changes.last_mut() {
+            Some(last) if last.offset + last.old.len().max(last.new.len()) == offset => {
+                last.old.extend(a);
+                last.new.extend(b);
+            }
+            _ => changes.push(ByteChange {
+                offset,
+                old: a.into_iter().copied().collect(),
+                new: b.into_iter().copied().collect(),
+            }),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png(chunks: &[(&str, &[u8])]) -> Png {
+        Png::from_chunks(
+            chunks
+                .iter()
+                .map(|(t, data)| Chunk::new(ChunkType::from_str(t).unwrap(), data.to_vec()))
+                .collect(),
+        )
+    }
+
+    fn kinds(changes: &[ChunkChange]) -> Vec<(ChangeKind, &str)> {
+        changes
+            .iter()
+            .map(|change| (change.kind, change.chunk_type.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_chunks() {
+        let old = png(&[
+            ("IHDR", b"header"),
+            ("tEXt", b"a\0old"),
+            ("tIME", b"time"),
+            ("IDAT", b"pixels"),
+            ("IEND", b""),
+        ]);
+        let new = png(&[
+            ("IHDR", b"header"),
+            ("pHYs", b"dpi"),
+            ("tEXt", b"a\0new"),
+            ("IDAT", b"pixels"),
+            ("IEND", b""),
+        ]);
+        let changes = diff(&old, &new, true);
+        assert_eq!(
+            kinds(&changes),
+            [
+                (ChangeKind::Unchanged, "IHDR"),
+                (ChangeKind::Added, "pHYs"),
+                (ChangeKind::Modified, "tEXt"),
+                (ChangeKind::Unchanged, "IDAT"),
+                (ChangeKind::Unchanged, "IEND"),
+                (ChangeKind::Removed, "tIME"),
+            ]
+        );
+        assert_eq!(changes[2].old.unwrap().index, 1);
+        assert_eq!(changes[2].new.unwrap().index, 2);
+        assert!(changes[5].new.is_none());
+        assert_eq!(changes[2].bytes.len(), 1);
+        assert!(diff(&old, &new, false)[2].bytes.is_empty());
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let file = png(&[("IHDR", b"header"), ("IEND", b"")]);
+        assert!(diff(&file, &file, false)
+            .iter()
+            .all(|change| change.kind == ChangeKind::Unchanged));
+    }
+
+    #[test]
+    fn test_byte_diff() {
+        assert_eq!(
+            byte_diff(b"abcdef", b"aXYdeZgh"),
+            [
+                ByteChange {
+                    offset: 1,
+                    old: b"bc".to_vec(),
+                    new: b"XY".to_vec()
+                },
+                ByteChange {
+                    offset: 5,
+                    old: b"f".to_vec(),
+                    new: b"Zgh".to_vec()
+                },
+            ]
+        );
+        assert!(byte_diff(b"same", b"same").is_empty());
+    }
+}
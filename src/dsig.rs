@@ -0,0 +1,254 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+use crate::signing::{SigningKey, VerifyingKey};
+
+pub const DSIG_CHUNK_TYPE: &str = "dSIG";
+pub const MARKER_LEN: usize = 16;
+const ED25519_SIGNATURE_LEN: usize = 64;
+const DER_SEQUENCE: u8 = 0x30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    Ed25519,
+    Pkcs7,
+    Unknown,
+}
+
+impl std::fmt::Display for SignatureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Ed25519 => "ed25519",
+            Self::Pkcs7 => "pkcs7",
+            Self::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A dSIG pair brackets the signed chunks: the opening chunk holds only a
+// marker, the closing chunk repeats it and carries the signature over every
+// chunk in between.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureBlock {
+    pub marker: [u8; MARKER_LEN],
+    pub open_index: usize,
+    pub close_index: usize,
+    pub signature: Vec<u8>,
+}
+
+impl SignatureBlock {
+    pub fn kind(&self) -> SignatureKind {
+        match self.signature.first() {
+            _ if self.signature.len() == ED25519_SIGNATURE_LEN => SignatureKind::Ed25519,
+            Some(&DER_SEQUENCE) => SignatureKind::Pkcs7,
+            _ => SignatureKind::Unknown,
+        }
+    }
+
+    pub fn signed_bytes(&self, png: &Png) -> Vec<u8> {
+        png.chunks()[self.open_index + 1..self.close_index]
+            .iter()
+            .flat_map(Chunk::as_bytes)
+            .collect()
+    }
+
+    // The spec places the pair directly after IHDR and directly before IEND so
+    // that every other chunk is covered.
+    pub fn covers_whole_file(&self, png: &Png) -> bool {
+        let is_type = |index: usize, chunk_type: &str| {
+            png.chunks()
+                .get(index)
+                .is_some_and(|chunk| chunk.chunk_type().to_string() == chunk_type)
+        };
+        self.open_index == 1 && is_type(0, "IHDR") && is_type(self.close_index + 1, "IEND")
+    }
+
+    pub fn verify(&self, png: &Png, key: &VerifyingKey) -> std::result::Result<(), PngmeError> {
+        if self.kind() != SignatureKind::Ed25519 {
+            return Err(PngmeError::Crypto(format!(
+                "cannot validate a {} dSIG signature, only ed25519",
+                self.kind()
+            )));
+        }
+        key.verify_bytes(&self.signed_bytes(png), &self.signature)
+    }
+}
+
+pub fn blocks(png: &Png) -> Result<Vec<SignatureBlock>> {
+    let mut open: Vec<(usize, [u8; MARKER_LEN])> = Vec::new();
+    let mut blocks = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if chunk.chunk_type().to_string() != DSIG_CHUNK_TYPE {
+            continue;
+        }
+        let data = chunk.data();
+        if data.len() < MARKER_LEN {
+            return Err(PngmeError::Malformed(format!(
+                "dSIG chunk #{} is shorter than its {}-byte marker",
+                index, MARKER_LEN
+            )));
+        }
+        let marker: [u8; MARKER_LEN] = data[..MARKER_LEN].try_into().unwrap();
+        if data.len() == MARKER_LEN {
+            open.push((index, marker));
+            continue;
+        }
+        let position = open
+            .iter()
+            .rposition(|(_, m)| *m == marker)
+            .ok_or_else(|| {
+                PngmeError::Malformed(format!("dSIG chunk #{} has no opening chunk", index))
+            })?;
+        let (open_index, _) = open.remove(position);
+        blocks.push(SignatureBlock {
+            marker,
+            open_index,
+            close_index: index,
+            signature: data[MARKER_LEN..].to_vec(),
+        });
+    }
+    if let Some((index, _)) = open.first() {
+        return Err(PngmeError::Malformed(format!(
+            "dSIG chunk #{} has no closing chunk",
+            index
+        )));
+    }
+    Ok(blocks)
+}
+
+pub fn sign(png: &mut Png, key: &SigningKey) -> Result<SignatureBlock> {
+    // Check before touching the file, so a refused signing leaves it as it was.
+    let first = png
+        .chunks()
+        .iter()
+        .map(|c| c.chunk_type().to_string())
+        .find(|chunk_type| chunk_type != DSIG_CHUNK_TYPE);
+    if first.as_deref() != Some("IHDR") {
+        return Err(PngmeError::Malformed(
+            "dSIG signing needs IHDR as the first chunk".into(),
+        ));
+    }
+    png.remove_chunks(DSIG_CHUNK_TYPE);
+    let mut marker = [0u8; MARKER_LEN];
+    OsRng.fill_bytes(&mut marker);
+    png.insert_chunk(1, Chunk::new(DSIG_CHUNK_TYPE.parse()?, marker.to_vec()))?;
+    let close_index = png.append_index();
+    let mut block = SignatureBlock {
+        marker,
+        open_index: 1,
+        close_index,
+        signature: Vec::new(),
+    };
+    block.signature = key.sign_bytes(&block.signed_bytes(png)).to_vec();
+    let data = marker.iter().chain(&block.signature).copied().collect();
+    png.insert_chunk(close_index, Chunk::new(DSIG_CHUNK_TYPE.parse()?, data))?;
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            chunk("IDAT", b"pixels"),
+            chunk("ruSt", b"message"),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::generate();
+        let mut png = testing_png();
+        sign(&mut png, &key).unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "dSIG", "IDAT", "ruSt", "dSIG", "IEND"]);
+
+        let found = blocks(&png).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind(), SignatureKind::Ed25519);
+        assert!(found[0].covers_whole_file(&png));
+        assert!(found[0].verify(&png, &key.verifying_key()).is_ok());
+        assert!(found[0]
+            .verify(&png, &SigningKey::generate().verifying_key())
+            .is_err());
+    }
+
+    #[test]
+    fn test_tampered_chunk_fails() {
+        let key = SigningKey::generate();
+        let mut png = testing_png();
+        sign(&mut png, &key).unwrap();
+        png.remove_chunks("ruSt");
+        png.insert_chunk(3, chunk("ruSt", b"tampered")).unwrap();
+        let block = &blocks(&png).unwrap()[0];
+        assert!(block.verify(&png, &key.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_resigning_replaces_pair() {
+        let key = SigningKey::generate();
+        let mut png = testing_png();
+        sign(&mut png, &key).unwrap();
+        sign(&mut png, &key).unwrap();
+        assert_eq!(png.chunks_by_type(DSIG_CHUNK_TYPE).count(), 2);
+        assert!(blocks(&png).unwrap()[0]
+            .verify(&png, &key.verifying_key())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_refused_signing_leaves_file_alone() {
+        let key = SigningKey::generate();
+        let mut png = testing_png();
+        sign(&mut png, &key).unwrap();
+        let ihdr = png.remove_chunk_at(0).unwrap();
+        png.push_chunk(ihdr);
+        let before = png.clone();
+        assert!(sign(&mut png, &key).is_err());
+        assert_eq!(png, before);
+    }
+
+    #[test]
+    fn test_foreign_pkcs7_block() {
+        let marker = [7u8; MARKER_LEN];
+        let mut closing = marker.to_vec();
+        closing.extend([DER_SEQUENCE, 0x82, 0x01, 0x00]);
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("dSIG", &marker),
+            chunk("IDAT", b"pixels"),
+            chunk("dSIG", &closing),
+            chunk("IEND", &[]),
+        ]);
+        let block = &blocks(&png).unwrap()[0];
+        assert_eq!(block.kind(), SignatureKind::Pkcs7);
+        assert_eq!(block.signature, [DER_SEQUENCE, 0x82, 0x01, 0x00]);
+        assert!(block
+            .verify(&png, &SigningKey::generate().verifying_key())
+            .is_err());
+    }
+
+    #[test]
+    fn test_unpaired_chunks() {
+        let opening = Png::from_chunks(vec![chunk("dSIG", &[1; MARKER_LEN])]);
+        assert!(blocks(&opening).is_err());
+        let mut closing = vec![2; MARKER_LEN];
+        closing.push(0);
+        let closing = Png::from_chunks(vec![chunk("dSIG", &closing)]);
+        assert!(blocks(&closing).is_err());
+        let short = Png::from_chunks(vec![chunk("dSIG", &[0; 4])]);
+        assert!(blocks(&short).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub chunks: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub index: usize,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub length: u32,
+    pub crc: u32,
+    pub file: String,
+}
+
+pub fn extract(png: &Png, dir: &Path, include_standard: bool) -> Result<Manifest> {
+    fs::create_dir_all(dir)?;
+    let mut chunks = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if chunk.chunk_type().is_standard() && !include_standard {
+            continue;
+        }
+        let file = format!("{}_{}.bin", index, chunk.chunk_type());
+        fs::write(dir.join(&file), chunk.data())?;
+        chunks.push(ManifestEntry {
+            index,
+            chunk_type: chunk.chunk_type().to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+            file,
+        });
+    }
+    let manifest = Manifest { chunks };
+    fs::write(
+        dir.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+pub fn assemble(dir: &Path, base: Option<&Png>) -> Result<Png> {
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE))?)?;
+    let mut entries = manifest.chunks;
+    entries.sort_by_key(|entry| entry.index);
+    let mut png = match base {
+        Some(base) => Png::from_chunks(
+            base.chunks()
+                .iter()
+                .filter(|chunk| chunk.chunk_type().is_standard())
+                .cloned()
+                .collect(),
+        ),
+        None => Png::from_chunks(Vec::new()),
+    };
+    for entry in entries {
+        let chunk_type: ChunkType = entry.chunk_type.parse()?;
+        let chunk = Chunk::new(chunk_type, fs::read(dir.join(&entry.file))?);
+        png.insert_chunk(entry.index.min(png.chunks().len()), chunk)?;
+    }
+    let types: Vec<String> = png
+        .chunks()
+        .iter()
+        .map(|chunk| chunk.chunk_type().to_string())
+        .collect();
+    if types.first().map(String::as_str) != Some("IHDR") || !types.iter().any(|t| t == "IEND") {
+        return Err(PngmeError::InvalidPng(
+            "assembled chunks must start with IHDR and include IEND, \
+             extract with --include-standard or pass --base"
+                .into(),
+        ));
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunk, ChunkType};
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn test_extract_non_standard() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = extract(&testing_png(), dir.path(), false).unwrap();
+        assert_eq!(manifest.chunks.len(), 1);
+        assert_eq!(manifest.chunks[0].file, "1_ruSt.bin");
+        assert_eq!(fs::read(dir.path().join("1_ruSt.bin")).unwrap(), b"hidden");
+        assert!(!dir.path().join("0_IHDR.bin").exists());
+
+        let written = fs::read_to_string(dir.path().join(MANIFEST_FILE)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Manifest>(&written).unwrap(),
+            manifest
+        );
+        assert!(written.contains("\"type\": \"ruSt\""));
+    }
+
+    #[test]
+    fn test_assemble_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        extract(&testing_png(), dir.path(), true).unwrap();
+        fs::write(dir.path().join("1_ruSt.bin"), b"edited").unwrap();
+        let png = assemble(dir.path(), None).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.chunks()[1].data(), b"edited");
+        assert!(Png::try_from(png.as_bytes().as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_assemble_onto_base() {
+        let dir = tempfile::tempdir().unwrap();
+        extract(&testing_png(), dir.path(), false).unwrap();
+        assert!(assemble(dir.path(), None).is_err());
+
+        let mut base = testing_png();
+        base.remove_first_chunk("ruSt").unwrap();
+        let png = assemble(dir.path(), Some(&base)).unwrap();
+        assert_eq!(png, testing_png());
+    }
+
+    #[test]
+    fn test_extract_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = extract(&testing_png(), dir.path(), true).unwrap();
+        let files: Vec<&str> = manifest.chunks.iter().map(|c| c.file.as_str()).collect();
+        assert_eq!(files, ["0_IHDR.bin", "1_ruSt.bin", "2_IEND.bin"]);
+    }
+}
@@ -0,0 +1,45 @@
+// Shannon entropy in bits per byte: 0 for constant data, close to 8 for
+// encrypted or well-compressed data, and typically 4-5 for English text.
+pub fn shannon(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_data() {
+        assert_eq!(shannon(&[]), 0.0);
+        assert_eq!(shannon(&[7; 100]), 0.0);
+        assert!(shannon(&[7]).is_sign_positive());
+    }
+
+    #[test]
+    fn test_uniform_data() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert!((shannon(&data) - 8.0).abs() < 1e-9);
+        assert!((shannon(b"abab") - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_text_is_lower_than_random() {
+        let text = shannon(b"the quick brown fox jumps over the lazy dog");
+        assert!(text > 3.0 && text < 5.0);
+    }
+}
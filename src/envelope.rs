@@ -0,0 +1,282 @@
+use crate::compression::Codec;
+use crate::error::{PngmeError, Result};
+
+pub const MAGIC: [u8; 5] = *b"PNGME";
+pub const VERSION: u8 = 1;
+
+pub const FLAG_COMPRESSED: u8 = 0b0000_0001;
+pub const FLAG_ENCRYPTED: u8 = 0b0000_0010;
+pub const FLAG_FILENAME: u8 = 0b0000_0100;
+pub const FLAG_MIME: u8 = 0b0000_1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+    Password,
+    Recipients,
+    Age,
+}
+
+impl Encryption {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Password => 1,
+            Self::Recipients => 2,
+            Self::Age => 3,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Password),
+            2 => Some(Self::Recipients),
+            3 => Some(Self::Age),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Password => write!(f, "password"),
+            Self::Recipients => write!(f, "x25519 recipients"),
+            Self::Age => write!(f, "age"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub version: u8,
+    pub codec: Option<Codec>,
+    pub encryption: Option<Encryption>,
+    pub cipher_id: u8,
+    pub filename: Option<String>,
+    pub mime: Option<String>,
+    pub original_len: u64,
+    pub body: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn new(body: Vec<u8>) -> Self {
+        Self {
+            version: VERSION,
+            codec: None,
+            encryption: None,
+            cipher_id: 0,
+            filename: None,
+            mime: None,
+            original_len: body.len() as u64,
+            body,
+        }
+    }
+
+    pub fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.codec.is_some() {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.encryption.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.filename.is_some() {
+            flags |= FLAG_FILENAME;
+        }
+        if self.mime.is_some() {
+            flags |= FLAG_MIME;
+        }
+        flags
+    }
+
+    pub fn is_text(&self) -> Option<bool> {
+        self.mime.as_deref().map(is_text_mime)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.version);
+        bytes.push(self.flags());
+        bytes.push(self.codec.map_or(0, |codec| codec.id()));
+        bytes.push(self.encryption.map_or(0, |encryption| encryption.id()));
+        bytes.push(self.cipher_id);
+        let filename = self.filename.as_deref().unwrap_or_default();
+        let filename_len = u16::try_from(filename.len()).map_err(|_| {
+            PngmeError::Usage(format!("file name must be at most {} bytes", u16::MAX))
+        })?;
+        bytes.extend_from_slice(&filename_len.to_be_bytes());
+        bytes.extend_from_slice(filename.as_bytes());
+        if let Some(mime) = &self.mime {
+            let mime_len = u8::try_from(mime.len()).map_err(|_| {
+                PngmeError::Usage(format!("MIME type must be at most {} bytes", u8::MAX))
+            })?;
+            bytes.push(mime_len);
+            bytes.extend_from_slice(mime.as_bytes());
+        }
+        bytes.extend_from_slice(&self.original_len.to_be_bytes());
+        bytes.extend_from_slice(&self.body);
+        Ok(bytes)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Option<Self>> {
+        if !data.starts_with(&MAGIC) {
+            return Ok(None);
+        }
+        let mut reader = Reader(&data[MAGIC.len()..]);
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(PngmeError::Malformed(format!(
+                "unsupported envelope version {}",
+                version
+            )));
+        }
+        let flags = reader.u8()?;
+        let codec_id = reader.u8()?;
+        let encryption_id = reader.u8()?;
+        let cipher_id = reader.u8()?;
+        let filename_len = u16::from_be_bytes(reader.take(2)?.try_into().unwrap());
+        let filename = reader.take(filename_len as usize)?;
+        let mime = match flags & FLAG_MIME {
+            0 => None,
+            _ => {
+                let mime_len = reader.u8()?;
+                Some(String::from_utf8(reader.take(mime_len as usize)?.to_vec())?)
+            }
+        };
+        let original_len = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+
+        let codec = match flags & FLAG_COMPRESSED {
+            0 => None,
+            _ => Some(Codec::from_id(codec_id).ok_or_else(|| {
+                PngmeError::Malformed(format!("unknown compression codec id {}", codec_id))
+            })?),
+        };
+        let encryption = match flags & FLAG_ENCRYPTED {
+            0 => None,
+            _ => Some(Encryption::from_id(encryption_id).ok_or_else(|| {
+                PngmeError::Malformed(format!("unknown encryption scheme id {}", encryption_id))
+            })?),
+        };
+        let filename = match flags & FLAG_FILENAME {
+            0 => None,
+            _ => Some(String::from_utf8(filename.to_vec())?),
+        };
+        Ok(Some(Self {
+            version,
+            codec,
+            encryption,
+            cipher_id,
+            filename,
+            mime,
+            original_len,
+            body: reader.0.to_vec(),
+        }))
+    }
+}
+
+pub fn is_text_mime(mime: &str) -> bool {
+    let essence = mime
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json" | "application/xml" | "application/javascript" | "application/toml"
+        )
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(PngmeError::Truncated("envelope header".to_string()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_round_trip() {
+        let envelope = Envelope::new(b"hello".to_vec());
+        let bytes = envelope.to_bytes().unwrap();
+        assert!(bytes.starts_with(b"PNGME\x01\x00"));
+        assert_eq!(Envelope::parse(&bytes).unwrap().unwrap(), envelope);
+    }
+
+    #[test]
+    fn test_full_round_trip() {
+        let envelope = Envelope {
+            codec: Some(Codec::Zstd),
+            encryption: Some(Encryption::Password),
+            cipher_id: 2,
+            filename: Some("secret.json".to_string()),
+            mime: Some("application/json".to_string()),
+            original_len: 1234,
+            ..Envelope::new(b"body".to_vec())
+        };
+        assert_eq!(
+            envelope.flags(),
+            FLAG_COMPRESSED | FLAG_ENCRYPTED | FLAG_FILENAME | FLAG_MIME
+        );
+        let parsed = Envelope::parse(&envelope.to_bytes().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed, envelope);
+    }
+
+    #[test]
+    fn test_is_text_mime() {
+        assert!(is_text_mime("text/plain; charset=utf-8"));
+        assert!(is_text_mime("application/json"));
+        assert!(is_text_mime("application/ld+json"));
+        assert!(!is_text_mime("image/png"));
+        assert!(!is_text_mime("application/octet-stream"));
+    }
+
+    #[test]
+    fn test_not_an_envelope() {
+        assert_eq!(Envelope::parse(b"just a message").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = Envelope::new(b"hello".to_vec()).to_bytes().unwrap();
+        bytes[MAGIC.len()] = VERSION + 1;
+        assert!(Envelope::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let bytes = Envelope::new(b"hello".to_vec()).to_bytes().unwrap();
+        assert!(Envelope::parse(&bytes[..10]).is_err());
+    }
+
+    #[test]
+    fn test_over_long_fields_are_rejected() {
+        let envelope = Envelope {
+            mime: Some("é".repeat(200)),
+            ..Envelope::new(b"body".to_vec())
+        };
+        assert!(envelope.to_bytes().is_err());
+        let envelope = Envelope {
+            filename: Some("a".repeat(u16::MAX as usize + 1)),
+            ..Envelope::new(b"body".to_vec())
+        };
+        assert!(envelope.to_bytes().is_err());
+    }
+}
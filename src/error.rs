@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, PngmeError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PngmeError {
+    #[error("invalid PNG signature")]
+    InvalidSignature,
+    #[error("invalid chunk type: {0}")]
+    InvalidChunkType(String),
+    #[error("crc mismatch: expected {expected:#010x}, found {found:#010x}")]
+    CrcMismatch { expected: u32, found: u32 },
+    #[error("truncated data: {0}")]
+    Truncated(String),
+    #[error("malformed chunk: {0}")]
+    Malformed(String),
+    #[error("invalid PNG: {0}")]
+    InvalidPng(String),
+    #[error("{} spec violation(s): {}", .0.len(), .0.join("; "))]
+    SpecViolations(Vec<String>),
+    #[error("{0}")]
+    ChunkNotFound(String),
+    #[error("crypto error: {0}")]
+    Crypto(String),
+    #[error("{0}")]
+    Usage(String),
+    #[error("{0} denied lint finding(s)")]
+    LintDenied(usize),
+    #[error("{failed} of {total} file(s) violate the policy")]
+    PolicyFailed { failed: usize, total: usize },
+    #[error("{failed} of {total} file(s) failed")]
+    BatchFailed { failed: usize, total: usize },
+    #[error("found {chunks} hidden chunk(s) in {files} file(s)")]
+    HiddenChunks { chunks: usize, files: usize },
+    #[error("found {findings} suspicious item(s) in {files} of {total} file(s)")]
+    SuspiciousFiles {
+        findings: usize,
+        files: usize,
+        total: usize,
+    },
+    #[error("chunk #{index} at offset {offset:#X}: {source}")]
+    InChunk {
+        index: usize,
+        offset: u64,
+        #[source]
+        source: Box<PngmeError>,
+    },
+    #[error("{}: {source}", path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        source: Box<PngmeError>,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("keyring: {0}")]
+    Keyring(#[from] keyring::Error),
+}
+
+impl PngmeError {
+    pub fn in_file(path: impl Into<PathBuf>, source: PngmeError) -> Self {
+        Self::File {
+            path: path.into(),
+            source: Box::new(source),
+        }
+    }
+
+    pub fn in_chunk(index: usize, offset: u64, source: PngmeError) -> Self {
+        Self::InChunk {
+            index,
+            offset,
+            source: Box::new(source),
+        }
+    }
+
+    // Where in the file parsing failed, as (chunk index, byte offset).
+    pub fn location(&self) -> Option<(usize, u64)> {
+        match self {
+            Self::InChunk { index, offset, .. } => Some((*index, *offset)),
+            Self::File { source, .. } => source.location(),
+            _ => None,
+        }
+    }
+
+    // A stable name for the kind of failure, looking through file and offset
+    // context to the error underneath.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidSignature => "invalid_signature",
+            Self::InvalidChunkType(_) => "invalid_chunk_type",
+            Self::CrcMismatch { .. } => "crc_mismatch",
+            Self::Truncated(_) => "truncated",
+            Self::Malformed(_) => "malformed",
+            Self::InvalidPng(_) => "invalid_png",
+            Self::SpecViolations(_) => "spec_violation",
+            Self::ChunkNotFound(_) => "chunk_not_found",
+            Self::Crypto(_) => "crypto",
+            Self::Usage(_) => "usage",
+            Self::LintDenied(_) => "lint_denied",
+            Self::PolicyFailed { .. } => "policy_failed",
+            Self::HiddenChunks { .. } => "hidden_chunks",
+            Self::SuspiciousFiles { .. } => "suspicious_files",
+            Self::BatchFailed { .. } => "batch_failed",
+            Self::InChunk { source, .. } => source.code(),
+            Self::File { source, .. } => source.code(),
+            Self::Io(_) => "io",
+            Self::Utf8(_) => "utf8",
+            Self::Json(_) => "json",
+            Self::Keyring(_) => "keyring",
+        }
+    }
+}
+
+pub fn error_code(error: &(dyn std::error::Error + 'static)) -> &'static str {
+    if let Some(error) = error.downcast_ref::<PngmeError>() {
+        error.code()
+    } else if error.is::<std::io::Error>() {
+        "io"
+    } else if error.is::<std::string::FromUtf8Error>() {
+        "utf8"
+    } else {
+        "other"
+    }
+}
@@ -0,0 +1,172 @@
+use ::exif::{Exif, In, Reader, Tag, Value};
+
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const EXIF_CHUNK_TYPE: &str = "eXIf";
+const JPEG_APP1_PREFIX: &[u8] = b"Exif\0\0";
+
+const SUMMARY_TAGS: [(&str, Tag); 9] = [
+    ("Camera make", Tag::Make),
+    ("Camera model", Tag::Model),
+    ("Lens", Tag::LensModel),
+    ("Taken", Tag::DateTimeOriginal),
+    ("Modified", Tag::DateTime),
+    ("Exposure", Tag::ExposureTime),
+    ("Aperture", Tag::FNumber),
+    ("ISO", Tag::PhotographicSensitivity),
+    ("Focal length", Tag::FocalLength),
+];
+
+pub fn get(png: &Png) -> Option<&[u8]> {
+    png.chunk_by_type(EXIF_CHUNK_TYPE).map(Chunk::data)
+}
+
+pub fn set(png: &mut Png, data: &[u8]) -> Result<()> {
+    // Sidecars cut from JPEG APP1 segments carry a prefix that eXIf must not.
+    let data = data.strip_prefix(JPEG_APP1_PREFIX).unwrap_or(data);
+    if !data.starts_with(b"II*\0") && !data.starts_with(b"MM\0*") {
+        return Err(PngmeError::Malformed(
+            "EXIF data must start with a TIFF header".into(),
+        ));
+    }
+    png.set_chunk_before_idat(Chunk::new(EXIF_CHUNK_TYPE.parse()?, data.to_vec()))
+}
+
+pub fn parse(data: &[u8]) -> Result<Exif> {
+    Reader::new()
+        .read_raw(data.to_vec())
+        .map_err(|e| PngmeError::Malformed(format!("EXIF: {}", e)))
+}
+
+pub fn summary(exif: &Exif) -> Vec<(String, String)> {
+    let mut summary: Vec<(String, String)> = SUMMARY_TAGS
+        .iter()
+        .filter_map(|(label, tag)| {
+            let field = exif.get_field(*tag, In::PRIMARY)?;
+            Some((
+                label.to_string(),
+                field.display_value().with_unit(exif).to_string(),
+            ))
+        })
+        .collect();
+    if let Some((latitude, longitude)) = gps_position(exif) {
+        summary.push((
+            "GPS position".to_string(),
+            format!("{:.6}, {:.6}", latitude, longitude),
+        ));
+    }
+    if let Some(field) = exif.get_field(Tag::GPSAltitude, In::PRIMARY) {
+        summary.push((
+            "GPS altitude".to_string(),
+            field.display_value().with_unit(exif).to_string(),
+        ));
+    }
+    summary
+}
+
+pub fn gps_position(exif: &Exif) -> Option<(f64, f64)> {
+    let coordinate = |tag: Tag, reference: Tag, negative: u8| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let degrees = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(part, scale)| part.to_f64() / scale)
+            .sum::<f64>();
+        let sign = match &exif.get_field(reference, In::PRIMARY)?.value {
+            Value::Ascii(values) if values.first()?.first() == Some(&negative) => -1.0,
+            _ => 1.0,
+        };
+        Some(sign * degrees)
+    };
+    Some((
+        coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?,
+        coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkType;
+    use ::exif::{experimental::Writer, Field, Rational};
+    use std::str::FromStr;
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn testing_exif() -> Vec<u8> {
+        let rational = |num| Rational { num, denom: 1 };
+        let fields = [
+            field(Tag::Make, Value::Ascii(vec![b"Pngme".to_vec()])),
+            field(Tag::Model, Value::Ascii(vec![b"Test Camera".to_vec()])),
+            field(
+                Tag::GPSLatitude,
+                Value::Rational(vec![rational(51), rational(30), rational(0)]),
+            ),
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()])),
+            field(
+                Tag::GPSLongitude,
+                Value::Rational(vec![rational(0), rational(6), rational(0)]),
+            ),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"W".to_vec()])),
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut data = std::io::Cursor::new(Vec::new());
+        writer.write(&mut data, false).unwrap();
+        data.into_inner()
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![0; 4]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn test_set_places_before_idat() {
+        let mut png = testing_png();
+        set(&mut png, &testing_exif()).unwrap();
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), EXIF_CHUNK_TYPE);
+        assert_eq!(get(&png).unwrap(), testing_exif());
+
+        set(&mut png, &testing_exif()).unwrap();
+        assert_eq!(png.chunks_by_type(EXIF_CHUNK_TYPE).count(), 1);
+    }
+
+    #[test]
+    fn test_set_strips_jpeg_prefix() {
+        let mut png = testing_png();
+        let data = [JPEG_APP1_PREFIX, &testing_exif()].concat();
+        set(&mut png, &data).unwrap();
+        assert_eq!(get(&png).unwrap(), testing_exif());
+    }
+
+    #[test]
+    fn test_set_rejects_non_tiff() {
+        assert!(set(&mut testing_png(), b"not exif").is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let exif = parse(&testing_exif()).unwrap();
+        let summary = summary(&exif);
+        assert!(summary.contains(&("Camera make".to_string(), "\"Pngme\"".to_string())));
+        let (latitude, longitude) = gps_position(&exif).unwrap();
+        assert!((latitude - 51.5).abs() < 1e-9);
+        assert!((longitude + 0.1).abs() < 1e-9);
+    }
+}
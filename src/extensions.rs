@@ -0,0 +1,420 @@
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+use crate::text::{self, Keyword};
+
+pub const OFFSET_CHUNK_TYPE: &str = "oFFs";
+pub const CALIBRATION_CHUNK_TYPE: &str = "pCAL";
+pub const SCALE_CHUNK_TYPE: &str = "sCAL";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Offset {
+    pub x: i32,
+    pub y: i32,
+    pub in_micrometres: bool,
+}
+
+impl Offset {
+    pub fn to_bytes(&self) -> [u8; 9] {
+        let mut bytes = [0u8; 9];
+        bytes[..4].copy_from_slice(&self.x.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.y.to_be_bytes());
+        bytes[8] = u8::from(self.in_micrometres);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 9] = bytes.try_into().map_err(|_| {
+            PngmeError::Malformed(format!("oFFs chunk should be 9 bytes, got {}", bytes.len()))
+        })?;
+        let in_micrometres = match bytes[8] {
+            0 => false,
+            1 => true,
+            unit => return Err(PngmeError::Malformed(format!("unknown oFFs unit {}", unit))),
+        };
+        Ok(Self {
+            x: i32::from_be_bytes(bytes[..4].try_into().unwrap()),
+            y: i32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            in_micrometres,
+        })
+    }
+}
+
+impl std::fmt::Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = if self.in_micrometres {
+            "micrometres"
+        } else {
+            "pixels"
+        };
+        write!(f, "offset ({}, {}) {}", self.x, self.y, unit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equation {
+    Linear,
+    Exponential,
+    ArbitraryExponential,
+    Hyperbolic,
+}
+
+impl Equation {
+    pub fn parameter_count(&self) -> usize {
+        match self {
+            Self::Linear => 2,
+            Self::Exponential => 3,
+            Self::ArbitraryExponential | Self::Hyperbolic => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Self::Linear),
+            1 => Ok(Self::Exponential),
+            2 => Ok(Self::ArbitraryExponential),
+            3 => Ok(Self::Hyperbolic),
+            _ => Err(PngmeError::Malformed(format!(
+                "unknown pCAL equation type {}",
+                byte
+            ))),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        self as u8
+    }
+}
+
+impl std::str::FromStr for Equation {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "linear" => Ok(Self::Linear),
+            "exponential" => Ok(Self::Exponential),
+            "arbitrary-exponential" => Ok(Self::ArbitraryExponential),
+            "hyperbolic" => Ok(Self::Hyperbolic),
+            _ => Err(format!("unknown pCAL equation {:?}", string)),
+        }
+    }
+}
+
+impl std::fmt::Display for Equation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Linear => "linear",
+            Self::Exponential => "base-e exponential",
+            Self::ArbitraryExponential => "arbitrary-base exponential",
+            Self::Hyperbolic => "hyperbolic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    pub name: Keyword,
+    pub original_zero: i32,
+    pub original_max: i32,
+    pub equation: Equation,
+    pub unit: String,
+    pub parameters: Vec<f64>,
+}
+
+impl Calibration {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.original_zero == self.original_max {
+            return Err(PngmeError::Malformed(
+                "pCAL original zero and max must differ".into(),
+            ));
+        }
+        if self.parameters.len() != self.equation.parameter_count() {
+            return Err(PngmeError::Malformed(format!(
+                "a {} equation takes {} parameters, got {}",
+                self.equation,
+                self.equation.parameter_count(),
+                self.parameters.len()
+            )));
+        }
+        if !self.unit.chars().all(|c| matches!(c, ' '..='~')) {
+            return Err(PngmeError::Malformed(
+                "pCAL unit must be printable ASCII".into(),
+            ));
+        }
+        let mut bytes = self
+            .name
+            .as_str()
+            .chars()
+            .map(|c| c as u8)
+            .collect::<Vec<_>>();
+        bytes.push(0);
+        bytes.extend(self.original_zero.to_be_bytes());
+        bytes.extend(self.original_max.to_be_bytes());
+        bytes.push(self.equation.to_byte());
+        bytes.push(self.parameters.len() as u8);
+        bytes.extend(self.unit.as_bytes());
+        for parameter in &self.parameters {
+            bytes.push(0);
+            bytes.extend(parameter.to_string().as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let separator = bytes.iter().position(|&b| b == 0).ok_or_else(|| {
+            PngmeError::Malformed("pCAL chunk has no calibration name terminator".into())
+        })?;
+        let name: Keyword = text::from_latin1(&bytes[..separator]).parse()?;
+        let rest = &bytes[separator + 1..];
+        if rest.len() < 10 {
+            return Err(PngmeError::Malformed("pCAL chunk is truncated".into()));
+        }
+        let original_zero = i32::from_be_bytes(rest[..4].try_into().unwrap());
+        let original_max = i32::from_be_bytes(rest[4..8].try_into().unwrap());
+        let equation = Equation::from_byte(rest[8])?;
+        let count = usize::from(rest[9]);
+        if count != equation.parameter_count() {
+            return Err(PngmeError::Malformed(format!(
+                "a {} equation takes {} parameters, got {}",
+                equation,
+                equation.parameter_count(),
+                count
+            )));
+        }
+        let mut fields = rest[10..].split(|&b| b == 0);
+        let unit = text::from_latin1(fields.next().unwrap_or_default());
+        let parameters = fields
+            .map(|field| parse_float("pCAL", field))
+            .collect::<Result<Vec<_>>>()?;
+        if parameters.len() != count {
+            return Err(PngmeError::Malformed(format!(
+                "pCAL chunk declares {} parameters but holds {}",
+                count,
+                parameters.len()
+            )));
+        }
+        Ok(Self {
+            name,
+            original_zero,
+            original_max,
+            equation,
+            unit,
+            parameters,
+        })
+    }
+}
+
+impl std::fmt::Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().map(f64::to_string).collect();
+        write!(
+            f,
+            "{:?}: samples {} to {}, {} equation with p = [{}], unit {:?}",
+            self.name.as_str(),
+            self.original_zero,
+            self.original_max,
+            self.equation,
+            parameters.join(", "),
+            self.unit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    pub width: f64,
+    pub height: f64,
+    pub in_radians: bool,
+}
+
+impl Scale {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        for value in [self.width, self.height] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(PngmeError::Malformed(format!(
+                    "sCAL pixel size must be positive, got {}",
+                    value
+                )));
+            }
+        }
+        let mut bytes = vec![if self.in_radians { 2 } else { 1 }];
+        bytes.extend(self.width.to_string().as_bytes());
+        bytes.push(0);
+        bytes.extend(self.height.to_string().as_bytes());
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (in_radians, rest) = match bytes {
+            [1, rest @ ..] => (false, rest),
+            [2, rest @ ..] => (true, rest),
+            [unit, ..] => return Err(PngmeError::Malformed(format!("unknown sCAL unit {}", unit))),
+            [] => return Err(PngmeError::Malformed("sCAL chunk is empty".into())),
+        };
+        let separator = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| PngmeError::Malformed("sCAL chunk has no height".into()))?;
+        let scale = Self {
+            width: parse_float("sCAL", &rest[..separator])?,
+            height: parse_float("sCAL", &rest[separator + 1..])?,
+            in_radians,
+        };
+        if scale.width <= 0.0 || scale.height <= 0.0 {
+            return Err(PngmeError::Malformed(
+                "sCAL pixel size must be positive".into(),
+            ));
+        }
+        Ok(scale)
+    }
+}
+
+impl std::fmt::Display for Scale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = if self.in_radians { "radians" } else { "metres" };
+        write!(f, "pixel size {} x {} {}", self.width, self.height, unit)
+    }
+}
+
+pub fn offset(png: &Png) -> Result<Option<Offset>> {
+    png.chunk_by_type(OFFSET_CHUNK_TYPE)
+        .map(|chunk| Offset::from_bytes(chunk.data()))
+        .transpose()
+}
+
+pub fn set_offset(png: &mut Png, offset: Offset) -> Result<()> {
+    png.set_chunk_before_idat(Chunk::new(
+        OFFSET_CHUNK_TYPE.parse()?,
+        offset.to_bytes().to_vec(),
+    ))
+}
+
+pub fn calibration(png: &Png) -> Result<Option<Calibration>> {
+    png.chunk_by_type(CALIBRATION_CHUNK_TYPE)
+        .map(|chunk| Calibration::from_bytes(chunk.data()))
+        .transpose()
+}
+
+pub fn set_calibration(png: &mut Png, calibration: &Calibration) -> Result<()> {
+    png.set_chunk_before_idat(Chunk::new(
+        CALIBRATION_CHUNK_TYPE.parse()?,
+        calibration.to_bytes()?,
+    ))
+}
+
+pub fn scale(png: &Png) -> Result<Option<Scale>> {
+    png.chunk_by_type(SCALE_CHUNK_TYPE)
+        .map(|chunk| Scale::from_bytes(chunk.data()))
+        .transpose()
+}
+
+pub fn set_scale(png: &mut Png, scale: Scale) -> Result<()> {
+    png.set_chunk_before_idat(Chunk::new(SCALE_CHUNK_TYPE.parse()?, scale.to_bytes()?))
+}
+
+fn parse_float(chunk_type: &str, bytes: &[u8]) -> Result<f64> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| {
+            PngmeError::Malformed(format!(
+                "{} field {:?} is not a floating-point number",
+                chunk_type,
+                String::from_utf8_lossy(bytes)
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_roundtrip() {
+        let offset = Offset {
+            x: -12,
+            y: 300,
+            in_micrometres: true,
+        };
+        assert_eq!(Offset::from_bytes(&offset.to_bytes()).unwrap(), offset);
+        assert_eq!(offset.to_string(), "offset (-12, 300) micrometres");
+        assert!(Offset::from_bytes(&[0; 8]).is_err());
+        assert!(Offset::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_calibration_roundtrip() {
+        let calibration = Calibration {
+            name: "temperature".parse().unwrap(),
+            original_zero: 0,
+            original_max: 65535,
+            equation: Equation::Linear,
+            unit: "K".to_string(),
+            parameters: vec![-40.5, 120.0],
+        };
+        let bytes = calibration.to_bytes().unwrap();
+        assert!(bytes.ends_with(b"K\0-40.5\x00120"));
+        assert_eq!(Calibration::from_bytes(&bytes).unwrap(), calibration);
+        assert_eq!(
+            calibration.to_string(),
+            "\"temperature\": samples 0 to 65535, linear equation with p = [-40.5, 120], unit \"K\""
+        );
+    }
+
+    #[test]
+    fn test_calibration_invalid() {
+        let mut calibration = Calibration {
+            name: "depth".parse().unwrap(),
+            original_zero: 0,
+            original_max: 255,
+            equation: Equation::Hyperbolic,
+            unit: "m".to_string(),
+            parameters: vec![1.0, 2.0],
+        };
+        assert!(calibration.to_bytes().is_err());
+        calibration.equation = Equation::Linear;
+        calibration.original_max = 0;
+        assert!(calibration.to_bytes().is_err());
+        assert!(Calibration::from_bytes(b"depth\0\0\0\0\0\0\0\0\xff\0\x02m\0one\0two").is_err());
+        assert!(Calibration::from_bytes(b"depth\0\0\0\0\0\0\0\0\xff\0\x02m\x001").is_err());
+    }
+
+    #[test]
+    fn test_scale_roundtrip() {
+        let scale = Scale {
+            width: 0.25,
+            height: 1.5e-6,
+            in_radians: false,
+        };
+        let bytes = scale.to_bytes().unwrap();
+        assert_eq!(bytes[0], 1);
+        assert_eq!(Scale::from_bytes(&bytes).unwrap(), scale);
+        assert_eq!(scale.to_string(), "pixel size 0.25 x 0.0000015 metres");
+        assert!(Scale::from_bytes(b"\x01-1\x001").is_err());
+        assert!(Scale::from_bytes(b"\x031\x001").is_err());
+        assert!(Scale {
+            width: 0.0,
+            height: 1.0,
+            in_radians: true
+        }
+        .to_bytes()
+        .is_err());
+    }
+
+    #[test]
+    fn test_set_replaces_existing() {
+        let mut png = Png::from_chunks(vec![]);
+        let mut offset = Offset {
+            x: 1,
+            y: 2,
+            in_micrometres: false,
+        };
+        set_offset(&mut png, offset).unwrap();
+        offset.x = 5;
+        set_offset(&mut png, offset).unwrap();
+        assert_eq!(png.chunks().len(), 1);
+        assert_eq!(self::offset(&png).unwrap(), Some(offset));
+    }
+}
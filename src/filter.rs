@@ -0,0 +1,132 @@
+use sha2::{Digest, Sha256};
+
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::pattern::ChunkPattern;
+use crate::png::Png;
+
+// Chunks removed by the clean filter, with the index each had in the original
+// file so the smudge filter can put them back where they were.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stash {
+    pub chunks: Vec<(usize, Chunk)>,
+}
+
+impl Stash {
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .flat_map(|(index, chunk)| {
+                (*index as u32)
+                    .to_be_bytes()
+                    .into_iter()
+                    .chain(chunk.as_bytes())
+            })
+            .collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut chunks = Vec::new();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (index, chunk_bytes) = rest.split_at_checked(4).ok_or_else(|| {
+                PngmeError::Truncated("stash entry has no chunk index".to_string())
+            })?;
+            let chunk = Chunk::try_from(chunk_bytes)?;
+            rest = &chunk_bytes[chunk.length() as usize + 12..];
+            chunks.push((
+                u32::from_be_bytes(index.try_into().unwrap()) as usize,
+                chunk,
+            ));
+        }
+        Ok(Self { chunks })
+    }
+}
+
+// The stash is looked up by a hash of the cleaned file, which is exactly what
+// the smudge filter is handed back by git.
+pub fn stash_key(cleaned: &[u8]) -> String {
+    Sha256::digest(cleaned)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn clean(png: &mut Png, patterns: &[ChunkPattern]) -> Stash {
+    let mut index = 0;
+    let mut chunks = Vec::new();
+    png.remove_chunks_where(|chunk| {
+        let matched = patterns
+            .iter()
+            .any(|pattern| pattern.matches(chunk.chunk_type()));
+        if matched {
+            chunks.push((index, chunk.clone()));
+        }
+        index += 1;
+        matched
+    });
+    Stash { chunks }
+}
+
+pub fn smudge(png: &mut Png, stash: Stash) -> Result<()> {
+    for (index, chunk) in stash.chunks {
+        png.insert_chunk(index.min(png.chunks().len()), chunk)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+
+    fn original() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Author\0me"),
+            chunk("IDAT", b"pixels"),
+            chunk("ruSt", b"secret"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    fn test_clean_then_smudge_restores_the_file() {
+        let mut png = original();
+        let patterns = ["tEXt".parse().unwrap(), "ru??".parse().unwrap()];
+        let stash = clean(&mut png, &patterns);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(
+            stash.chunks.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 3]
+        );
+
+        smudge(&mut png, stash).unwrap();
+        assert_eq!(png, original());
+    }
+
+    #[test]
+    fn test_stash_round_trip() {
+        let mut png = original();
+        let stash = clean(&mut png, &["ruSt".parse().unwrap()]);
+        assert_eq!(Stash::from_bytes(&stash.to_bytes()).unwrap(), stash);
+        assert!(Stash::from_bytes(&[0, 0]).is_err());
+        assert!(Stash::from_bytes(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stash_key() {
+        let key = stash_key(b"cleaned");
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, stash_key(b"other"));
+    }
+}
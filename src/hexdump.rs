@@ -0,0 +1,65 @@
+const BYTES_PER_LINE: usize = 16;
+
+// Formats data like `hexdump -C`: offset, two groups of eight hex bytes and
+// the printable ASCII alongside, stopping after `head` bytes if given.
+pub fn hexdump(data: &[u8], head: Option<usize>) -> String {
+    let shown = &data[..head.unwrap_or(data.len()).min(data.len())];
+    let mut dump = String::new();
+    for (line, bytes) in shown.chunks(BYTES_PER_LINE).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            if i == BYTES_PER_LINE / 2 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x} ", byte));
+        }
+        let ascii: String = bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump.push_str(&format!(
+            "{:08x}  {:<49} |{}|\n",
+            line * BYTES_PER_LINE,
+            hex,
+            ascii
+        ));
+    }
+    if shown.len() < data.len() {
+        dump.push_str(&format!("... {} more bytes\n", data.len() - shown.len()));
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"Hello, world!\n\x00\xffmore", None);
+        assert_eq!(
+            dump,
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n\
+             00000010  6d 6f 72 65                                       |more|\n"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_head() {
+        let dump = hexdump(&[0xab; 40], Some(16));
+        assert_eq!(dump.lines().count(), 2);
+        assert!(dump.ends_with("... 24 more bytes\n"));
+        assert_eq!(hexdump(b"abc", Some(10)), hexdump(b"abc", None));
+    }
+
+    #[test]
+    fn test_hexdump_empty() {
+        assert_eq!(hexdump(&[], None), "");
+    }
+}
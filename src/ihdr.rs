@@ -0,0 +1,224 @@
+use serde::Serialize;
+
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const IHDR_CHUNK_TYPE: &str = "IHDR";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColourType {
+    Greyscale,
+    Truecolour,
+    Indexed,
+    GreyscaleAlpha,
+    TruecolourAlpha,
+}
+
+impl ColourType {
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Self::Greyscale),
+            2 => Ok(Self::Truecolour),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GreyscaleAlpha),
+            6 => Ok(Self::TruecolourAlpha),
+            _ => Err(PngmeError::Malformed(format!(
+                "unknown colour type {}",
+                byte
+            ))),
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Greyscale => 0,
+            Self::Truecolour => 2,
+            Self::Indexed => 3,
+            Self::GreyscaleAlpha => 4,
+            Self::TruecolourAlpha => 6,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Greyscale | Self::Indexed => 1,
+            Self::GreyscaleAlpha => 2,
+            Self::Truecolour => 3,
+            Self::TruecolourAlpha => 4,
+        }
+    }
+
+    fn allowed_bit_depths(&self) -> &'static [u8] {
+        match self {
+            Self::Greyscale => &[1, 2, 4, 8, 16],
+            Self::Indexed => &[1, 2, 4, 8],
+            _ => &[8, 16],
+        }
+    }
+}
+
+impl std::fmt::Display for ColourType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Greyscale => "greyscale",
+            Self::Truecolour => "truecolour",
+            Self::Indexed => "indexed",
+            Self::GreyscaleAlpha => "greyscale with alpha",
+            Self::TruecolourAlpha => "truecolour with alpha",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub colour_type: ColourType,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlaced: bool,
+}
+
+impl ImageHeader {
+    pub fn bits_per_pixel(&self) -> usize {
+        self.colour_type.channels() * usize::from(self.bit_depth)
+    }
+
+    // Bytes in one scanline of the given width, without the filter-type byte.
+    pub fn row_len(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 13] {
+        let mut bytes = [0; 13];
+        bytes[..4].copy_from_slice(&self.width.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.height.to_be_bytes());
+        bytes[8..].copy_from_slice(&[
+            self.bit_depth,
+            self.colour_type.code(),
+            self.compression_method,
+            self.filter_method,
+            u8::from(self.interlaced),
+        ]);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 13 {
+            return Err(PngmeError::Malformed(format!(
+                "IHDR chunk should be 13 bytes, got {}",
+                bytes.len()
+            )));
+        }
+        let width = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        let bit_depth = bytes[8];
+        let colour_type = ColourType::from_byte(bytes[9])?;
+        if !colour_type.allowed_bit_depths().contains(&bit_depth) {
+            return Err(PngmeError::Malformed(format!(
+                "bit depth {} is not allowed for {} images",
+                bit_depth, colour_type
+            )));
+        }
+        // Deflate and adaptive filtering are the only methods the spec defines.
+        if bytes[10] != 0 {
+            return Err(PngmeError::Malformed(format!(
+                "unknown compression method {}",
+                bytes[10]
+            )));
+        }
+        if bytes[11] != 0 {
+            return Err(PngmeError::Malformed(format!(
+                "unknown filter method {}",
+                bytes[11]
+            )));
+        }
+        let interlaced = match bytes[12] {
+            0 => false,
+            1 => true,
+            method => {
+                return Err(PngmeError::Malformed(format!(
+                    "unknown interlace method {}",
+                    method
+                )))
+            }
+        };
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            colour_type,
+            compression_method: bytes[10],
+            filter_method: bytes[11],
+            interlaced,
+        })
+    }
+}
+
+impl std::fmt::Display for ImageHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "width {}, height {}, bit depth {}, colour type {} ({}), compression {} (deflate), \
+             filter {} (adaptive), interlace {} ({})",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.colour_type.code(),
+            self.colour_type,
+            self.compression_method,
+            self.filter_method,
+            u8::from(self.interlaced),
+            if self.interlaced { "Adam7" } else { "none" }
+        )
+    }
+}
+
+pub fn get(png: &Png) -> Option<ImageHeader> {
+    png.chunk_by_type(IHDR_CHUNK_TYPE)
+        .and_then(|chunk| ImageHeader::from_bytes(chunk.data()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_header() {
+        let header = ImageHeader::from_bytes(&[0, 0, 0, 4, 0, 0, 0, 3, 8, 2, 0, 0, 0]).unwrap();
+        assert_eq!(header.width, 4);
+        assert_eq!(header.height, 3);
+        assert_eq!(header.colour_type, ColourType::Truecolour);
+        assert_eq!(header.colour_type.channels(), 3);
+        assert_eq!(header.row_len(header.width), 12);
+        assert_eq!(
+            header.to_string(),
+            "width 4, height 3, bit depth 8, colour type 2 (truecolour), compression 0 (deflate), \
+             filter 0 (adaptive), interlace 0 (none)"
+        );
+    }
+
+    #[test]
+    fn test_image_header_interlaced() {
+        let header = ImageHeader::from_bytes(&[0, 0, 2, 88, 0, 0, 1, 144, 16, 6, 0, 0, 1]).unwrap();
+        assert_eq!((header.width, header.height), (600, 400));
+        assert!(header.interlaced);
+        assert!(header.to_string().ends_with("interlace 1 (Adam7)"));
+        assert_eq!(header.bits_per_pixel(), 64);
+        assert_eq!(header.row_len(header.width), 4800);
+        assert_eq!(ImageHeader::from_bytes(&header.to_bytes()).unwrap(), header);
+    }
+
+    #[test]
+    fn test_image_header_invalid() {
+        assert!(ImageHeader::from_bytes(&[0; 12]).is_err());
+        assert!(ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 16, 3, 0, 0, 0]).is_err());
+        assert!(ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 5, 0, 0, 0]).is_err());
+        assert!(ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 2]).is_err());
+        assert!(ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 1, 0, 0]).is_err());
+        assert!(ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 1, 0]).is_err());
+    }
+}
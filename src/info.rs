@@ -0,0 +1,138 @@
+use serde::Serialize;
+
+use crate::chunk::Chunk;
+use crate::envelope;
+use crate::exif;
+use crate::ihdr::{self, ImageHeader};
+use crate::png::Png;
+use crate::registry;
+use crate::signing::SIGNATURE_CHUNK_TYPE;
+use crate::text;
+use crate::vfs;
+use crate::xmp;
+
+const ICC_CHUNK_TYPE: &str = "iCCP";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkCount {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub header: Option<ImageHeader>,
+    pub file_size: usize,
+    pub trailing_bytes: usize,
+    pub chunks: usize,
+    pub critical: usize,
+    pub ancillary: usize,
+    pub unknown: usize,
+    pub text_entries: usize,
+    pub has_exif: bool,
+    pub has_xmp: bool,
+    pub has_icc: bool,
+    pub pngme_chunks: Vec<ChunkCount>,
+}
+
+impl Summary {
+    pub fn of(png: &Png) -> Self {
+        let chunks = png.chunks();
+        let critical = chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type().is_critical())
+            .count();
+        let unknown = chunks
+            .iter()
+            .filter(|chunk| registry::lookup(chunk.chunk_type()).is_none())
+            .count();
+        let mut pngme_chunks: Vec<ChunkCount> = Vec::new();
+        for chunk in chunks.iter().filter(|chunk| is_pngme_chunk(chunk)) {
+            let chunk_type = chunk.chunk_type().to_string();
+            match pngme_chunks.iter_mut().find(|c| c.chunk_type == chunk_type) {
+                Some(counted) => counted.count += 1,
+                None => pngme_chunks.push(ChunkCount {
+                    chunk_type,
+                    count: 1,
+                }),
+            }
+        }
+        Self {
+            header: ihdr::get(png),
+            file_size: png.as_bytes().len(),
+            trailing_bytes: png.trailing_data().len(),
+            chunks: chunks.len(),
+            critical,
+            ancillary: chunks.len() - critical,
+            unknown,
+            text_entries: text::entries(png).map_or(0, |entries| entries.len()),
+            has_exif: exif::get(png).is_some(),
+            has_xmp: xmp::get(png).is_ok_and(|packet| packet.is_some()),
+            has_icc: png.chunk_by_type(ICC_CHUNK_TYPE).is_some(),
+            pngme_chunks,
+        }
+    }
+}
+
+pub fn is_pngme_chunk(chunk: &Chunk) -> bool {
+    chunk.data().starts_with(&envelope::MAGIC)
+        || [
+            SIGNATURE_CHUNK_TYPE,
+            vfs::INDEX_CHUNK_TYPE,
+            vfs::DATA_CHUNK_TYPE,
+        ]
+        .contains(&chunk.chunk_type().to_string().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use crate::envelope::Envelope;
+    use crate::text::TextEntry;
+
+    #[test]
+    fn test_summary() {
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 4, 0, 0, 0, 3, 8, 2, 0, 0, 0]),
+            chunk("IDAT", b"pixels"),
+            chunk(
+                "ruSt",
+                &Envelope::new(b"secret".to_vec()).to_bytes().unwrap(),
+            ),
+            chunk("ruSt", &Envelope::new(b"more".to_vec()).to_bytes().unwrap()),
+            chunk("siGn", &[0; 68]),
+            chunk("IEND", &[]),
+        ]);
+        text::set(
+            &mut png,
+            &TextEntry::new("Title".parse().unwrap(), "Example"),
+        )
+        .unwrap();
+        let summary = Summary::of(&png);
+        assert_eq!(summary.header.unwrap().width, 4);
+        assert_eq!(summary.file_size, png.as_bytes().len());
+        assert_eq!(summary.trailing_bytes, 0);
+        assert_eq!(summary.chunks, 7);
+        assert_eq!(summary.critical, 3);
+        assert_eq!(summary.ancillary, 4);
+        assert_eq!(summary.unknown, 2);
+        assert_eq!(summary.text_entries, 1);
+        assert!(!summary.has_exif && !summary.has_xmp && !summary.has_icc);
+        let counts: Vec<(&str, usize)> = summary
+            .pngme_chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.count))
+            .collect();
+        assert_eq!(counts, [("ruSt", 2), ("siGn", 1)]);
+    }
+
+    #[test]
+    fn test_summary_of_empty_png() {
+        let summary = Summary::of(&Png::from_chunks(vec![]));
+        assert_eq!(summary.header, None);
+        assert_eq!(summary.chunks, 0);
+        assert!(summary.pngme_chunks.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::chunk::Chunk;
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::validate::{self, Violation};
+
+#[derive(Debug)]
+pub struct ChunkIntegrity {
+    pub index: usize,
+    pub offset: u64,
+    pub chunk_type: String,
+    pub length: u32,
+    pub error: Option<PngmeError>,
+}
+
+// The result of checking a file byte by byte: the signature, each chunk's
+// length, type and CRC, and the chunk layout rules once every chunk has been read.
+#[derive(Debug)]
+pub struct Integrity {
+    pub signature_valid: bool,
+    pub chunks: Vec<ChunkIntegrity>,
+    pub violations: Vec<Violation>,
+    pub trailing_bytes: usize,
+}
+
+impl Integrity {
+    pub fn check(bytes: &[u8]) -> Self {
+        let signature_valid = bytes.len() >= 8 && bytes[..8] == Png::STANDARD_HEADER;
+        let mut integrity = Self {
+            signature_valid,
+            chunks: Vec::new(),
+            violations: Vec::new(),
+            trailing_bytes: 0,
+        };
+        if !signature_valid {
+            return integrity;
+        }
+        let mut parsed = Vec::new();
+        let mut offset = 8;
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            let mut check = ChunkIntegrity {
+                index: integrity.chunks.len(),
+                offset: offset as u64,
+                chunk_type: rest
+                    .get(4..8)
+                    .unwrap_or_default()
+                    .escape_ascii()
+                    .to_string(),
+                length: rest
+                    .get(..4)
+                    .map_or(0, |length| u32::from_be_bytes(length.try_into().unwrap())),
+                error: None,
+            };
+            match Chunk::read_unchecked(rest) {
+                Ok((chunk, crc)) => {
+                    if chunk.crc() != crc {
+                        check.error = Some(PngmeError::CrcMismatch {
+                            expected: chunk.crc(),
+                            found: crc,
+                        });
+                    }
+                    offset += chunk.length() as usize + 12;
+                    let end = &chunk.chunk_type().bytes() == b"IEND";
+                    parsed.push(chunk);
+                    integrity.chunks.push(check);
+                    if end {
+                        integrity.trailing_bytes = bytes.len() - offset;
+                        break;
+                    }
+                }
+                Err(error) => {
+                    check.error = Some(error);
+                    integrity.chunks.push(check);
+                    break;
+                }
+            }
+        }
+        integrity.violations = validate::violations(&Png::from_chunks(parsed));
+        integrity
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.signature_valid
+            && self.violations.is_empty()
+            && self.chunks.iter().all(|chunk| chunk.error.is_none())
+    }
+
+    // The first problem found, with chunk context, for callers that only need
+    // to know whether and why the check failed.
+    pub fn into_result(self) -> Result<(), PngmeError> {
+        if !self.signature_valid {
+            return Err(PngmeError::InvalidSignature);
+        }
+        if let Some(chunk) = self.chunks.into_iter().find(|chunk| chunk.error.is_some()) {
+            return Err(PngmeError::in_chunk(
+                chunk.index,
+                chunk.offset,
+                chunk.error.unwrap(),
+            ));
+        }
+        validate::check_violations(self.violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn file(types: &[&str]) -> Vec<u8> {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), b"data".to_vec()))
+                .collect(),
+        )
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_intact_file() {
+        let integrity = Integrity::check(&file(&["IHDR", "IDAT", "IEND"]));
+        assert!(integrity.is_ok());
+        assert_eq!(integrity.chunks.len(), 3);
+        assert_eq!(integrity.chunks[1].chunk_type, "IDAT");
+        assert_eq!(integrity.chunks[1].offset, 24);
+        assert!(integrity.into_result().is_ok());
+    }
+
+    #[test]
+    fn test_bad_crc_is_reported_and_checking_continues() {
+        let mut bytes = file(&["IHDR", "IDAT", "IEND"]);
+        bytes[35] ^= 0xff;
+        let integrity = Integrity::check(&bytes);
+        assert!(!integrity.is_ok());
+        assert!(matches!(
+            integrity.chunks[1].error,
+            Some(PngmeError::CrcMismatch { .. })
+        ));
+        assert!(integrity.chunks[2].error.is_none());
+        assert!(integrity
+            .into_result()
+            .unwrap_err()
+            .to_string()
+            .starts_with("chunk #1 at offset 0x18: crc mismatch"));
+    }
+
+    #[test]
+    fn test_truncated_file_stops_checking() {
+        let mut bytes = file(&["IHDR", "IDAT", "IEND"]);
+        bytes.truncate(30);
+        let integrity = Integrity::check(&bytes);
+        assert_eq!(integrity.chunks.len(), 2);
+        assert!(matches!(
+            integrity.chunks[1].error,
+            Some(PngmeError::Truncated(_))
+        ));
+        assert_eq!(
+            integrity.violations[0].to_string(),
+            "chunk #0: IEND must be the last chunk, found IHDR"
+        );
+    }
+
+    #[test]
+    fn test_signature_and_structure() {
+        assert!(!Integrity::check(b"GIF89a").signature_valid);
+        let integrity = Integrity::check(&file(&["IHDR", "IDAT"]));
+        assert_eq!(
+            integrity.violations[0].to_string(),
+            "chunk #1: IEND must be the last chunk, found IDAT"
+        );
+        assert!(matches!(
+            integrity.into_result(),
+            Err(PngmeError::SpecViolations(_))
+        ));
+    }
+
+    #[test]
+    fn test_data_after_iend_is_not_an_error() {
+        let mut bytes = file(&["IHDR", "IDAT", "IEND"]);
+        bytes.extend_from_slice(b"appended");
+        let integrity = Integrity::check(&bytes);
+        assert_eq!(integrity.chunks.len(), 3);
+        assert_eq!(integrity.trailing_bytes, 8);
+        assert!(integrity.is_ok());
+    }
+}
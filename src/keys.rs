@@ -0,0 +1,222 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{IdentityKey, RecipientKey};
+use crate::error::{PngmeError, Result};
+use crate::signing::{SigningKey, VerifyingKey};
+
+pub const KEY_DIR_ENV: &str = "PNGME_KEY_DIR";
+pub const SIGNING_EXTENSION: &str = "ed25519";
+pub const ENCRYPTION_EXTENSION: &str = "x25519";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeys {
+    pub name: String,
+    pub signing: VerifyingKey,
+    pub encryption: RecipientKey,
+}
+
+impl KeyStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os(KEY_DIR_ENV)
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".pngme/keys")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn signing_key_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, SIGNING_EXTENSION))
+    }
+
+    pub fn encryption_key_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, ENCRYPTION_EXTENSION))
+    }
+
+    pub fn generate(&self, name: &str, overwrite: bool) -> Result<PublicKeys> {
+        validate_name(name)?;
+        create_private_dir(&self.dir)?;
+        let signing = SigningKey::generate();
+        let encryption = IdentityKey::generate();
+        let keys = PublicKeys {
+            name: name.to_string(),
+            signing: signing.verifying_key(),
+            encryption: encryption.recipient(),
+        };
+        let signing_path = self.signing_key_path(name);
+        let encryption_path = self.encryption_key_path(name);
+        write_key(&signing_path, &signing.to_base64(), true, overwrite)?;
+        write_key(&encryption_path, &encryption.to_base64(), true, overwrite)?;
+        write_key(
+            &pub_path(&signing_path),
+            &keys.signing.to_string(),
+            false,
+            overwrite,
+        )?;
+        write_key(
+            &pub_path(&encryption_path),
+            &keys.encryption.to_string(),
+            false,
+            overwrite,
+        )?;
+        Ok(keys)
+    }
+
+    pub fn public_keys(&self, name: &str) -> Result<PublicKeys> {
+        validate_name(name)?;
+        let signing = fs::read_to_string(pub_path(&self.signing_key_path(name)))?;
+        let encryption = fs::read_to_string(pub_path(&self.encryption_key_path(name)))?;
+        Ok(PublicKeys {
+            name: name.to_string(),
+            signing: signing.trim().parse()?,
+            encryption: encryption.trim().parse()?,
+        })
+    }
+
+    pub fn names(&self) -> Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let suffix = format!(".{}.pub", SIGNING_EXTENSION);
+        let mut names: Vec<String> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|file_name| file_name.strip_suffix(&suffix))
+                    .map(String::from)
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(PngmeError::Usage(format!(
+            "invalid key name {:?}: use letters, digits, '-' or '_'",
+            name
+        )))
+    }
+}
+
+fn pub_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".pub");
+    PathBuf::from(path)
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+fn write_key(path: &Path, contents: &str, secret: bool, overwrite: bool) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(if secret { 0o600 } else { 0o644 });
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| PngmeError::in_file(path, e.into()))?;
+    writeln!(file, "{}", contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path().join("keys"));
+        let generated = store.generate("alice", false).unwrap();
+        assert_eq!(store.public_keys("alice").unwrap(), generated);
+
+        let signing: SigningKey = fs::read_to_string(store.signing_key_path("alice"))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        assert_eq!(signing.verifying_key(), generated.signing);
+        let identity: IdentityKey = fs::read_to_string(store.encryption_key_path("alice"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(identity.recipient(), generated.encryption);
+    }
+
+    #[test]
+    fn test_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path());
+        store.generate("alice", false).unwrap();
+        assert!(store.generate("alice", false).is_err());
+        assert!(store.generate("alice", true).is_ok());
+    }
+
+    #[test]
+    fn test_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path());
+        assert!(store.names().unwrap().is_empty());
+        store.generate("bob", false).unwrap();
+        store.generate("alice", false).unwrap();
+        assert_eq!(store.names().unwrap(), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path());
+        assert!(store.generate("../escape", false).is_err());
+        assert!(store.generate("", false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_key_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::new(dir.path().join("keys"));
+        store.generate("alice", false).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&store.signing_key_path("alice")), 0o600);
+        assert_eq!(mode(&store.encryption_key_path("alice")), 0o600);
+        assert_eq!(mode(store.dir()), 0o700);
+    }
+}
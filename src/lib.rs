@@ -1,2 +1,53 @@
-mod chunk;
-mod chunk_type;
+pub mod analyze;
+pub mod archive;
+pub mod batch;
+pub mod chunk;
+pub mod chunk_type;
+pub mod colour;
+pub mod compression;
+pub mod crypto;
+pub mod describe;
+pub mod diff;
+pub mod dsig;
+pub mod dump;
+pub mod entropy;
+pub mod envelope;
+pub mod error;
+pub mod exif;
+pub mod extensions;
+pub mod filter;
+pub mod hexdump;
+pub mod ihdr;
+pub mod info;
+pub mod integrity;
+pub mod keys;
+pub mod lint;
+pub mod optimize;
+pub mod palette;
+pub mod passphrase;
+pub mod pattern;
+pub mod phys;
+pub mod pixels;
+pub mod png;
+pub mod policy;
+pub mod registry;
+pub mod repair;
+pub mod report;
+pub mod scan;
+pub mod signing;
+pub mod sniff;
+pub mod split;
+pub mod stego;
+pub mod strip;
+pub mod template;
+pub mod text;
+pub mod timestamp;
+pub mod transfer;
+pub mod validate;
+pub mod vfs;
+pub mod xmp;
+
+pub use chunk::Chunk;
+pub use chunk_type::ChunkType;
+pub use error::{PngmeError, Result};
+pub use png::Png;
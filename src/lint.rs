@@ -0,0 +1,316 @@
+use serde::Serialize;
+
+use crate::error::PngmeError;
+use crate::png::Png;
+use crate::registry;
+use crate::text::{self, Keyword};
+use crate::validate;
+
+// Ancillary chunks this large are almost always embedded files or bloat.
+pub const MAX_ANCILLARY_LEN: u32 = 1 << 20;
+const COLOUR_SPACE_CHUNK_TYPES: [&str; 3] = ["sRGB", "gAMA", "iCCP"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    ChunkOrder,
+    KeywordLength,
+    MissingColourSpace,
+    OversizedAncillary,
+    NonSpecChunk,
+    DuplicateMetadata,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Self::ChunkOrder,
+        Self::KeywordLength,
+        Self::MissingColourSpace,
+        Self::OversizedAncillary,
+        Self::NonSpecChunk,
+        Self::DuplicateMetadata,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ChunkOrder => "chunk-order",
+            Self::KeywordLength => "keyword-length",
+            Self::MissingColourSpace => "missing-colour-space",
+            Self::OversizedAncillary => "oversized-ancillary",
+            Self::NonSpecChunk => "non-spec-chunk",
+            Self::DuplicateMetadata => "duplicate-metadata",
+        }
+    }
+
+    // Layout violations make files that strict decoders reject; everything
+    // else is only worth a warning unless the caller asks otherwise.
+    pub fn default_level(self) -> Level {
+        match self {
+            Self::ChunkOrder => Level::Deny,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = PngmeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|rule| rule.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|rule| rule.name()).collect();
+                PngmeError::Usage(format!(
+                    "unknown lint rule {:?}, expected one of: {}",
+                    s,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warning"),
+            Self::Deny => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    pub allow: Vec<Rule>,
+    pub deny: Vec<Rule>,
+}
+
+impl LintConfig {
+    pub fn level(&self, rule: Rule) -> Level {
+        if self.allow.contains(&rule) {
+            Level::Allow
+        } else if self.deny.contains(&rule) {
+            Level::Deny
+        } else {
+            rule.default_level()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub level: Level,
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]: ", self.level, self.rule)?;
+        match self.index {
+            Some(index) => write!(f, "chunk #{}: {}", index, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn lint(png: &Png, config: &LintConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut add = |rule: Rule, index: Option<usize>, message: String| {
+        let level = config.level(rule);
+        if level != Level::Allow {
+            findings.push(Finding {
+                rule,
+                level,
+                index,
+                message,
+            });
+        }
+    };
+
+    for violation in validate::violations(png) {
+        add(Rule::ChunkOrder, violation.index, violation.message);
+    }
+    if !COLOUR_SPACE_CHUNK_TYPES
+        .iter()
+        .any(|chunk_type| png.chunk_by_type(chunk_type).is_some())
+    {
+        add(
+            Rule::MissingColourSpace,
+            None,
+            "no sRGB, gAMA or iCCP chunk, so colours are device dependent".to_string(),
+        );
+    }
+
+    let mut keywords: Vec<&[u8]> = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        if !chunk_type.is_critical() && chunk.length() > MAX_ANCILLARY_LEN {
+            add(
+                Rule::OversizedAncillary,
+                Some(index),
+                format!(
+                    "{} is {} bytes, more than {}",
+                    chunk_type,
+                    chunk.length(),
+                    MAX_ANCILLARY_LEN
+                ),
+            );
+        }
+        if registry::lookup(chunk_type).is_none() {
+            add(
+                Rule::NonSpecChunk,
+                Some(index),
+                format!("{} is not a registered chunk type", chunk_type),
+            );
+        }
+        let Some(keyword) = text::raw_keyword(chunk) else {
+            continue;
+        };
+        if keyword.is_empty() || keyword.len() > Keyword::MAX_LEN {
+            add(
+                Rule::KeywordLength,
+                Some(index),
+                format!(
+                    "{} keyword is {} bytes, expected 1 to {}",
+                    chunk_type,
+                    keyword.len(),
+                    Keyword::MAX_LEN
+                ),
+            );
+        }
+        if keywords.contains(&keyword) {
+            add(
+                Rule::DuplicateMetadata,
+                Some(index),
+                format!(
+                    "text keyword {:?} appears more than once",
+                    keyword.escape_ascii().to_string()
+                ),
+            );
+        }
+        keywords.push(keyword);
+    }
+    findings
+}
+
+pub fn denied(findings: &[Finding]) -> usize {
+    findings
+        .iter()
+        .filter(|finding| finding.level == Level::Deny)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use crate::chunk::Chunk;
+    use std::str::FromStr;
+
+    fn png(chunks: Vec<Chunk>) -> Png {
+        let mut all = vec![chunk("IHDR", &[0; 13]), chunk("sRGB", &[0])];
+        all.extend(chunks);
+        all.push(chunk("IDAT", b"pixels"));
+        all.push(chunk("IEND", b""));
+        Png::from_chunks(all)
+    }
+
+    fn rules(findings: &[Finding]) -> Vec<Rule> {
+        findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        let findings = lint(
+            &png(vec![chunk("tEXt", b"Title\0x")]),
+            &LintConfig::default(),
+        );
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn test_text_rules() {
+        let long = [b"k".repeat(80), b"\0text".to_vec()].concat();
+        let findings = lint(
+            &png(vec![
+                chunk("tEXt", b"Title\0a"),
+                chunk("tEXt", b"Title\0b"),
+                chunk("tEXt", &long),
+            ]),
+            &LintConfig::default(),
+        );
+        assert_eq!(
+            rules(&findings),
+            [Rule::DuplicateMetadata, Rule::KeywordLength]
+        );
+        assert_eq!(
+            findings[0].to_string(),
+            "warning[duplicate-metadata]: chunk #3: text keyword \"Title\" appears more than once"
+        );
+    }
+
+    #[test]
+    fn test_chunk_rules() {
+        let mut file = png(vec![chunk("ruSt", b"data")]);
+        file.remove_first_chunk("sRGB").unwrap();
+        file.insert_chunk(0, chunk("tIME", &[0; 7])).unwrap();
+        let findings = lint(&file, &LintConfig::default());
+        assert_eq!(
+            rules(&findings),
+            [
+                Rule::ChunkOrder,
+                Rule::MissingColourSpace,
+                Rule::NonSpecChunk
+            ]
+        );
+        assert_eq!(findings[0].level, Level::Deny);
+        assert_eq!(denied(&findings), 1);
+    }
+
+    #[test]
+    fn test_oversized_ancillary() {
+        let big = vec![0; MAX_ANCILLARY_LEN as usize + 1];
+        let findings = lint(&png(vec![chunk("zTXt", &big)]), &LintConfig::default());
+        assert_eq!(
+            rules(&findings),
+            [Rule::OversizedAncillary, Rule::KeywordLength]
+        );
+    }
+
+    #[test]
+    fn test_config_levels() {
+        let config = LintConfig {
+            allow: vec![Rule::ChunkOrder],
+            deny: vec![Rule::NonSpecChunk],
+        };
+        let findings = lint(&png(vec![chunk("ruSt", b"data")]), &config);
+        assert_eq!(rules(&findings), [Rule::NonSpecChunk]);
+        assert_eq!(denied(&findings), 1);
+        assert!(findings[0].to_string().starts_with("error[non-spec-chunk]"));
+    }
+
+    #[test]
+    fn test_rule_names() {
+        for rule in Rule::ALL {
+            assert_eq!(Rule::from_str(rule.name()).unwrap(), rule);
+        }
+        assert!(Rule::from_str("everything").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::process::ExitCode;
+
+use clap::Parser;
+use pngme::batch;
+use pngme::report::ErrorReport;
+
+use crate::args::ErrorFormat;
+use crate::style::Style;
+
+mod args;
+mod commands;
+mod style;
+
+fn main() -> ExitCode {
+    let cli = args::Cli::parse();
+    let walk = batch::Walk {
+        recursive: cli.walk.recursive,
+        max_depth: cli.walk.max_depth,
+        include: cli.walk.include,
+        exclude: cli.walk.exclude,
+    };
+    match commands::run(
+        cli.command,
+        &walk,
+        cli.threads,
+        cli.format,
+        Style::new(cli.color),
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            let report = ErrorReport::new(error.as_ref());
+            match cli.errors {
+                ErrorFormat::Text => eprintln!("error: {}", error),
+                ErrorFormat::Json => eprintln!(
+                    "{}",
+                    serde_json::to_string(&report).expect("error reports always serialize")
+                ),
+            }
+            ExitCode::from(report.exit_code())
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{ColourType, ImageHeader};
+use crate::palette::{BACKGROUND_CHUNK_TYPE, PALETTE_CHUNK_TYPE, TRANSPARENCY_CHUNK_TYPE};
+use crate::pattern::ChunkPattern;
+use crate::pixels::{Filter, Pixels, IDAT_CHUNK_TYPE};
+use crate::png::Png;
+
+// Ancillary chunks that change how the image looks or animates, which
+// `optimize` keeps. Everything else ancillary is metadata and goes.
+pub const RENDERING_CHUNK_TYPES: [&str; 13] = [
+    "tRNS", "gAMA", "cHRM", "sRGB", "iCCP", "cICP", "mDCV", "cLLI", "sBIT", "pHYs", "acTL", "fcTL",
+    "fdAT",
+];
+
+// Chunks holding samples in the image's own colour type and bit depth, which
+// would need rewriting if either changed.
+const SAMPLE_CHUNK_TYPES: [&str; 3] = [TRANSPARENCY_CHUNK_TYPE, BACKGROUND_CHUNK_TYPE, "sBIT"];
+
+// APNG frames share the IHDR, so their data would have to be reduced too.
+const ANIMATION_CHUNK_TYPES: [&str; 3] = ["acTL", "fcTL", "fdAT"];
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // Ancillary chunks to keep on top of the rendering ones.
+    pub keep: Vec<ChunkPattern>,
+    // Narrow the colour type or bit depth when every sample still fits.
+    pub reduce: bool,
+    pub filter: Filter,
+    // Write the image data Adam7-interlaced, even when it comes out larger.
+    pub interlace: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub removed: Vec<Chunk>,
+    // The header before and after, when the image was narrowed.
+    pub reduced: Option<(ImageHeader, ImageHeader)>,
+    // Whether the IDAT chunks were replaced; a re-deflate that comes out
+    // larger is thrown away.
+    pub recompressed: bool,
+    pub original_len: usize,
+    pub optimized_len: usize,
+}
+
+impl Report {
+    pub fn saved(&self) -> usize {
+        self.original_len.saturating_sub(self.optimized_len)
+    }
+}
+
+// Shrinks `png` without changing how it renders: strips metadata, optionally
+// narrows the pixel format, and re-deflates the image data at the highest
+// level. Interlaced images stay interlaced; others are interlaced on request.
+pub fn optimize(png: &mut Png, options: &Options) -> Result<Report> {
+    let original_len = png.as_bytes().len();
+    let removed = png.remove_chunks_where(|chunk| {
+        let chunk_type = chunk.chunk_type();
+        !chunk_type.is_critical()
+            && !RENDERING_CHUNK_TYPES.contains(&chunk_type.to_string().as_str())
+            && !options
+                .keep
+                .iter()
+                .any(|pattern| pattern.matches(chunk_type))
+    });
+    let mut pixels = png.decode_pixels()?;
+    let mut reduced = None;
+    if options.reduce {
+        if let Some(narrower) = reduce(png, &pixels)? {
+            reduced = Some((pixels.header, narrower.header));
+            pixels = narrower;
+        }
+    }
+    let interlaced = options.interlace && !pixels.header.interlaced;
+    if interlaced {
+        pixels.header.interlaced = true;
+    }
+    let mut candidate = png.clone();
+    candidate.encode_pixels_at(&pixels, options.filter, flate2::Compression::best())?;
+    // Never trade a working image for a smaller broken one.
+    if candidate.decode_pixels()? != pixels {
+        return Err(PngmeError::Malformed(
+            "re-encoded image data does not decode to the same pixels".to_string(),
+        ));
+    }
+    let recompressed = reduced.is_some() || interlaced || idat_len(&candidate) < idat_len(png);
+    if recompressed {
+        *png = candidate;
+    }
+    Ok(Report {
+        removed,
+        reduced,
+        recompressed,
+        original_len,
+        optimized_len: png.as_bytes().len(),
+    })
+}
+
+fn idat_len(png: &Png) -> usize {
+    png.chunks_by_type(IDAT_CHUNK_TYPE)
+        .map(|chunk| chunk.data().len())
+        .sum()
+}
+
+// A narrower copy of `pixels` holding exactly the same image, if there is
+// one: 16-bit samples that repeat their high byte, alpha that is opaque
+// everywhere, colour that is grey everywhere, and greyscale or palette
+// samples that fit in fewer bits.
+fn reduce(png: &Png, pixels: &Pixels) -> Result<Option<Pixels>> {
+    let has_any = |types: &[&str]| types.iter().any(|t| png.chunk_by_type(t).is_some());
+    if has_any(&ANIMATION_CHUNK_TYPES) {
+        return Ok(None);
+    }
+    let mut header = pixels.header;
+    let mut samples = pixels.samples();
+    let sample_chunks = has_any(&SAMPLE_CHUNK_TYPES);
+    if !sample_chunks && header.colour_type != ColourType::Indexed {
+        if header.bit_depth == 16 && samples.iter().all(|&s| s >> 8 == s & 0xff) {
+            samples.iter_mut().for_each(|s| *s >>= 8);
+            header.bit_depth = 8;
+        }
+        let opaque = (1u32 << header.bit_depth) - 1;
+        let channels = header.colour_type.channels();
+        let without_alpha = match header.colour_type {
+            ColourType::GreyscaleAlpha => Some(ColourType::Greyscale),
+            ColourType::TruecolourAlpha => Some(ColourType::Truecolour),
+            _ => None,
+        };
+        if let Some(colour_type) = without_alpha {
+            if samples
+                .chunks_exact(channels)
+                .all(|pixel| u32::from(pixel[channels - 1]) == opaque)
+            {
+                samples = samples
+                    .chunks_exact(channels)
+                    .flat_map(|pixel| pixel[..channels - 1].to_vec())
+                    .collect();
+                header.colour_type = colour_type;
+            }
+        }
+        let grey = match header.colour_type {
+            ColourType::Truecolour => Some(ColourType::Greyscale),
+            ColourType::TruecolourAlpha => Some(ColourType::GreyscaleAlpha),
+            _ => None,
+        };
+        if let Some(colour_type) = grey {
+            let channels = header.colour_type.channels();
+            if samples
+                .chunks_exact(channels)
+                .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2])
+            {
+                samples = samples
+                    .chunks_exact(channels)
+                    .flat_map(|pixel| [&pixel[..1], &pixel[3..]].concat())
+                    .collect();
+                header.colour_type = colour_type;
+            }
+        }
+    }
+    let palette_len = png
+        .chunk_by_type(PALETTE_CHUNK_TYPE)
+        .map_or(0, |chunk| chunk.data().len() / 3);
+    let narrowable = match header.colour_type {
+        ColourType::Greyscale => !sample_chunks,
+        ColourType::Indexed => true,
+        _ => false,
+    };
+    if narrowable && header.bit_depth == 8 {
+        for depth in [1u8, 2, 4] {
+            let max = (1u16 << depth) - 1;
+            if header.colour_type == ColourType::Indexed {
+                // The palette may not have more entries than the depth can index.
+                if palette_len <= usize::from(max) + 1 && samples.iter().all(|&s| s <= max) {
+                    header.bit_depth = depth;
+                    break;
+                }
+            } else {
+                // Narrow greyscale samples scale up to the full range, so
+                // only multiples of the step survive the round trip.
+                let step = 255 / max;
+                if samples.iter().all(|&s| s % step == 0) {
+                    samples.iter_mut().for_each(|s| *s /= step);
+                    header.bit_depth = depth;
+                    break;
+                }
+            }
+        }
+    }
+    if header == pixels.header {
+        return Ok(None);
+    }
+    Pixels::from_samples(header, &samples).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use crate::ihdr;
+    use crate::pixels::tests::image;
+
+    fn with_samples(depth: u8, colour: u8, width: u32, samples: &[u16]) -> Png {
+        let mut png = image(width, 1, depth, colour, &[]);
+        let pixels = Pixels::from_samples(ihdr::get(&png).unwrap(), samples).unwrap();
+        png.encode_pixels(&pixels, Filter::None).unwrap();
+        png
+    }
+
+    fn reduced(png: &mut Png) -> (u8, ColourType, Vec<u16>) {
+        let options = Options {
+            reduce: true,
+            ..Options::default()
+        };
+        optimize(png, &options).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+        (
+            pixels.header.bit_depth,
+            pixels.colour_type(),
+            pixels.samples(),
+        )
+    }
+
+    #[test]
+    fn test_strips_metadata_and_keeps_rendering_chunks() {
+        let mut png = with_samples(8, 0, 2, &[3, 200]);
+        for (index, chunk_type) in ["gAMA", "tEXt", "ruSt"].into_iter().enumerate() {
+            png.insert_chunk(index + 1, chunk(chunk_type, b"data"))
+                .unwrap();
+        }
+        let options = Options {
+            keep: vec!["ruSt".parse().unwrap()],
+            ..Options::default()
+        };
+        let report = optimize(&mut png, &options).unwrap();
+        assert_eq!(report.removed, [chunk("tEXt", b"data")]);
+        assert!(report.reduced.is_none());
+        assert_eq!(report.saved(), report.original_len - report.optimized_len);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "ruSt", "IDAT", "IEND"]);
+        assert_eq!(png.decode_pixels().unwrap().samples(), [3, 200]);
+    }
+
+    #[test]
+    fn test_recompresses_only_when_smaller() {
+        // A gradient stored unfiltered shrinks once Sub is tried.
+        let samples: Vec<u16> = (0..256).map(|x| x * 7 % 256).collect();
+        let mut png = with_samples(8, 0, 256, &samples);
+        let report = optimize(&mut png, &Options::default()).unwrap();
+        assert!(report.recompressed);
+        let before = png.clone();
+        let report = optimize(&mut png, &Options::default()).unwrap();
+        assert!(!report.recompressed);
+        assert_eq!(png, before);
+    }
+
+    #[test]
+    fn test_reduces_opaque_grey_sixteen_bit_to_one_bit() {
+        let mut png = with_samples(16, 6, 2, &[0, 0, 0, 65535, 65535, 65535, 65535, 65535]);
+        assert_eq!(reduced(&mut png), (1, ColourType::Greyscale, vec![0, 1]));
+    }
+
+    #[test]
+    fn test_keeps_information() {
+        // Translucent, coloured pixels and a 16-bit sample with distinct
+        // bytes cannot be narrowed.
+        let mut png = with_samples(8, 6, 1, &[10, 20, 30, 128]);
+        assert_eq!(
+            reduced(&mut png),
+            (8, ColourType::TruecolourAlpha, vec![10, 20, 30, 128])
+        );
+        let mut png = with_samples(16, 0, 2, &[0x1234, 0]);
+        assert_eq!(
+            reduced(&mut png),
+            (16, ColourType::Greyscale, vec![0x1234, 0])
+        );
+        let mut png = with_samples(8, 0, 2, &[0, 85]);
+        assert_eq!(reduced(&mut png), (2, ColourType::Greyscale, vec![0, 1]));
+    }
+
+    #[test]
+    fn test_sample_chunks_block_reduction() {
+        let mut png = with_samples(8, 4, 1, &[0, 255]);
+        png.insert_chunk(1, chunk("sBIT", &[8, 8])).unwrap();
+        assert_eq!(
+            reduced(&mut png),
+            (8, ColourType::GreyscaleAlpha, vec![0, 255])
+        );
+    }
+
+    #[test]
+    fn test_reduces_palette_depth() {
+        let mut png = with_samples(8, 3, 3, &[0, 2, 1]);
+        png.insert_chunk(1, chunk("PLTE", &[0; 9])).unwrap();
+        assert_eq!(reduced(&mut png), (2, ColourType::Indexed, vec![0, 2, 1]));
+        let mut png = with_samples(8, 3, 2, &[0, 1]);
+        png.insert_chunk(1, chunk("PLTE", &[0; 15])).unwrap();
+        assert_eq!(reduced(&mut png), (4, ColourType::Indexed, vec![0, 1]));
+    }
+
+    #[test]
+    fn test_interlaces_on_request() {
+        let samples: Vec<u16> = (0..64).collect();
+        let mut png = with_samples(8, 0, 64, &samples);
+        let options = Options {
+            interlace: true,
+            ..Options::default()
+        };
+        let report = optimize(&mut png, &options).unwrap();
+        assert!(report.recompressed);
+        assert!(ihdr::get(&png).unwrap().interlaced);
+        assert_eq!(png.decode_pixels().unwrap().samples(), samples);
+    }
+
+    #[test]
+    fn test_large_images_stay_decodable() {
+        let mut png = image(700, 600, 8, 2, &[]);
+        let header = ihdr::get(&png).unwrap();
+        let mut seed = 5u32;
+        let data = (0..header.row_len(700) * 600)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let pixels = Pixels { header, data };
+        png.encode_pixels(&pixels, Filter::None).unwrap();
+        png.insert_chunk(1, chunk("tEXt", b"Title\0x")).unwrap();
+        optimize(&mut png, &Options::default()).unwrap();
+        assert!(png.chunks_by_type(IDAT_CHUNK_TYPE).count() > 1);
+        assert_eq!(png.decode_pixels().unwrap(), pixels);
+    }
+}
@@ -0,0 +1,420 @@
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{ColourType, ImageHeader};
+use crate::png::Png;
+use crate::text::{self, Keyword};
+
+pub const PALETTE_CHUNK_TYPE: &str = "PLTE";
+pub const TRANSPARENCY_CHUNK_TYPE: &str = "tRNS";
+pub const BACKGROUND_CHUNK_TYPE: &str = "bKGD";
+pub const SUGGESTED_PALETTE_CHUNK_TYPE: &str = "sPLT";
+pub const HISTOGRAM_CHUNK_TYPE: &str = "hIST";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette(pub Vec<[u8; 3]>);
+
+impl Palette {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(3) || bytes.len() > 256 * 3 {
+            return Err(PngmeError::Malformed(format!(
+                "PLTE chunk should hold 1 to 256 RGB entries, got {} bytes",
+                bytes.len()
+            )));
+        }
+        Ok(Self(
+            bytes
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect(),
+        ))
+    }
+
+    pub fn describe(&self, limit: Option<usize>) -> String {
+        let colours: Vec<String> = self
+            .0
+            .iter()
+            .map(|&[r, g, b]| hex_colour(&[r.into(), g.into(), b.into()], 8))
+            .collect();
+        format!(
+            "{} entries: {}",
+            self.0.len(),
+            join_limited(&colours, limit)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    Grey(u16),
+    Rgb([u16; 3]),
+    Alpha(Vec<u8>),
+}
+
+impl Transparency {
+    pub fn from_bytes(bytes: &[u8], header: &ImageHeader) -> Result<Self> {
+        match header.colour_type {
+            ColourType::Greyscale => Ok(Self::Grey(read_samples::<1>("tRNS", bytes)?[0])),
+            ColourType::Truecolour => Ok(Self::Rgb(read_samples::<3>("tRNS", bytes)?)),
+            ColourType::Indexed if bytes.len() <= 256 => Ok(Self::Alpha(bytes.to_vec())),
+            ColourType::Indexed => Err(PngmeError::Malformed(format!(
+                "tRNS chunk has {} alpha values but a palette holds at most 256",
+                bytes.len()
+            ))),
+            colour_type => Err(PngmeError::Malformed(format!(
+                "tRNS chunk is not allowed for {} images",
+                colour_type
+            ))),
+        }
+    }
+
+    pub fn describe(&self, bit_depth: u8, limit: Option<usize>) -> String {
+        match self {
+            Self::Grey(level) => format!("transparent grey level {}", level),
+            Self::Rgb(rgb) => format!("transparent colour {}", hex_colour(rgb, bit_depth)),
+            Self::Alpha(alphas) => {
+                let alphas: Vec<String> = alphas.iter().map(u8::to_string).collect();
+                format!(
+                    "alpha for {} palette entries: {}",
+                    alphas.len(),
+                    join_limited(&alphas, limit)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Background {
+    Grey(u16),
+    Rgb([u16; 3]),
+    Index(u8),
+}
+
+impl Background {
+    pub fn from_bytes(bytes: &[u8], header: &ImageHeader) -> Result<Self> {
+        match header.colour_type {
+            ColourType::Greyscale | ColourType::GreyscaleAlpha => {
+                Ok(Self::Grey(read_samples::<1>("bKGD", bytes)?[0]))
+            }
+            ColourType::Truecolour | ColourType::TruecolourAlpha => {
+                Ok(Self::Rgb(read_samples::<3>("bKGD", bytes)?))
+            }
+            ColourType::Indexed => match bytes {
+                [index] => Ok(Self::Index(*index)),
+                _ => Err(PngmeError::Malformed(format!(
+                    "bKGD chunk should be 1 byte for indexed images, got {}",
+                    bytes.len()
+                ))),
+            },
+        }
+    }
+
+    pub fn describe(&self, bit_depth: u8, palette: Option<&Palette>) -> String {
+        match self {
+            Self::Grey(level) => format!("background grey level {}", level),
+            Self::Rgb(rgb) => format!("background {}", hex_colour(rgb, bit_depth)),
+            Self::Index(index) => match palette.and_then(|p| p.0.get(*index as usize)) {
+                Some(&[r, g, b]) => format!(
+                    "background palette index {} ({})",
+                    index,
+                    hex_colour(&[r.into(), g.into(), b.into()], 8)
+                ),
+                None => format!("background palette index {}", index),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuggestedEntry {
+    pub rgba: [u16; 4],
+    pub frequency: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestedPalette {
+    pub name: Keyword,
+    pub sample_depth: u8,
+    pub entries: Vec<SuggestedEntry>,
+}
+
+impl SuggestedPalette {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let separator = bytes.iter().position(|&b| b == 0).ok_or_else(|| {
+            PngmeError::Malformed("sPLT chunk has no palette name terminator".into())
+        })?;
+        let name: Keyword = text::from_latin1(&bytes[..separator]).parse()?;
+        let (sample_depth, rest) = match &bytes[separator + 1..] {
+            [depth @ (8 | 16), rest @ ..] => (*depth, rest),
+            [depth, ..] => {
+                return Err(PngmeError::Malformed(format!(
+                    "unknown sPLT sample depth {}",
+                    depth
+                )))
+            }
+            [] => {
+                return Err(PngmeError::Malformed(
+                    "sPLT chunk has no sample depth".into(),
+                ))
+            }
+        };
+        let sample_len = usize::from(sample_depth / 8);
+        let entry_len = sample_len * 4 + 2;
+        if !rest.len().is_multiple_of(entry_len) {
+            return Err(PngmeError::Malformed(format!(
+                "sPLT entries should be {} bytes each, got {} trailing bytes",
+                entry_len,
+                rest.len()
+            )));
+        }
+        let entries = rest
+            .chunks_exact(entry_len)
+            .map(|entry| {
+                let mut rgba = [0; 4];
+                for (sample, bytes) in rgba.iter_mut().zip(entry.chunks_exact(sample_len)) {
+                    *sample = bytes.iter().fold(0, |acc, &b| (acc << 8) | u16::from(b));
+                }
+                let frequency = u16::from_be_bytes([entry[entry_len - 2], entry[entry_len - 1]]);
+                SuggestedEntry { rgba, frequency }
+            })
+            .collect();
+        Ok(Self {
+            name,
+            sample_depth,
+            entries,
+        })
+    }
+
+    pub fn describe(&self, limit: Option<usize>) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let [r, g, b, a] = entry.rgba;
+                let alpha = if self.sample_depth == 16 {
+                    format!("{:04x}", a)
+                } else {
+                    format!("{:02x}", a)
+                };
+                format!(
+                    "{}{} (freq {})",
+                    hex_colour(&[r, g, b], self.sample_depth),
+                    alpha,
+                    entry.frequency
+                )
+            })
+            .collect();
+        format!(
+            "{:?}, {}-bit, {} entries: {}",
+            self.name.as_str(),
+            self.sample_depth,
+            self.entries.len(),
+            join_limited(&entries, limit)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram(pub Vec<u16>);
+
+impl Histogram {
+    pub fn from_bytes(bytes: &[u8], palette: Option<&Palette>) -> Result<Self> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(2) {
+            return Err(PngmeError::Malformed(format!(
+                "hIST chunk should hold 2 bytes per palette entry, got {} bytes",
+                bytes.len()
+            )));
+        }
+        let frequencies: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        if let Some(palette) = palette {
+            if frequencies.len() != palette.0.len() {
+                return Err(PngmeError::Malformed(format!(
+                    "hIST chunk has {} entries but the palette has {}",
+                    frequencies.len(),
+                    palette.0.len()
+                )));
+            }
+        }
+        Ok(Self(frequencies))
+    }
+
+    pub fn describe(&self, limit: Option<usize>) -> String {
+        let frequencies: Vec<String> = self.0.iter().map(u16::to_string).collect();
+        format!(
+            "frequencies for {} palette entries: {}",
+            frequencies.len(),
+            join_limited(&frequencies, limit)
+        )
+    }
+}
+
+pub fn get(png: &Png) -> Option<Palette> {
+    png.chunk_by_type(PALETTE_CHUNK_TYPE)
+        .and_then(|chunk| Palette::from_bytes(chunk.data()).ok())
+}
+
+pub fn suggested_palettes(png: &Png) -> Vec<SuggestedPalette> {
+    png.chunks_by_type(SUGGESTED_PALETTE_CHUNK_TYPE)
+        .filter_map(|chunk| SuggestedPalette::from_bytes(chunk.data()).ok())
+        .collect()
+}
+
+pub fn histogram(png: &Png) -> Option<Histogram> {
+    png.chunk_by_type(HISTOGRAM_CHUNK_TYPE)
+        .and_then(|chunk| Histogram::from_bytes(chunk.data(), get(png).as_ref()).ok())
+}
+
+fn hex_colour(rgb: &[u16; 3], bit_depth: u8) -> String {
+    if bit_depth == 16 {
+        format!("#{:04x}{:04x}{:04x}", rgb[0], rgb[1], rgb[2])
+    } else {
+        format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+    }
+}
+
+fn join_limited(items: &[String], limit: Option<usize>) -> String {
+    let shown = limit.unwrap_or(items.len()).min(items.len());
+    let mut joined = items[..shown].join(", ");
+    if shown < items.len() {
+        if shown > 0 {
+            joined.push_str(", ");
+        }
+        joined.push_str(&format!("... ({} more)", items.len() - shown));
+    }
+    joined
+}
+
+fn read_samples<const N: usize>(chunk_type: &str, bytes: &[u8]) -> Result<[u16; N]> {
+    if bytes.len() != N * 2 {
+        return Err(PngmeError::Malformed(format!(
+            "{} chunk should be {} bytes for this colour type, got {}",
+            chunk_type,
+            N * 2,
+            bytes.len()
+        )));
+    }
+    let mut samples = [0; N];
+    for (sample, pair) in samples.iter_mut().zip(bytes.chunks_exact(2)) {
+        *sample = u16::from_be_bytes([pair[0], pair[1]]);
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(colour_type: u8, bit_depth: u8) -> ImageHeader {
+        ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, bit_depth, colour_type, 0, 0, 0]).unwrap()
+    }
+
+    #[test]
+    fn test_palette() {
+        let palette = Palette::from_bytes(&[255, 0, 0, 0, 128, 255, 1, 2, 3]).unwrap();
+        assert_eq!(palette.0.len(), 3);
+        assert_eq!(
+            palette.describe(None),
+            "3 entries: #ff0000, #0080ff, #010203"
+        );
+        assert_eq!(
+            palette.describe(Some(1)),
+            "3 entries: #ff0000, ... (2 more)"
+        );
+        assert_eq!(palette.describe(Some(0)), "3 entries: ... (3 more)");
+    }
+
+    #[test]
+    fn test_palette_invalid() {
+        assert!(Palette::from_bytes(&[]).is_err());
+        assert!(Palette::from_bytes(&[1, 2]).is_err());
+        assert!(Palette::from_bytes(&[0; 257 * 3]).is_err());
+    }
+
+    #[test]
+    fn test_transparency() {
+        let indexed = header(3, 8);
+        let alpha = Transparency::from_bytes(&[0, 128, 255], &indexed).unwrap();
+        assert_eq!(alpha, Transparency::Alpha(vec![0, 128, 255]));
+        assert_eq!(
+            alpha.describe(8, Some(2)),
+            "alpha for 3 palette entries: 0, 128, ... (1 more)"
+        );
+
+        let rgb = Transparency::from_bytes(&[0, 255, 0, 0, 0, 16], &header(2, 8)).unwrap();
+        assert_eq!(rgb.describe(8, None), "transparent colour #ff0010");
+        let rgb16 = Transparency::from_bytes(&[1, 0, 0, 0, 255, 255], &header(2, 16)).unwrap();
+        assert_eq!(rgb16.describe(16, None), "transparent colour #01000000ffff");
+
+        assert!(Transparency::from_bytes(&[0, 1], &header(6, 8)).is_err());
+        assert!(Transparency::from_bytes(&[0], &header(0, 8)).is_err());
+    }
+
+    #[test]
+    fn test_background() {
+        let palette = Palette::from_bytes(&[0, 0, 0, 255, 255, 255]).unwrap();
+        let index = Background::from_bytes(&[1], &header(3, 8)).unwrap();
+        assert_eq!(
+            index.describe(8, Some(&palette)),
+            "background palette index 1 (#ffffff)"
+        );
+        assert_eq!(index.describe(8, None), "background palette index 1");
+
+        let grey = Background::from_bytes(&[0, 7], &header(4, 8)).unwrap();
+        assert_eq!(grey, Background::Grey(7));
+        assert!(Background::from_bytes(&[0, 1], &header(3, 8)).is_err());
+    }
+
+    #[test]
+    fn test_suggested_palette() {
+        let mut bytes = b"fast\0\x08".to_vec();
+        bytes.extend([255, 0, 0, 255, 0, 10, 0, 0, 255, 128, 0, 3]);
+        let splt = SuggestedPalette::from_bytes(&bytes).unwrap();
+        assert_eq!(splt.name.as_str(), "fast");
+        assert_eq!(
+            splt.entries[1],
+            SuggestedEntry {
+                rgba: [0, 0, 255, 128],
+                frequency: 3
+            }
+        );
+        assert_eq!(
+            splt.describe(Some(1)),
+            "\"fast\", 8-bit, 2 entries: #ff0000ff (freq 10), ... (1 more)"
+        );
+    }
+
+    #[test]
+    fn test_suggested_palette_16_bit() {
+        let mut bytes = b"deep\0\x10".to_vec();
+        bytes.extend([1, 2, 3, 4, 5, 6, 7, 8, 0, 1]);
+        let splt = SuggestedPalette::from_bytes(&bytes).unwrap();
+        assert_eq!(splt.entries[0].rgba, [0x0102, 0x0304, 0x0506, 0x0708]);
+        assert_eq!(
+            splt.describe(None),
+            "\"deep\", 16-bit, 1 entries: #0102030405060708 (freq 1)"
+        );
+    }
+
+    #[test]
+    fn test_suggested_palette_invalid() {
+        assert!(SuggestedPalette::from_bytes(b"no terminator").is_err());
+        assert!(SuggestedPalette::from_bytes(b"name\0\x04").is_err());
+        assert!(SuggestedPalette::from_bytes(b"name\0\x08\x01").is_err());
+        assert!(SuggestedPalette::from_bytes(b" bad\0\x08").is_err());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let palette = Palette::from_bytes(&[0; 6]).unwrap();
+        let hist = Histogram::from_bytes(&[0, 5, 1, 0], Some(&palette)).unwrap();
+        assert_eq!(hist, Histogram(vec![5, 256]));
+        assert_eq!(
+            hist.describe(None),
+            "frequencies for 2 palette entries: 5, 256"
+        );
+        assert!(Histogram::from_bytes(&[0, 5], Some(&palette)).is_err());
+        assert!(Histogram::from_bytes(&[0, 5, 1], None).is_err());
+    }
+}
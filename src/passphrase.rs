@@ -0,0 +1,108 @@
+use crate::error::{PngmeError, Result};
+
+pub const KEYRING_SERVICE: &str = "pngme";
+pub const PASSWORD_ENV: &str = "PNGME_PASSWORD";
+
+pub fn from_keyring(name: &str) -> Result<Option<String>> {
+    match keyring::Entry::new(KEYRING_SERVICE, name)?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn store_in_keyring(name: &str, password: &str) -> Result<()> {
+    Ok(keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(password)?)
+}
+
+pub fn prompt(confirm: bool) -> Result<String> {
+    let password = rpassword::prompt_password("Passphrase: ")?;
+    if confirm && rpassword::prompt_password("Confirm passphrase: ")? != password {
+        return Err(PngmeError::Usage("passphrases do not match".to_string()));
+    }
+    if password.is_empty() {
+        return Err(PngmeError::Usage(
+            "passphrase must not be empty".to_string(),
+        ));
+    }
+    Ok(password)
+}
+
+// A resolved passphrase. One that did not come from the keyring is only saved
+// there by `remember`, so a mistyped passphrase never replaces a good one.
+pub struct Passphrase {
+    pub password: String,
+    keyring: Option<String>,
+}
+
+impl Passphrase {
+    // Call once the passphrase has encrypted or decrypted something.
+    pub fn remember(&self) -> Result<()> {
+        match &self.keyring {
+            Some(name) => store_in_keyring(name, &self.password),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn resolve(
+    password: Option<String>,
+    keyring: Option<&str>,
+    confirm: bool,
+) -> Result<Passphrase> {
+    if let Some(name) = keyring {
+        if password.is_none() {
+            if let Some(stored) = from_keyring(name)? {
+                return Ok(Passphrase {
+                    password: stored,
+                    keyring: None,
+                });
+            }
+        }
+    }
+    let password = resolve_without_keyring(password, std::env::var(PASSWORD_ENV).ok(), || {
+        prompt(confirm)
+    })?;
+    Ok(Passphrase {
+        password,
+        keyring: keyring.map(str::to_string),
+    })
+}
+
+fn resolve_without_keyring(
+    password: Option<String>,
+    env: Option<String>,
+    prompt: impl FnOnce() -> Result<String>,
+) -> Result<String> {
+    match password.or(env.filter(|env| !env.is_empty())) {
+        Some(password) => Ok(password),
+        None => prompt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explicit_password_wins() {
+        let password = resolve_without_keyring(
+            Some("hunter2".into()),
+            Some("env".into()),
+            || unreachable!(),
+        );
+        assert_eq!(password.unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_env_password() {
+        let password = resolve_without_keyring(None, Some("env".into()), || unreachable!());
+        assert_eq!(password.unwrap(), "env");
+    }
+
+    #[test]
+    fn test_falls_back_to_prompt() {
+        let password = resolve_without_keyring(None, Some(String::new()), || Ok("typed".into()));
+        assert_eq!(password.unwrap(), "typed");
+    }
+}
@@ -0,0 +1,114 @@
+use regex::Regex;
+
+use crate::chunk_type::ChunkType;
+
+#[derive(Debug, Clone)]
+pub struct ChunkPattern {
+    source: String,
+    regex: Option<Regex>,
+}
+
+impl ChunkPattern {
+    pub fn exact(&self) -> Option<&str> {
+        match self.regex {
+            None => Some(&self.source),
+            Some(_) => None,
+        }
+    }
+
+    pub fn matches(&self, chunk_type: &ChunkType) -> bool {
+        let chunk_type = chunk_type.to_string();
+        match &self.regex {
+            Some(regex) => regex.is_match(&chunk_type),
+            None => chunk_type == self.source,
+        }
+    }
+}
+
+impl std::str::FromStr for ChunkPattern {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let regex = if let Some(regex) = string
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(Regex::new(regex).map_err(|e| e.to_string())?)
+        } else if string.contains(['?', '*', '[']) {
+            Some(glob_to_regex(string)?)
+        } else {
+            string.parse::<ChunkType>().map_err(|e| e.to_string())?;
+            None
+        };
+        Ok(Self {
+            source: string.to_string(),
+            regex,
+        })
+    }
+}
+
+impl std::fmt::Display for ChunkPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> Result<Regex, String> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '?' => regex.push('.'),
+            '*' => regex.push_str(".*"),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match class.strip_prefix('!') {
+                    Some(negated) => regex.push_str(&format!("[^{}]", negated)),
+                    None => regex.push_str(&format!("[{}]", class)),
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|e| format!("invalid glob {:?}: {}", glob, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn matches(pattern: &str, chunk_type: &str) -> bool {
+        ChunkPattern::from_str(pattern)
+            .unwrap()
+            .matches(&ChunkType::from_str(chunk_type).unwrap())
+    }
+
+    #[test]
+    fn test_exact() {
+        let pattern = ChunkPattern::from_str("ruSt").unwrap();
+        assert_eq!(pattern.exact(), Some("ruSt"));
+        assert!(matches("ruSt", "ruSt"));
+        assert!(!matches("ruSt", "RuSt"));
+        assert!(ChunkPattern::from_str("toolong").is_err());
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(matches("ru??", "ruSt"));
+        assert!(matches("*St", "ruSt"));
+        assert!(matches("[rR]uSt", "RuSt"));
+        assert!(!matches("[!rR]uSt", "RuSt"));
+        assert!(!matches("ru?", "ruSt"));
+        assert_eq!(ChunkPattern::from_str("ru??").unwrap().exact(), None);
+    }
+
+    #[test]
+    fn test_regex() {
+        assert!(matches("/^[a-z]{2}[A-Z][a-z]$/", "ruSt"));
+        assert!(!matches("/^[a-z]{2}[A-Z][a-z]$/", "IHDR"));
+        assert!(matches("/DR/", "IHDR"));
+        assert!(ChunkPattern::from_str("/(/").is_err());
+    }
+}
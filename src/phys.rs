@@ -0,0 +1,115 @@
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const PHYS_CHUNK_TYPE: &str = "pHYs";
+pub const METERS_PER_INCH: f64 = 0.0254;
+const UNIT_METER: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalDimensions {
+    pub x: u32,
+    pub y: u32,
+    pub in_meters: bool,
+}
+
+impl PhysicalDimensions {
+    pub fn from_dpi(x_dpi: f64, y_dpi: f64) -> Result<Self> {
+        let to_ppm = |dpi: f64| {
+            let ppm = (dpi / METERS_PER_INCH).round();
+            if !(1.0..=u32::MAX as f64).contains(&ppm) {
+                return Err(PngmeError::Malformed(format!(
+                    "{} DPI is out of range",
+                    dpi
+                )));
+            }
+            Ok(ppm as u32)
+        };
+        Ok(Self {
+            x: to_ppm(x_dpi)?,
+            y: to_ppm(y_dpi)?,
+            in_meters: true,
+        })
+    }
+
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        self.in_meters.then(|| {
+            (
+                f64::from(self.x) * METERS_PER_INCH,
+                f64::from(self.y) * METERS_PER_INCH,
+            )
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 9] {
+        let mut bytes = [0u8; 9];
+        bytes[..4].copy_from_slice(&self.x.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.y.to_be_bytes());
+        bytes[8] = u8::from(self.in_meters) * UNIT_METER;
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 9] = bytes.try_into().map_err(|_| {
+            PngmeError::Malformed(format!("pHYs chunk should be 9 bytes, got {}", bytes.len()))
+        })?;
+        Ok(Self {
+            x: u32::from_be_bytes(bytes[..4].try_into().unwrap()),
+            y: u32::from_be_bytes(bytes[4..8].try_into().unwrap()),
+            in_meters: bytes[8] == UNIT_METER,
+        })
+    }
+}
+
+pub fn get(png: &Png) -> Result<Option<PhysicalDimensions>> {
+    png.chunk_by_type(PHYS_CHUNK_TYPE)
+        .map(|chunk| PhysicalDimensions::from_bytes(chunk.data()))
+        .transpose()
+}
+
+pub fn set(png: &mut Png, dimensions: PhysicalDimensions) -> Result<()> {
+    png.set_chunk_before_idat(Chunk::new(
+        PHYS_CHUNK_TYPE.parse()?,
+        dimensions.to_bytes().to_vec(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dpi_conversion() {
+        let dimensions = PhysicalDimensions::from_dpi(300.0, 72.0).unwrap();
+        assert_eq!(dimensions.x, 11811);
+        assert_eq!(dimensions.y, 2835);
+        let (x, y) = dimensions.dpi().unwrap();
+        assert_eq!(x.round(), 300.0);
+        assert_eq!(y.round(), 72.0);
+        assert!(PhysicalDimensions::from_dpi(0.0, 72.0).is_err());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let dimensions = PhysicalDimensions::from_dpi(300.0, 300.0).unwrap();
+        let bytes = dimensions.to_bytes();
+        assert_eq!(bytes, [0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, 1]);
+        assert_eq!(PhysicalDimensions::from_bytes(&bytes).unwrap(), dimensions);
+        assert!(PhysicalDimensions::from_bytes(&bytes[..8]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_unit_has_no_dpi() {
+        let dimensions = PhysicalDimensions::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 2, 0]).unwrap();
+        assert_eq!(dimensions.dpi(), None);
+    }
+
+    #[test]
+    fn test_set_and_get() {
+        let mut png = Png::from_chunks(Vec::new());
+        assert_eq!(get(&png).unwrap(), None);
+        let dimensions = PhysicalDimensions::from_dpi(150.0, 150.0).unwrap();
+        set(&mut png, dimensions).unwrap();
+        assert_eq!(get(&png).unwrap(), Some(dimensions));
+    }
+}
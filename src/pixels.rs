@@ -0,0 +1,693 @@
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{self, ColourType, ImageHeader};
+use crate::png::Png;
+use crate::split;
+
+pub const IDAT_CHUNK_TYPE: &str = "IDAT";
+
+// The Adam7 passes as (x, y, x step, y step): a pass holds every pixel at
+// (x + i * x step, y + j * y step), stored as a small image of its own.
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+// The scanline filter written when image data is re-encoded. Adaptive picks
+// one per scanline, which usually gives the smallest output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+    #[default]
+    Adaptive,
+}
+
+impl FromStr for Filter {
+    type Err = PngmeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "sub" => Ok(Self::Sub),
+            "up" => Ok(Self::Up),
+            "average" => Ok(Self::Average),
+            "paeth" => Ok(Self::Paeth),
+            "adaptive" => Ok(Self::Adaptive),
+            _ => Err(PngmeError::Usage(format!(
+                "unknown filter {:?}, expected none, sub, up, average, paeth or adaptive",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Sub => "sub",
+            Self::Up => "up",
+            Self::Average => "average",
+            Self::Paeth => "paeth",
+            Self::Adaptive => "adaptive",
+        })
+    }
+}
+
+// The unfiltered scanlines of an image, without their filter-type bytes.
+// Samples narrower than a byte stay packed, and 16-bit samples stay
+// big-endian, exactly as the PNG stores them. Interlaced images are held
+// de-interlaced; `header.interlaced` only says how `encode` writes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pixels {
+    pub header: ImageHeader,
+    pub data: Vec<u8>,
+}
+
+impl Pixels {
+    // Packs row-major samples into scanlines, the inverse of `samples`.
+    pub fn from_samples(header: ImageHeader, samples: &[u16]) -> Result<Self> {
+        let depth = usize::from(header.bit_depth);
+        let per_row = header.width as usize * header.colour_type.channels();
+        if samples.len() != per_row * header.height as usize {
+            return Err(PngmeError::Usage(format!(
+                "{} samples do not fill a {}x{} {} image",
+                samples.len(),
+                header.width,
+                header.height,
+                header.colour_type
+            )));
+        }
+        if let Some(sample) = samples
+            .iter()
+            .find(|&&sample| u32::from(sample) >> depth != 0)
+        {
+            return Err(PngmeError::Usage(format!(
+                "sample {} does not fit in {} bits",
+                sample, depth
+            )));
+        }
+        let row_len = header.row_len(header.width);
+        let mut data = vec![0u8; row_len * header.height as usize];
+        for (row, samples) in data
+            .chunks_exact_mut(row_len.max(1))
+            .zip(samples.chunks_exact(per_row.max(1)))
+        {
+            for (i, &sample) in samples.iter().enumerate() {
+                match depth {
+                    16 => row[2 * i..2 * i + 2].copy_from_slice(&sample.to_be_bytes()),
+                    8 => row[i] = sample as u8,
+                    _ => {
+                        let bit = i * depth;
+                        row[bit / 8] |= (sample as u8) << (8 - depth - bit % 8);
+                    }
+                }
+            }
+        }
+        Ok(Self { header, data })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.header.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+
+    pub fn colour_type(&self) -> ColourType {
+        self.header.colour_type
+    }
+
+    pub fn row_len(&self) -> usize {
+        self.header.row_len(self.header.width)
+    }
+
+    // Every sample in row-major order, widened to 16 bits.
+    pub fn samples(&self) -> Vec<u16> {
+        let depth = usize::from(self.header.bit_depth);
+        let per_row = self.header.width as usize * self.header.colour_type.channels();
+        let mut samples = Vec::with_capacity(per_row * self.header.height as usize);
+        for row in self.data.chunks_exact(self.row_len().max(1)) {
+            match depth {
+                16 => samples.extend(
+                    row.chunks_exact(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+                ),
+                8 => samples.extend(row.iter().map(|&byte| u16::from(byte))),
+                _ => samples.extend((0..per_row).map(|i| {
+                    let bit = i * depth;
+                    let shift = 8 - depth - bit % 8;
+                    u16::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+                })),
+            }
+        }
+        samples
+    }
+}
+
+// One non-empty Adam7 pass of an image. Pixel (i, j) of the pass is pixel
+// (x + i * x_step, y + j * y_step) of the image.
+struct Pass {
+    x: usize,
+    y: usize,
+    x_step: usize,
+    y_step: usize,
+    width: u32,
+    height: u32,
+}
+
+fn passes(header: &ImageHeader) -> Vec<Pass> {
+    ADAM7
+        .into_iter()
+        .filter_map(|(x, y, x_step, y_step)| {
+            let width = header.width.saturating_sub(x).div_ceil(x_step);
+            let height = header.height.saturating_sub(y).div_ceil(y_step);
+            (width > 0 && height > 0).then_some(Pass {
+                x: x as usize,
+                y: y as usize,
+                x_step: x_step as usize,
+                y_step: y_step as usize,
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+// Copies pixel `from` of scanline `source` over pixel `to` of `target`.
+fn copy_pixel(source: &[u8], from: usize, target: &mut [u8], to: usize, bits: usize) {
+    if bits >= 8 {
+        let len = bits / 8;
+        target[to * len..(to + 1) * len].copy_from_slice(&source[from * len..(from + 1) * len]);
+    } else {
+        let mask = (1u8 << bits) - 1;
+        let shift = |index: usize| 8 - bits - index * bits % 8;
+        let value = (source[from * bits / 8] >> shift(from)) & mask;
+        let byte = &mut target[to * bits / 8];
+        *byte = (*byte & !(mask << shift(to))) | (value << shift(to));
+    }
+}
+
+// Inflates the IDAT stream and undoes the per-scanline filters, gathering
+// the Adam7 passes of an interlaced image back into whole scanlines.
+pub(crate) fn decode(png: &Png) -> Result<Pixels> {
+    let header = ihdr::get(png)
+        .ok_or_else(|| PngmeError::InvalidPng("missing or malformed IHDR chunk".to_string()))?;
+    let compressed: Vec<u8> = png
+        .chunks_by_type(IDAT_CHUNK_TYPE)
+        .flat_map(|chunk| chunk.data().iter().copied())
+        .collect();
+    if compressed.is_empty() {
+        return Err(PngmeError::ChunkNotFound("no IDAT chunk".to_string()));
+    }
+    let row_len = header.row_len(header.width);
+    let expected: u64 = if header.interlaced {
+        passes(&header)
+            .into_iter()
+            .map(|pass| (header.row_len(pass.width) as u64 + 1) * u64::from(pass.height))
+            .sum()
+    } else {
+        (row_len as u64 + 1) * u64::from(header.height)
+    };
+    // Reading one byte past the expected size is enough to tell the stream is
+    // too long without inflating all of a decompression bomb.
+    let mut filtered = Vec::new();
+    flate2::read::ZlibDecoder::new(compressed.as_slice())
+        .take(expected + 1)
+        .read_to_end(&mut filtered)?;
+    if filtered.len() as u64 != expected {
+        return Err(PngmeError::Malformed(format!(
+            "image data is {} bytes, expected {}",
+            filtered.len(),
+            expected
+        )));
+    }
+    let bits = header.bits_per_pixel();
+    if !header.interlaced {
+        let data = unfilter(&filtered, row_len, bits.div_ceil(8))?;
+        return Ok(Pixels { header, data });
+    }
+    let mut data = vec![0u8; row_len * header.height as usize];
+    let mut offset = 0;
+    for pass in passes(&header) {
+        let pass_row_len = header.row_len(pass.width);
+        let len = (pass_row_len + 1) * pass.height as usize;
+        let lines = unfilter(
+            &filtered[offset..offset + len],
+            pass_row_len,
+            bits.div_ceil(8),
+        )?;
+        offset += len;
+        for (j, source) in lines.chunks_exact(pass_row_len).enumerate() {
+            let row = pass.y + j * pass.y_step;
+            let target = &mut data[row * row_len..(row + 1) * row_len];
+            for i in 0..pass.width as usize {
+                copy_pixel(source, i, target, pass.x + i * pass.x_step, bits);
+            }
+        }
+    }
+    Ok(Pixels { header, data })
+}
+
+// Filters and deflates `pixels`, split into Adam7 passes when the header says
+// the image is interlaced, and replaces the IDAT chunks of `png` with the
+// result, leaving every other chunk alone.
+pub(crate) fn encode(
+    png: &mut Png,
+    pixels: &Pixels,
+    filter: Filter,
+    level: flate2::Compression,
+) -> Result<()> {
+    let row_len = pixels.row_len();
+    if pixels.data.len() != row_len * pixels.height() as usize {
+        return Err(PngmeError::Usage(format!(
+            "{} bytes of pixel data do not fill a {}x{} {} image",
+            pixels.data.len(),
+            pixels.width(),
+            pixels.height(),
+            pixels.colour_type()
+        )));
+    }
+    let bpp = pixels.header.bits_per_pixel().div_ceil(8);
+    // The specification recommends no filtering for palette and sub-byte
+    // images, where neighbouring bytes do not hold comparable samples.
+    let filter = match filter {
+        Filter::Adaptive
+            if pixels.header.colour_type == ColourType::Indexed || pixels.header.bit_depth < 8 =>
+        {
+            Filter::None
+        }
+        filter => filter,
+    };
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+    if pixels.header.interlaced {
+        let bits = pixels.header.bits_per_pixel();
+        for pass in passes(&pixels.header) {
+            let pass_row_len = pixels.header.row_len(pass.width);
+            let mut lines = vec![0u8; pass_row_len * pass.height as usize];
+            for (j, target) in lines.chunks_exact_mut(pass_row_len).enumerate() {
+                let row = pass.y + j * pass.y_step;
+                let source = &pixels.data[row * row_len..(row + 1) * row_len];
+                for i in 0..pass.width as usize {
+                    copy_pixel(source, pass.x + i * pass.x_step, target, i, bits);
+                }
+            }
+            write_scanlines(&mut encoder, &lines, pass_row_len, bpp, filter)?;
+        }
+    } else {
+        write_scanlines(&mut encoder, &pixels.data, row_len, bpp, filter)?;
+    }
+    replace_idat(png, &encoder.finish()?)
+}
+
+// Replaces the IDAT chunks of `png` with `stream`, cut into chunks where the
+// first old one was. Decoders simply concatenate IDAT data, so unlike split
+// payloads the parts carry no sequence header.
+pub(crate) fn replace_idat(png: &mut Png, stream: &[u8]) -> Result<()> {
+    let index = png
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.chunk_type().to_string() == IDAT_CHUNK_TYPE)
+        .unwrap_or_else(|| png.append_index());
+    png.remove_chunks(IDAT_CHUNK_TYPE);
+    let chunk_type = ChunkType::from_str(IDAT_CHUNK_TYPE)?;
+    for (offset, part) in stream.chunks(split::DEFAULT_MAX_CHUNK_LEN).enumerate() {
+        png.insert_chunk(index + offset, Chunk::new(chunk_type, part.to_vec()))?;
+    }
+    Ok(())
+}
+
+fn write_scanlines(
+    output: &mut impl Write,
+    data: &[u8],
+    row_len: usize,
+    bpp: usize,
+    filter: Filter,
+) -> Result<()> {
+    let mut previous = vec![0u8; row_len];
+    let mut line = vec![0u8; row_len + 1];
+    for row in data.chunks_exact(row_len.max(1)) {
+        if filter == Filter::Adaptive {
+            // Minimum sum of absolute differences, the heuristic libpng uses.
+            let mut best = u64::MAX;
+            let mut candidate = vec![0u8; row_len + 1];
+            for kind in 0..5 {
+                filter_row(kind, row, &previous, bpp, &mut candidate);
+                let cost = candidate[1..]
+                    .iter()
+                    .map(|&byte| u64::from((byte as i8).unsigned_abs()))
+                    .sum();
+                if cost < best {
+                    best = cost;
+                    line.copy_from_slice(&candidate);
+                }
+            }
+        } else {
+            filter_row(filter as u8, row, &previous, bpp, &mut line);
+        }
+        output.write_all(&line)?;
+        previous.copy_from_slice(row);
+    }
+    Ok(())
+}
+
+// Reverses the five scanline filters; `bpp` is the distance in bytes to the
+// corresponding byte of the previous pixel, at least 1.
+fn unfilter(filtered: &[u8], row_len: usize, bpp: usize) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; filtered.len() / (row_len + 1) * row_len];
+    let mut previous = vec![0u8; row_len];
+    for (y, line) in filtered.chunks_exact(row_len + 1).enumerate() {
+        let (filter, line) = (line[0], &line[1..]);
+        let row = &mut data[y * row_len..(y + 1) * row_len];
+        for x in 0..row_len {
+            let a = if x >= bpp { row[x - bpp] } else { 0 };
+            let b = previous[x];
+            let c = if x >= bpp { previous[x - bpp] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => {
+                    return Err(PngmeError::Malformed(format!(
+                        "scanline {} has unknown filter type {}",
+                        y, filter
+                    )))
+                }
+            };
+            row[x] = line[x].wrapping_add(predicted);
+        }
+        previous.copy_from_slice(row);
+    }
+    Ok(data)
+}
+
+// Writes the filter-type byte and the filtered bytes of `row` to `line`.
+fn filter_row(kind: u8, row: &[u8], previous: &[u8], bpp: usize, line: &mut [u8]) {
+    line[0] = kind;
+    for x in 0..row.len() {
+        let a = if x >= bpp { row[x - bpp] } else { 0 };
+        let b = previous[x];
+        let c = if x >= bpp { previous[x - bpp] } else { 0 };
+        let predicted = match kind {
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => 0,
+        };
+        line[x + 1] = row[x].wrapping_sub(predicted);
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+
+    pub(crate) fn image(width: u32, height: u32, depth: u8, colour: u8, filtered: &[u8]) -> Png {
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend(height.to_be_bytes());
+        header.extend([depth, colour, 0, 0, 0]);
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(filtered).unwrap();
+        Png::from_chunks(vec![
+            chunk("IHDR", &header),
+            chunk("IDAT", &encoder.finish().unwrap()),
+            chunk("IEND", b""),
+        ])
+    }
+
+    fn interlaced_image(width: u32, height: u32, depth: u8, colour: u8, filtered: &[u8]) -> Png {
+        let mut png = image(width, height, depth, colour, filtered);
+        let mut header = ihdr::get(&png).unwrap();
+        header.interlaced = true;
+        png.remove_chunks(ihdr::IHDR_CHUNK_TYPE);
+        png.insert_chunk(0, chunk("IHDR", &header.to_bytes()))
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_decode_every_filter() {
+        // Two greyscale+alpha pixels per row, so each byte's left neighbour
+        // is two bytes back.
+        let filtered = [
+            0, 10, 20, 30, 40, //
+            1, 10, 20, 5, 5, //
+            2, 1, 1, 1, 1, //
+            3, 5, 5, 10, 10, //
+            4, 1, 1, 1, 1,
+        ];
+        let pixels = decode(&image(2, 5, 8, 4, &filtered)).unwrap();
+        assert_eq!(pixels.row_len(), 4);
+        assert_eq!(
+            pixels.data,
+            [
+                10, 20, 30, 40, //
+                10, 20, 15, 25, //
+                11, 21, 16, 26, //
+                10, 15, 23, 30, //
+                11, 16, 24, 31,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_samples_by_depth() {
+        let pixels = decode(&image(3, 1, 2, 0, &[0, 0b0110_1100])).unwrap();
+        assert_eq!(pixels.samples(), [1, 2, 3]);
+        let pixels = decode(&image(1, 1, 16, 0, &[0, 0x12, 0x34])).unwrap();
+        assert_eq!(pixels.samples(), [0x1234]);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_data() {
+        assert!(decode(&image(2, 1, 8, 0, &[0, 1])).is_err());
+        assert!(decode(&image(1, 1, 8, 0, &[5, 1])).is_err());
+        let mut png = image(1, 1, 8, 0, &[0, 1]);
+        png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == IDAT_CHUNK_TYPE);
+        assert!(decode(&png).is_err());
+    }
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(10, 10, 20), 10);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut png = image(2, 2, 8, 0, &[1, 10, 20, 2, 5, 5]);
+        let mut pixels = decode(&png).unwrap();
+        assert_eq!(pixels.data, [10, 30, 15, 35]);
+        pixels.data[3] = 99;
+        encode(
+            &mut png,
+            &pixels,
+            Filter::None,
+            flate2::Compression::default(),
+        )
+        .unwrap();
+        assert_eq!(decode(&png).unwrap(), pixels);
+        assert_eq!(png.chunks_by_type(IDAT_CHUNK_TYPE).count(), 1);
+        assert_eq!(
+            png.chunks().last().unwrap().chunk_type().to_string(),
+            "IEND"
+        );
+    }
+
+    #[test]
+    fn test_from_samples_round_trip() {
+        for (depth, colour, samples) in [
+            (1, 0, vec![1, 0, 1, 1, 0, 0, 0, 1, 1, 1]),
+            (4, 3, vec![15, 3, 0, 7, 9, 1, 2, 12, 5, 4]),
+            (8, 4, (0..20).collect()),
+            (16, 0, (0..10).map(|i| i * 6553).collect()),
+        ] {
+            let png = image(5, 2, depth, colour, &[]);
+            let header = ihdr::get(&png).unwrap();
+            let pixels = Pixels::from_samples(header, &samples).unwrap();
+            assert_eq!(pixels.samples(), samples);
+            assert_eq!((pixels.width(), pixels.height()), (5, 2));
+        }
+    }
+
+    #[test]
+    fn test_from_samples_rejects_bad_input() {
+        let header = ihdr::get(&image(2, 2, 2, 0, &[])).unwrap();
+        assert!(Pixels::from_samples(header, &[0, 1, 2]).is_err());
+        assert!(Pixels::from_samples(header, &[0, 1, 2, 4]).is_err());
+        assert!(Pixels::from_samples(header, &[0, 1, 2, 3]).is_ok());
+    }
+
+    #[test]
+    fn test_every_filter_round_trips() {
+        let mut png = image(3, 3, 8, 2, &[0; 30]);
+        let mut pixels = decode(&png).unwrap();
+        for (i, byte) in pixels.data.iter_mut().enumerate() {
+            *byte = (i * 37 % 251) as u8;
+        }
+        for filter in ["none", "sub", "up", "average", "paeth", "adaptive"] {
+            let filter = Filter::from_str(filter).unwrap();
+            assert_eq!(filter.to_string().parse::<Filter>().unwrap(), filter);
+            encode(&mut png, &pixels, filter, flate2::Compression::fast()).unwrap();
+            assert_eq!(decode(&png).unwrap(), pixels);
+        }
+        assert!(Filter::from_str("best").is_err());
+    }
+
+    #[test]
+    fn test_adaptive_filter_choice() {
+        // A horizontal gradient is all zeros under Sub, and a copy of the row
+        // above is all zeros under Up.
+        let row: Vec<u8> = (0..16).map(|x| x * 10).collect();
+        let mut pixels = decode(&image(16, 2, 8, 0, &[0; 34])).unwrap();
+        pixels.data = [row.clone(), row].concat();
+        let mut png = image(16, 2, 8, 0, &[0; 34]);
+        encode(
+            &mut png,
+            &pixels,
+            Filter::Adaptive,
+            flate2::Compression::default(),
+        )
+        .unwrap();
+        let mut filtered = Vec::new();
+        flate2::read::ZlibDecoder::new(png.payload(IDAT_CHUNK_TYPE).unwrap().unwrap().as_slice())
+            .read_to_end(&mut filtered)
+            .unwrap();
+        assert_eq!((filtered[0], filtered[17]), (1, 2));
+    }
+
+    #[test]
+    fn test_decode_interlaced() {
+        // A 2x2 image only fills passes 1, 6 and 7.
+        let pixels = decode(&interlaced_image(2, 2, 8, 0, &[0, 1, 0, 2, 0, 3, 4])).unwrap();
+        assert_eq!(pixels.data, [1, 2, 3, 4]);
+        assert!(pixels.header.interlaced);
+        assert!(decode(&interlaced_image(2, 2, 8, 0, &[0, 1, 0, 2, 0, 3])).is_err());
+    }
+
+    #[test]
+    fn test_interlaced_round_trip() {
+        for (depth, colour) in [(1, 0), (2, 0), (4, 3), (8, 2), (16, 6)] {
+            let mut png = interlaced_image(11, 9, depth, colour, &[]);
+            let header = ihdr::get(&png).unwrap();
+            let max = (1u32 << depth) - 1;
+            let samples: Vec<u16> = (0..11 * 9 * header.colour_type.channels() as u32)
+                .map(|i| (i * 7919 % (max + 1)) as u16)
+                .collect();
+            let pixels = Pixels::from_samples(header, &samples).unwrap();
+            encode(
+                &mut png,
+                &pixels,
+                Filter::Adaptive,
+                flate2::Compression::fast(),
+            )
+            .unwrap();
+            assert_eq!(decode(&png).unwrap(), pixels);
+
+            let mut flat = png.clone();
+            let mut deinterlaced = pixels.clone();
+            deinterlaced.header.interlaced = false;
+            flat.encode_pixels(&deinterlaced, Filter::None).unwrap();
+            assert!(!ihdr::get(&flat).unwrap().interlaced);
+            assert_eq!(decode(&flat).unwrap().samples(), samples);
+        }
+    }
+
+    #[test]
+    fn test_sixteen_bit_filters_use_whole_pixels() {
+        // Each 16-bit RGB pixel is six bytes, so Sub and Paeth look six bytes
+        // back rather than at the other half of the same sample.
+        let mut png = image(2, 2, 16, 2, &[0; 26]);
+        let header = ihdr::get(&png).unwrap();
+        let samples = [
+            0x0102, 0x0304, 0x0506, 0xFFFF, 0x0000, 0x8001, 7, 8, 9, 10, 11, 12,
+        ];
+        let pixels = Pixels::from_samples(header, &samples).unwrap();
+        for filter in [Filter::Sub, Filter::Average, Filter::Paeth] {
+            encode(&mut png, &pixels, filter, flate2::Compression::fast()).unwrap();
+            assert_eq!(decode(&png).unwrap().samples(), samples);
+        }
+        let filtered = [
+            1, 1, 2, 3, 4, 5, 6, 0xFE, 0xFD, 0xFD, 0xFC, 0x7B, 0xFB, //
+            0, 0, 7, 0, 8, 0, 9, 0, 10, 0, 11, 0, 12,
+        ];
+        assert_eq!(
+            decode(&image(2, 2, 16, 2, &filtered)).unwrap().samples(),
+            samples
+        );
+    }
+
+    #[test]
+    fn test_large_streams_span_plain_idat_chunks() {
+        // Noise barely compresses, so this is well over one chunk's worth.
+        let mut png = image(700, 600, 8, 2, &[]);
+        let header = ihdr::get(&png).unwrap();
+        let mut seed = 1u32;
+        let data = (0..header.row_len(700) * 600)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        let pixels = Pixels { header, data };
+        encode(&mut png, &pixels, Filter::None, flate2::Compression::fast()).unwrap();
+        let idats: Vec<&Chunk> = png.chunks_by_type(IDAT_CHUNK_TYPE).collect();
+        assert_eq!(idats.len(), 2);
+        assert!(idats
+            .iter()
+            .all(|chunk| !chunk.data().starts_with(&split::MAGIC)));
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), IDAT_CHUNK_TYPE);
+        assert_eq!(decode(&png).unwrap(), pixels);
+    }
+
+    #[test]
+    fn test_encode_rejects_wrong_data_length() {
+        let mut png = image(2, 2, 8, 0, &[1, 10, 20, 2, 5, 5]);
+        let mut pixels = decode(&png).unwrap();
+        pixels.data.pop();
+        let before = png.as_bytes();
+        assert!(matches!(
+            png.encode_pixels(&pixels, Filter::None),
+            Err(PngmeError::Usage(_))
+        ));
+        assert_eq!(png.as_bytes(), before);
+    }
+}
@@ -0,0 +1,994 @@
+use crate::chunk::{human_size, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{self, IHDR_CHUNK_TYPE};
+use crate::pixels::{self, Filter, Pixels};
+use crate::registry;
+use crate::split;
+use crate::validate;
+
+const UNIQUE_TYPES: [&[u8; 4]; 3] = [b"IHDR", b"PLTE", b"IEND"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Png {
+    chunks: Vec<Chunk>,
+    // Bytes after IEND, such as an appended zip archive, kept so the file
+    // round-trips unchanged.
+    trailing_data: Vec<u8>,
+}
+
+// Problems `Png::try_from_lossy` stepped over instead of failing on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    CrcMismatch {
+        index: usize,
+        offset: u64,
+        expected: u32,
+        found: u32,
+    },
+    UnknownChunk {
+        index: usize,
+        offset: u64,
+        chunk_type: ChunkType,
+    },
+    TrailingData {
+        offset: u64,
+        length: usize,
+        reason: String,
+    },
+}
+
+// What `Png::salvage` had to leave out of a cut-short file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Salvage {
+    pub truncated_at: Option<u64>,
+    pub partial_chunk: Option<String>,
+    pub missing_iend: bool,
+}
+
+impl Salvage {
+    pub fn is_complete(&self) -> bool {
+        self.truncated_at.is_none() && !self.missing_iend
+    }
+}
+
+impl std::fmt::Display for Salvage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.truncated_at, &self.partial_chunk) {
+            (Some(offset), Some(reason)) => {
+                write!(f, "file is truncated at offset {:#X}: {}", offset, reason)?
+            }
+            _ if self.missing_iend => write!(f, "file has no IEND chunk")?,
+            _ => write!(f, "file is complete")?,
+        }
+        if self.truncated_at.is_some() && self.missing_iend {
+            write!(f, ", and has no IEND chunk")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CrcMismatch {
+                index,
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "chunk #{} at offset {:#X}: crc mismatch: expected {:#010x}, found {:#010x}",
+                index, offset, expected, found
+            ),
+            Self::UnknownChunk {
+                index,
+                offset,
+                chunk_type,
+            } => write!(
+                f,
+                "chunk #{} at offset {:#X}: unknown chunk type {}",
+                index, offset, chunk_type
+            ),
+            Self::TrailingData {
+                offset,
+                length,
+                reason,
+            } => write!(
+                f,
+                "ignored {} bytes at offset {:#X}: {}",
+                length, offset, reason
+            ),
+        }
+    }
+}
+
+impl Png {
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Self {
+            chunks,
+            trailing_data: Vec::new(),
+        }
+    }
+
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
+
+    pub fn strip_trailing_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing_data)
+    }
+
+    pub(crate) fn with_trailing_data(mut self, trailing_data: Vec<u8>) -> Self {
+        self.trailing_data = trailing_data;
+        self
+    }
+
+    pub fn append_chunk(&mut self, chunk: Chunk) -> Result<()> {
+        self.insert_chunk(self.append_index(), chunk)
+    }
+
+    pub fn push_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+
+    pub fn append_index(&self) -> usize {
+        self.chunks
+            .iter()
+            .position(|chunk| &chunk.chunk_type().bytes() == b"IEND")
+            .unwrap_or(self.chunks.len())
+    }
+
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        self.check_unique(chunk.chunk_type())?;
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    pub fn set_chunk_before_idat(&mut self, chunk: Chunk) -> Result<()> {
+        let chunk_type = *chunk.chunk_type();
+        let index = self
+            .chunks
+            .iter()
+            .position(|existing| existing.chunk_type() == &chunk_type)
+            .or_else(|| {
+                self.chunks
+                    .iter()
+                    .position(|existing| &existing.chunk_type().bytes() == b"IDAT")
+            })
+            .unwrap_or_else(|| self.append_index());
+        self.remove_chunks_where(|existing| existing.chunk_type() == &chunk_type);
+        self.insert_chunk(index, chunk)
+    }
+
+    pub fn check_unique(&self, chunk_type: &ChunkType) -> Result<()> {
+        if UNIQUE_TYPES.contains(&&chunk_type.bytes())
+            && self
+                .chunks
+                .iter()
+                .any(|chunk| chunk.chunk_type() == chunk_type)
+        {
+            return Err(PngmeError::InvalidPng(format!(
+                "{} may only appear once and the file already has one",
+                chunk_type
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn chunk_at(&self, index: usize) -> Option<&Chunk> {
+        self.chunks.get(index)
+    }
+
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(PngmeError::InvalidPng(format!(
+                "chunk index {} is out of range, the file has {} chunks",
+                index,
+                self.chunks.len()
+            )));
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    pub fn remove_first_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type().to_string() == chunk_type)
+            .ok_or_else(|| PngmeError::ChunkNotFound(format!("no chunk of type {}", chunk_type)))?;
+        Ok(self.chunks.remove(index))
+    }
+
+    pub fn remove_chunks(&mut self, chunk_type: &str) -> Vec<Chunk> {
+        self.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == chunk_type)
+    }
+
+    pub fn remove_chunks_where(&mut self, mut predicate: impl FnMut(&Chunk) -> bool) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| predicate(chunk));
+        self.chunks = kept;
+        removed
+    }
+
+    // Sorts the chunks into the canonical order. The sort is stable, so chunks
+    // of the same rank, such as text chunks or consecutive IDATs, keep their
+    // relative order. Returns whether anything moved.
+    pub fn normalize_order(&mut self) -> bool {
+        let first_idat = self
+            .chunks
+            .iter()
+            .position(|chunk| &chunk.chunk_type().bytes() == b"IDAT")
+            .unwrap_or(self.chunks.len());
+        let mut ranked: Vec<(u8, Chunk)> = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let rank =
+                    validate::canonical_rank(&chunk.chunk_type().to_string(), index > first_idat);
+                (rank, chunk)
+            })
+            .collect();
+        let sorted = ranked.is_sorted_by_key(|(rank, _)| *rank);
+        ranked.sort_by_key(|(rank, _)| *rank);
+        self.chunks = ranked.into_iter().map(|(_, chunk)| chunk).collect();
+        !sorted
+    }
+
+    pub fn header(&self) -> &[u8; 8] {
+        &Self::STANDARD_HEADER
+    }
+
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
+        self.chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type().to_string() == chunk_type)
+    }
+
+    pub fn chunks_by_type<'a>(&'a self, chunk_type: &'a str) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks
+            .iter()
+            .filter(move |chunk| chunk.chunk_type().to_string() == chunk_type)
+    }
+
+    pub fn append_payload(
+        &mut self,
+        chunk_type: ChunkType,
+        data: &[u8],
+        max_chunk_len: usize,
+    ) -> Result<usize> {
+        self.check_unique(&chunk_type)?;
+        self.insert_payload(self.append_index(), chunk_type, data, max_chunk_len)
+    }
+
+    pub fn replace_payload(
+        &mut self,
+        chunk_type: ChunkType,
+        data: &[u8],
+        max_chunk_len: usize,
+    ) -> Result<(usize, usize)> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type() == &chunk_type)
+            .unwrap_or_else(|| self.append_index());
+        self.remove_chunks_where(|chunk| chunk.chunk_type() == &chunk_type);
+        let count = self.insert_payload(index, chunk_type, data, max_chunk_len)?;
+        Ok((index, count))
+    }
+
+    pub fn insert_payload(
+        &mut self,
+        index: usize,
+        chunk_type: ChunkType,
+        data: &[u8],
+        max_chunk_len: usize,
+    ) -> Result<usize> {
+        if index > self.chunks.len() {
+            return Err(PngmeError::InvalidPng(format!(
+                "insertion index {} is out of range, the file has {} chunks",
+                index,
+                self.chunks.len()
+            )));
+        }
+        let parts = split::split(data, max_chunk_len)?;
+        let count = parts.len();
+        self.chunks.splice(
+            index..index,
+            parts.into_iter().map(|part| Chunk::new(chunk_type, part)),
+        );
+        Ok(count)
+    }
+
+    pub fn payload(&self, chunk_type: &str) -> Result<Option<Vec<u8>>> {
+        let Some(first) = self.chunk_by_type(chunk_type) else {
+            return Ok(None);
+        };
+        let joined = split::join(self.chunks_by_type(chunk_type).map(Chunk::data))?;
+        Ok(Some(joined.unwrap_or_else(|| first.data().to_vec())))
+    }
+
+    // The image as unfiltered scanlines: every IDAT chunk concatenated,
+    // inflated and unfiltered.
+    pub fn decode_pixels(&self) -> Result<Pixels> {
+        pixels::decode(self)
+    }
+
+    // The inverse of `decode_pixels`. Replaces the IDAT chunks, and the IHDR
+    // chunk too when `pixels` describes a different image.
+    pub fn encode_pixels(&mut self, pixels: &Pixels, filter: Filter) -> Result<()> {
+        self.encode_pixels_at(pixels, filter, flate2::Compression::default())
+    }
+
+    pub(crate) fn encode_pixels_at(
+        &mut self,
+        pixels: &Pixels,
+        filter: Filter,
+        level: flate2::Compression,
+    ) -> Result<()> {
+        pixels::encode(self, pixels, filter, level)?;
+        if ihdr::get(self) != Some(pixels.header) {
+            let chunk = Chunk::new(IHDR_CHUNK_TYPE.parse()?, pixels.header.to_bytes().to_vec());
+            match self
+                .chunks
+                .iter()
+                .position(|existing| existing.chunk_type().to_string() == IHDR_CHUNK_TYPE)
+            {
+                Some(index) => self.chunks[index] = chunk,
+                None => self.chunks.insert(0, chunk),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.header()
+            .iter()
+            .copied()
+            .chain(self.chunks.iter().flat_map(Chunk::as_bytes))
+            .chain(self.trailing_data.iter().copied())
+            .collect()
+    }
+}
+
+fn is_end(chunk: &Chunk) -> bool {
+    &chunk.chunk_type().bytes() == b"IEND"
+}
+
+impl Png {
+    // Like `try_from`, but keeps chunks with bad CRCs and stops at the first
+    // unparseable bytes instead of failing, so damaged files can be inspected.
+    pub fn try_from_lossy(
+        value: &[u8],
+    ) -> std::result::Result<(Self, Vec<ParseWarning>), PngmeError> {
+        if value.len() < 8 || value[..8] != Self::STANDARD_HEADER {
+            return Err(PngmeError::InvalidSignature);
+        }
+        let mut chunks = Vec::new();
+        let mut warnings = Vec::new();
+        let mut trailing_data = Vec::new();
+        let mut offset = 8;
+        while offset < value.len() {
+            let start = offset as u64;
+            let chunk = match Chunk::try_from_ignoring_crc(&value[offset..]) {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    warnings.push(ParseWarning::TrailingData {
+                        offset: start,
+                        length: value.len() - offset,
+                        reason: error.to_string(),
+                    });
+                    break;
+                }
+            };
+            let index = chunks.len();
+            if let Some(found) = chunk.stored_crc() {
+                warnings.push(ParseWarning::CrcMismatch {
+                    index,
+                    offset: start,
+                    expected: chunk.crc(),
+                    found,
+                });
+            }
+            if registry::lookup(chunk.chunk_type()).is_none() {
+                warnings.push(ParseWarning::UnknownChunk {
+                    index,
+                    offset: start,
+                    chunk_type: *chunk.chunk_type(),
+                });
+            }
+            offset += chunk.length() as usize + 12;
+            let end = is_end(&chunk);
+            chunks.push(chunk.with_offset(start));
+            if end {
+                trailing_data = value[offset..].to_vec();
+                break;
+            }
+        }
+        let png = Self {
+            chunks,
+            trailing_data,
+        };
+        Ok((png, warnings))
+    }
+
+    // Like `try_from`, but a file that ends mid-chunk keeps every complete
+    // chunk before the cut. Any other damage is still an error.
+    pub fn salvage(value: &[u8]) -> std::result::Result<(Self, Salvage), PngmeError> {
+        if value.len() < 8 || value[..8] != Self::STANDARD_HEADER {
+            return Err(PngmeError::InvalidSignature);
+        }
+        let mut chunks = Vec::new();
+        let mut salvage = Salvage::default();
+        let mut trailing_data = Vec::new();
+        let mut offset = 8;
+        while offset < value.len() {
+            let chunk = match Chunk::try_from(&value[offset..]) {
+                Ok(chunk) => chunk.with_offset(offset as u64),
+                Err(error @ PngmeError::Truncated(_)) => {
+                    salvage.truncated_at = Some(offset as u64);
+                    salvage.partial_chunk = Some(format!("chunk #{}: {}", chunks.len(), error));
+                    break;
+                }
+                Err(error) => return Err(PngmeError::in_chunk(chunks.len(), offset as u64, error)),
+            };
+            offset += chunk.length() as usize + 12;
+            let end = is_end(&chunk);
+            chunks.push(chunk);
+            if end {
+                trailing_data = value[offset..].to_vec();
+                break;
+            }
+        }
+        salvage.missing_iend = !chunks.iter().any(is_end);
+        let png = Self {
+            chunks,
+            trailing_data,
+        };
+        Ok((png, salvage))
+    }
+
+    // Like `try_from`, but chunks whose CRC does not match are kept, with the
+    // CRC from the file available through `Chunk::stored_crc`.
+    pub fn try_from_ignoring_crc(value: &[u8]) -> std::result::Result<Self, PngmeError> {
+        Self::parse(value, Chunk::try_from_ignoring_crc)
+    }
+
+    fn parse(
+        value: &[u8],
+        read_chunk: impl Fn(&[u8]) -> std::result::Result<Chunk, PngmeError>,
+    ) -> std::result::Result<Self, PngmeError> {
+        if value.len() < 8 || value[..8] != Self::STANDARD_HEADER {
+            return Err(PngmeError::InvalidSignature);
+        }
+        let mut chunks = Vec::new();
+        let mut trailing_data = Vec::new();
+        let mut offset = 8;
+        while offset < value.len() {
+            let chunk = read_chunk(&value[offset..])
+                .map_err(|e| PngmeError::in_chunk(chunks.len(), offset as u64, e))?
+                .with_offset(offset as u64);
+            offset += chunk.length() as usize + 12;
+            let end = is_end(&chunk);
+            chunks.push(chunk);
+            if end {
+                trailing_data = value[offset..].to_vec();
+                break;
+            }
+        }
+        Ok(Self {
+            chunks,
+            trailing_data,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for Png {
+    type Error = PngmeError;
+
+    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+        Self::parse(value, |bytes| Chunk::try_from(bytes))
+    }
+}
+
+impl std::fmt::Display for Png {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total: usize = self.chunks.iter().map(|c| c.length() as usize + 12).sum();
+        writeln!(
+            f,
+            "PNG, {} chunks, {}",
+            self.chunks.len(),
+            human_size((Self::STANDARD_HEADER.len() + total + self.trailing_data.len()) as u64)
+        )?;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>5}  {}  {:>9}  {:#010x}",
+                index,
+                chunk.chunk_type(),
+                human_size(chunk.length().into()),
+                chunk.crc()
+            )?;
+        }
+        if !self.trailing_data.is_empty() {
+            writeln!(
+                f,
+                "{:>5}  {:>4}  {:>9}",
+                "",
+                "tail",
+                human_size(self.trailing_data.len() as u64)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
+        let chunk_type: ChunkType = chunk_type.parse()?;
+        Ok(Chunk::new(chunk_type, data.as_bytes().to_vec()))
+    }
+
+    fn testing_chunks() -> Vec<Chunk> {
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(testing_chunks())
+    }
+
+    #[test]
+    fn test_from_chunks() {
+        let png = testing_png();
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_valid_from_bytes() {
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(testing_chunks().iter().flat_map(Chunk::as_bytes))
+            .collect();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png, testing_png());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
+            .iter()
+            .copied()
+            .chain(testing_chunks().iter().flat_map(Chunk::as_bytes))
+            .collect();
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_chunk() {
+        let mut bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(testing_chunks().iter().flat_map(Chunk::as_bytes))
+            .collect();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_chunk_by_type() {
+        let png = testing_png();
+        let chunk = png.chunk_by_type("FrSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+    }
+
+    #[test]
+    fn test_append_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        let chunk = png.chunk_by_type("TeSt").unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    fn test_append_chunk_goes_before_iend() {
+        let mut png = testing_png();
+        png.push_chunk(chunk_from_strings("IEND", "").unwrap());
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(png.chunks()[3].chunk_type().to_string(), "TeSt");
+        assert_eq!(png.chunks()[4].chunk_type().to_string(), "IEND");
+
+        png.push_chunk(chunk_from_strings("LaTe", "after").unwrap());
+        assert_eq!(png.chunks()[5].chunk_type().to_string(), "LaTe");
+    }
+
+    #[test]
+    fn test_refuses_duplicate_structural_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IHDR", "header").unwrap())
+            .unwrap();
+        assert!(png
+            .append_chunk(chunk_from_strings("IHDR", "again").unwrap())
+            .is_err());
+        assert!(png
+            .insert_chunk(0, chunk_from_strings("IHDR", "again").unwrap())
+            .is_err());
+        let ihdr = ChunkType::try_from(*b"IHDR").unwrap();
+        assert!(png.append_payload(ihdr, b"again", 64).is_err());
+        png.append_chunk(chunk_from_strings("TeSt", "one").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "two").unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_set_chunk_before_idat() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "").unwrap(),
+            chunk_from_strings("IDAT", "").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.set_chunk_before_idat(chunk_from_strings("pHYs", "one").unwrap())
+            .unwrap();
+        png.set_chunk_before_idat(chunk_from_strings("pHYs", "two").unwrap())
+            .unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunks()[1].data(), b"two");
+    }
+
+    #[test]
+    fn test_remove_first_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        png.remove_first_chunk("TeSt").unwrap();
+        assert!(png.chunk_by_type("TeSt").is_none());
+        assert!(png.remove_first_chunk("TeSt").is_err());
+    }
+
+    #[test]
+    fn test_insert_payload() {
+        let mut png = testing_png();
+        let chunk_type = ChunkType::try_from(*b"ruSt").unwrap();
+        let data: Vec<u8> = (0..=255).cycle().take(500).collect();
+        assert_eq!(png.insert_payload(1, chunk_type, &data, 200).unwrap(), 3);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["FrSt", "ruSt", "ruSt", "ruSt", "miDl", "LASt"]);
+        assert_eq!(png.payload("ruSt").unwrap().unwrap(), data);
+        assert!(png.insert_payload(7, chunk_type, b"x", 200).is_err());
+    }
+
+    #[test]
+    fn test_replace_payload() {
+        let mut png = testing_png();
+        let chunk_type = ChunkType::try_from(*b"miDl").unwrap();
+        png.insert_payload(3, chunk_type, b"second", 64).unwrap();
+        assert_eq!(png.replace_payload(chunk_type, b"new", 64).unwrap(), (1, 1));
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.chunks()[1].data(), b"new");
+
+        let other = ChunkType::try_from(*b"neWw").unwrap();
+        assert_eq!(png.replace_payload(other, b"x", 64).unwrap(), (3, 1));
+    }
+
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "one").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "two").unwrap())
+            .unwrap();
+        let data: Vec<&[u8]> = png.chunks_by_type("TeSt").map(Chunk::data).collect();
+        assert_eq!(data, [b"one".as_slice(), b"two"]);
+        assert_eq!(png.chunks_by_type("NoNe").count(), 0);
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "TeSt");
+        assert_eq!(png.chunks().len(), 4);
+    }
+
+    #[test]
+    fn test_chunk_at() {
+        let mut png = testing_png();
+        assert_eq!(png.chunk_at(1).unwrap().chunk_type().to_string(), "miDl");
+        assert!(png.chunk_at(3).is_none());
+        let removed = png.remove_chunk_at(0).unwrap();
+        assert_eq!(removed.chunk_type().to_string(), "FrSt");
+        assert!(png.remove_chunk_at(2).is_err());
+    }
+
+    #[test]
+    fn test_remove_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "one").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "two").unwrap())
+            .unwrap();
+        let removed = png.remove_chunks("TeSt");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[1].data(), b"two");
+        assert_eq!(png, testing_png());
+        assert!(png.remove_chunks("TeSt").is_empty());
+    }
+
+    #[test]
+    fn test_remove_chunks_where() {
+        let mut png = testing_png();
+        let removed = png.remove_chunks_where(|chunk| chunk.chunk_type().is_critical());
+        assert_eq!(removed.len(), 2);
+        assert_eq!(png.chunks().len(), 1);
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), "miDl");
+    }
+
+    #[test]
+    fn test_split_payload_round_trip() {
+        let mut png = testing_png();
+        let data: Vec<u8> = (0..=255).cycle().take(500).collect();
+        let chunk_type = ChunkType::try_from(*b"ruSt").unwrap();
+        assert_eq!(png.append_payload(chunk_type, &data, 64).unwrap(), 10);
+        assert_eq!(png.chunks_by_type("ruSt").count(), 10);
+        assert_eq!(png.payload("ruSt").unwrap().unwrap(), data);
+    }
+
+    #[test]
+    fn test_unsplit_payload() {
+        let png = testing_png();
+        assert_eq!(png.payload("miDl").unwrap().unwrap(), b"I am another chunk");
+        assert_eq!(png.payload("NoNe").unwrap(), None);
+    }
+
+    #[test]
+    fn test_as_bytes_round_trip() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        assert_eq!(Png::try_from(bytes.as_ref()).unwrap(), png);
+    }
+
+    #[test]
+    fn test_parsed_chunks_record_offsets() {
+        let png = testing_png();
+        let parsed = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        let offsets: Vec<Option<u64>> = parsed.chunks().iter().map(Chunk::offset).collect();
+        let first = 8 + 12 + "I am the first chunk".len() as u64;
+        let second = first + 12 + "I am another chunk".len() as u64;
+        assert_eq!(offsets, [Some(8), Some(first), Some(second)]);
+        assert_eq!(png.chunks()[0].offset(), None);
+    }
+
+    #[test]
+    fn test_png_display_is_a_table() {
+        let display = testing_png().to_string();
+        let lines: Vec<&str> = display.lines().collect();
+        assert_eq!(lines[0], "PNG, 3 chunks, 101 B");
+        assert!(lines[1].starts_with("    0  FrSt       20 B  0x"));
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_error_offset() {
+        let chunks = testing_chunks();
+        let mut bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(chunks.iter().flat_map(Chunk::as_bytes))
+            .collect();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let error = Png::try_from(bytes.as_ref()).unwrap_err();
+        let expected = 8 + chunks[..2]
+            .iter()
+            .map(|c| c.as_bytes().len())
+            .sum::<usize>();
+        assert_eq!(error.location(), Some((2, expected as u64)));
+        assert!(error
+            .to_string()
+            .starts_with(&format!("chunk #2 at offset {:#X}: crc mismatch", expected)));
+        assert_eq!(PngmeError::InvalidPng("x".into()).location(), None);
+    }
+
+    #[test]
+    fn test_truncated_error_context() {
+        let mut bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(testing_chunks().iter().flat_map(Chunk::as_bytes))
+            .collect();
+        bytes.truncate(18);
+        let error = Png::try_from(bytes.as_ref()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "chunk #0 at offset 0x8: truncated data: expected at least 12 bytes, got 10"
+        );
+    }
+
+    #[test]
+    fn test_try_from_lossy_collects_warnings() {
+        let chunks = testing_chunks();
+        let mut bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(chunks.iter().flat_map(Chunk::as_bytes))
+            .collect();
+        let second = 8 + chunks[0].as_bytes().len();
+        let crc_end = second + chunks[1].as_bytes().len();
+        bytes[crc_end - 1] ^= 0xff;
+        bytes.extend_from_slice(b"junk");
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let (png, warnings) = Png::try_from_lossy(&bytes).unwrap();
+        assert_eq!(png.chunks(), testing_png().chunks());
+        assert_eq!(png.chunks()[1].offset(), Some(second as u64));
+        assert!(matches!(
+            warnings[..],
+            [
+                ParseWarning::UnknownChunk { index: 0, .. },
+                ParseWarning::CrcMismatch { index: 1, .. },
+                ParseWarning::UnknownChunk { index: 1, .. },
+                ParseWarning::UnknownChunk { index: 2, .. },
+                ParseWarning::TrailingData { length: 4, .. },
+            ]
+        ));
+        assert!(warnings[4]
+            .to_string()
+            .starts_with("ignored 4 bytes at offset"));
+    }
+
+    #[test]
+    fn test_try_from_lossy_still_needs_a_signature() {
+        assert!(Png::try_from_lossy(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_try_from_ignoring_crc() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let png = Png::try_from_ignoring_crc(&bytes).unwrap();
+        assert_eq!(png, testing_png());
+        let flagged: Vec<usize> = png
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.stored_crc().is_some())
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(flagged, [2]);
+
+        bytes.truncate(bytes.len() - 1);
+        assert!(Png::try_from_ignoring_crc(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_salvage_truncated_file() {
+        let chunks = testing_chunks();
+        let mut bytes = testing_png().as_bytes();
+        let third = 8 + chunks[..2]
+            .iter()
+            .map(|c| c.as_bytes().len())
+            .sum::<usize>();
+        bytes.truncate(third + 10);
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let (png, salvage) = Png::salvage(&bytes).unwrap();
+        assert_eq!(png.chunks(), &chunks[..2]);
+        assert_eq!(salvage.truncated_at, Some(third as u64));
+        assert!(salvage.missing_iend);
+        assert_eq!(
+            salvage.to_string(),
+            format!(
+                "file is truncated at offset {:#X}: chunk #2: truncated data: \
+                 expected at least 12 bytes, got 10, and has no IEND chunk",
+                third
+            )
+        );
+    }
+
+    #[test]
+    fn test_salvage_complete_file() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap())
+            .unwrap();
+        let (salvaged, salvage) = Png::salvage(&png.as_bytes()).unwrap();
+        assert_eq!(salvaged, png);
+        assert!(salvage.is_complete());
+
+        let mut bytes = png.as_bytes();
+        bytes[20] ^= 0xff;
+        assert!(Png::salvage(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_trailing_data_round_trips() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap())
+            .unwrap();
+        let mut bytes = png.as_bytes();
+        bytes.extend_from_slice(b"appended");
+
+        let mut parsed = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(parsed.chunks(), png.chunks());
+        assert_eq!(parsed.trailing_data(), b"appended");
+        assert_eq!(parsed.as_bytes(), bytes);
+        assert!(parsed.to_string().contains("tail"));
+
+        assert_eq!(parsed.strip_trailing_data(), b"appended");
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_normalize_order() {
+        let types = |png: &Png| -> Vec<String> {
+            png.chunks()
+                .iter()
+                .map(|chunk| chunk.chunk_type().to_string())
+                .collect()
+        };
+        let mut png = Png::from_chunks(
+            [
+                "IHDR", "tEXt", "pHYs", "PLTE", "gAMA", "IDAT", "IDAT", "tIME", "tRNS", "IEND",
+            ]
+            .iter()
+            .map(|t| chunk_from_strings(t, "").unwrap())
+            .collect(),
+        );
+        assert!(png.normalize_order());
+        assert_eq!(
+            types(&png),
+            ["IHDR", "gAMA", "PLTE", "tRNS", "pHYs", "tEXt", "IDAT", "IDAT", "tIME", "IEND"]
+        );
+        assert!(crate::validate::violations(&png).is_empty());
+        assert!(!png.normalize_order());
+    }
+
+    #[test]
+    fn test_pixels_round_trip() {
+        let mut png = crate::pixels::tests::image(2, 2, 8, 0, &[0, 1, 2, 0, 3, 4]);
+        let mut pixels = png.decode_pixels().unwrap();
+        assert_eq!((pixels.width(), pixels.height()), (2, 2));
+        assert_eq!(pixels.samples(), [1, 2, 3, 4]);
+        pixels.data[0] = 9;
+        png.encode_pixels(&pixels, Filter::Adaptive).unwrap();
+        assert_eq!(png.decode_pixels().unwrap(), pixels);
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_encode_pixels_rewrites_header() {
+        let mut png = crate::pixels::tests::image(2, 2, 8, 0, &[0, 1, 2, 0, 3, 4]);
+        let mut header = ihdr::get(&png).unwrap();
+        header.width = 1;
+        header.height = 3;
+        let pixels = Pixels::from_samples(header, &[5, 6, 7]).unwrap();
+        png.encode_pixels(&pixels, Filter::Sub).unwrap();
+        assert_eq!(ihdr::get(&png), Some(header));
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), IHDR_CHUNK_TYPE);
+        assert_eq!(png.decode_pixels().unwrap().samples(), [5, 6, 7]);
+    }
+}
@@ -0,0 +1,183 @@
+use serde::Deserialize;
+
+use crate::chunk::Chunk;
+use crate::error::PngmeError;
+use crate::pattern::ChunkPattern;
+use crate::png::Png;
+use crate::registry;
+use crate::validate::Violation;
+
+// Rules for which chunks a file may carry, read from a TOML file such as:
+//
+//     allowed_chunks = ["IHDR", "PLTE", "IDAT", "IEND", "tEXt"]
+//     max_ancillary_size = 65536
+//     allow_unknown_private = false
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub allowed_chunks: Option<Vec<String>>,
+    pub max_ancillary_size: Option<u32>,
+    #[serde(default = "allow_by_default")]
+    pub allow_unknown_private: bool,
+}
+
+fn allow_by_default() -> bool {
+    true
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allowed_chunks: None,
+            max_ancillary_size: None,
+            allow_unknown_private: true,
+        }
+    }
+}
+
+impl std::str::FromStr for Policy {
+    type Err = PngmeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|error| PngmeError::Usage(format!("invalid policy: {}", error)))
+    }
+}
+
+impl Policy {
+    pub fn violations(&self, png: &Png) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            let chunk_type = chunk.chunk_type();
+            let name = chunk_type.to_string();
+            let listed = self
+                .allowed_chunks
+                .as_ref()
+                .map(|allowed| allowed.contains(&name));
+            if listed == Some(false) {
+                violations.push(Violation::at(
+                    index,
+                    format!("{} is not an allowed chunk type", name),
+                ));
+            } else if listed.is_none()
+                && !self.allow_unknown_private
+                && !chunk_type.is_public()
+                && registry::lookup(chunk_type).is_none()
+            {
+                violations.push(Violation::at(
+                    index,
+                    format!("{} is an unknown private chunk", name),
+                ));
+            }
+            if let Some(max) = self.max_ancillary_size {
+                if !chunk_type.is_critical() && chunk.length() > max {
+                    violations.push(Violation::at(
+                        index,
+                        format!(
+                            "{} payload is {} bytes, the limit is {}",
+                            name,
+                            chunk.length(),
+                            max
+                        ),
+                    ));
+                }
+            }
+        }
+        violations
+    }
+}
+
+// Chunks that are not part of any known specification or match the denylist,
+// i.e. the ones most likely to carry data nobody meant to commit.
+pub fn hidden_chunks<'a>(png: &'a Png, deny: &[ChunkPattern]) -> Vec<(usize, &'a Chunk)> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            registry::lookup(chunk.chunk_type()).is_none()
+                || deny
+                    .iter()
+                    .any(|pattern| pattern.matches(chunk.chunk_type()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png(chunks: &[(&str, usize)]) -> Png {
+        Png::from_chunks(
+            chunks
+                .iter()
+                .map(|(t, len)| Chunk::new(ChunkType::from_str(t).unwrap(), vec![0; *len]))
+                .collect(),
+        )
+    }
+
+    fn messages(policy: &Policy, file: &Png) -> Vec<String> {
+        policy
+            .violations(file)
+            .iter()
+            .map(Violation::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = Policy::from_str(
+            "allowed_chunks = [\"IHDR\", \"IDAT\", \"IEND\"]\nmax_ancillary_size = 10\n",
+        )
+        .unwrap();
+        assert_eq!(policy.allowed_chunks.unwrap().len(), 3);
+        assert_eq!(policy.max_ancillary_size, Some(10));
+        assert!(policy.allow_unknown_private);
+        assert_eq!(Policy::from_str("").unwrap(), Policy::default());
+        assert!(Policy::from_str("max_size = 1").is_err());
+    }
+
+    #[test]
+    fn test_allowed_chunks_and_size() {
+        let policy = Policy {
+            allowed_chunks: Some(vec!["IHDR".into(), "tEXt".into(), "IEND".into()]),
+            max_ancillary_size: Some(4),
+            ..Policy::default()
+        };
+        let file = png(&[("IHDR", 13), ("tEXt", 5), ("ruSt", 1), ("IEND", 0)]);
+        assert_eq!(
+            messages(&policy, &file),
+            [
+                "chunk #1: tEXt payload is 5 bytes, the limit is 4",
+                "chunk #2: ruSt is not an allowed chunk type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_private_chunks() {
+        let policy = Policy {
+            allow_unknown_private: false,
+            ..Policy::default()
+        };
+        let file = png(&[("IHDR", 13), ("ruSt", 1), ("RUST", 1), ("IEND", 0)]);
+        assert_eq!(
+            messages(&policy, &file),
+            ["chunk #1: ruSt is an unknown private chunk"]
+        );
+        assert!(Policy::default().violations(&file).is_empty());
+    }
+
+    #[test]
+    fn test_hidden_chunks() {
+        let file = png(&[("IHDR", 13), ("tEXt", 4), ("ruSt", 8), ("IEND", 0)]);
+        let indices = |deny: &[ChunkPattern]| -> Vec<usize> {
+            hidden_chunks(&file, deny)
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect()
+        };
+        assert_eq!(indices(&[]), [2]);
+        assert_eq!(indices(&["tEXt".parse().unwrap()]), [1, 2]);
+    }
+}
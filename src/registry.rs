@@ -0,0 +1,121 @@
+use crate::chunk_type::ChunkType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownChunk {
+    pub chunk_type: &'static str,
+    pub description: &'static str,
+    pub origin: &'static str,
+}
+
+const fn known(
+    chunk_type: &'static str,
+    description: &'static str,
+    origin: &'static str,
+) -> KnownChunk {
+    KnownChunk {
+        chunk_type,
+        description,
+        origin,
+    }
+}
+
+const SPEC: &str = "PNG specification";
+const EXTENSIONS: &str = "PNG extensions";
+
+pub const KNOWN_CHUNKS: &[KnownChunk] = &[
+    known("IHDR", "Image header", SPEC),
+    known("PLTE", "Palette", SPEC),
+    known("IDAT", "Image data", SPEC),
+    known("IEND", "Image trailer", SPEC),
+    known("acTL", "Animation control", SPEC),
+    known("bKGD", "Background colour", SPEC),
+    known("cHRM", "Primary chromaticities and white point", SPEC),
+    known("cICP", "Coding-independent code points", SPEC),
+    known("cLLI", "Content light level information", SPEC),
+    known("eXIf", "Exchangeable image file (EXIF) profile", SPEC),
+    known("fcTL", "Animation frame control", SPEC),
+    known("fdAT", "Animation frame data", SPEC),
+    known("gAMA", "Image gamma", SPEC),
+    known("hIST", "Palette histogram", SPEC),
+    known("iCCP", "Embedded ICC profile", SPEC),
+    known("iTXt", "International textual data", SPEC),
+    known("mDCV", "Mastering display colour volume", SPEC),
+    known("pHYs", "Physical pixel dimensions", SPEC),
+    known("sBIT", "Significant bits", SPEC),
+    known("sPLT", "Suggested palette", SPEC),
+    known("sRGB", "Standard RGB colour space", SPEC),
+    known("tEXt", "Textual data", SPEC),
+    known("tIME", "Image last-modification time", SPEC),
+    known("tRNS", "Transparency", SPEC),
+    known("zTXt", "Compressed textual data", SPEC),
+    known("oFFs", "Image offset", EXTENSIONS),
+    known("pCAL", "Pixel calibration", EXTENSIONS),
+    known("sCAL", "Physical scale of image subject", EXTENSIONS),
+    known("gIFg", "GIF graphic control extension", EXTENSIONS),
+    known("gIFt", "GIF plain text extension (deprecated)", EXTENSIONS),
+    known("gIFx", "GIF application extension", EXTENSIONS),
+    known("sTER", "Stereo image indicator", EXTENSIONS),
+    known("dSIG", "Digital signature", EXTENSIONS),
+    known("fRAc", "Fractal image parameters", EXTENSIONS),
+    known("iDOT", "Apple multithreaded decoding offsets", "Apple"),
+    known("CgBI", "Apple iOS optimised PNG marker", "Apple"),
+    known("vpAg", "Virtual page size", "ImageMagick"),
+    known("caNv", "Virtual canvas size and offset", "ImageMagick"),
+    known("orNT", "Image orientation", "ImageMagick"),
+    known("npTc", "Nine-patch stretch regions", "Android"),
+    known("npLb", "Nine-patch layout bounds", "Android"),
+    known("npOl", "Nine-patch outline", "Android"),
+    known("mkBF", "Fireworks document data", "Macromedia Fireworks"),
+    known("mkBS", "Fireworks document data", "Macromedia Fireworks"),
+    known("mkBT", "Fireworks document data", "Macromedia Fireworks"),
+    known("mkTS", "Fireworks document data", "Macromedia Fireworks"),
+    known("prVW", "Fireworks preview image", "Macromedia Fireworks"),
+    known("siGn", "Ed25519 chunk signature", "pngme"),
+    known("fsIx", "Embedded file system index", "pngme"),
+    known("fsDt", "Embedded file system data", "pngme"),
+];
+
+pub fn lookup(chunk_type: &ChunkType) -> Option<&'static KnownChunk> {
+    let bytes = chunk_type.bytes();
+    KNOWN_CHUNKS
+        .iter()
+        .find(|known| known.chunk_type.as_bytes() == bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::STANDARD_TYPES;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_lookup() {
+        let idot = lookup(&ChunkType::from_str("iDOT").unwrap()).unwrap();
+        assert_eq!(idot.origin, "Apple");
+        assert_eq!(
+            lookup(&ChunkType::from_str("gAMA").unwrap())
+                .unwrap()
+                .description,
+            "Image gamma"
+        );
+        assert_eq!(lookup(&ChunkType::from_str("ruSt").unwrap()), None);
+    }
+
+    #[test]
+    fn test_covers_standard_types() {
+        for standard in STANDARD_TYPES {
+            let chunk_type = ChunkType::try_from(*standard).unwrap();
+            assert_eq!(lookup(&chunk_type).unwrap().origin, SPEC);
+        }
+    }
+
+    #[test]
+    fn test_entries_are_valid_and_unique() {
+        for (i, known) in KNOWN_CHUNKS.iter().enumerate() {
+            assert!(ChunkType::from_str(known.chunk_type).is_ok());
+            assert!(!KNOWN_CHUNKS[i + 1..]
+                .iter()
+                .any(|other| other.chunk_type == known.chunk_type));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::chunk::{Chunk, CRC_32};
+use crate::chunk_type::ChunkType;
+use crate::error::PngmeError;
+use crate::png::Png;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    Crc {
+        index: usize,
+        chunk_type: ChunkType,
+        found: u32,
+        expected: u32,
+    },
+    Length {
+        index: usize,
+        chunk_type: ChunkType,
+        declared: u32,
+        actual: u32,
+    },
+    DroppedTrailingData {
+        offset: u64,
+        length: usize,
+    },
+    AppendedIend,
+}
+
+impl std::fmt::Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Crc {
+                index,
+                chunk_type,
+                found,
+                expected,
+            } => write!(
+                f,
+                "chunk #{} ({}): rewrote crc {:#010x} as {:#010x}",
+                index, chunk_type, found, expected
+            ),
+            Self::Length {
+                index,
+                chunk_type,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "chunk #{} ({}): corrected length {} to {}",
+                index, chunk_type, declared, actual
+            ),
+            Self::DroppedTrailingData { offset, length } => write!(
+                f,
+                "dropped {} unreadable bytes at offset {:#X}",
+                length, offset
+            ),
+            Self::AppendedIend => write!(f, "appended a missing IEND chunk"),
+        }
+    }
+}
+
+// Rebuilds a PNG from damaged bytes. A chunk whose CRC does not match is first
+// checked for a wrong length field: if some other data length makes the CRC
+// that follows it match, the length was wrong. Otherwise the CRC is rewritten.
+// Bytes that cannot be read as a chunk at all are dropped.
+pub fn repair(bytes: &[u8]) -> Result<(Png, Vec<Fix>), PngmeError> {
+    if bytes.len() < 8 || bytes[..8] != Png::STANDARD_HEADER {
+        return Err(PngmeError::InvalidSignature);
+    }
+    let mut chunks = Vec::new();
+    let mut fixes = Vec::new();
+    let mut trailing_data = Vec::new();
+    let mut offset = 8;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        let Some((chunk, consumed, fix)) = repair_chunk(chunks.len(), rest) else {
+            fixes.push(Fix::DroppedTrailingData {
+                offset: offset as u64,
+                length: rest.len(),
+            });
+            break;
+        };
+        fixes.extend(fix);
+        offset += consumed;
+        let end = &chunk.chunk_type().bytes() == b"IEND";
+        chunks.push(chunk);
+        if end {
+            trailing_data = bytes[offset..].to_vec();
+            break;
+        }
+    }
+    if !chunks
+        .iter()
+        .any(|chunk| &chunk.chunk_type().bytes() == b"IEND")
+    {
+        chunks.push(Chunk::new(ChunkType::try_from(*b"IEND")?, Vec::new()));
+        fixes.push(Fix::AppendedIend);
+    }
+    Ok((
+        Png::from_chunks(chunks).with_trailing_data(trailing_data),
+        fixes,
+    ))
+}
+
+fn repair_chunk(index: usize, rest: &[u8]) -> Option<(Chunk, usize, Option<Fix>)> {
+    if rest.len() < 12 {
+        return None;
+    }
+    let declared = u32::from_be_bytes(rest[..4].try_into().unwrap());
+    let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&rest[4..8]).unwrap()).ok()?;
+    let crc_at =
+        |length: usize| u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+    let available = rest.len() - 12;
+    let declared_fits = declared as usize <= available;
+    if declared_fits {
+        let data = &rest[8..8 + declared as usize];
+        let chunk = Chunk::new(chunk_type, data.to_vec());
+        if chunk.crc() == crc_at(declared as usize) {
+            return Some((chunk, declared as usize + 12, None));
+        }
+    }
+
+    let mut digest = CRC_32.digest();
+    digest.update(&chunk_type.bytes());
+    for length in 0..=available {
+        if length as u32 != declared && digest.clone().finalize() == crc_at(length) {
+            let chunk = Chunk::new(chunk_type, rest[8..8 + length].to_vec());
+            let fix = Fix::Length {
+                index,
+                chunk_type,
+                declared,
+                actual: length as u32,
+            };
+            return Some((chunk, length + 12, Some(fix)));
+        }
+        if length < available {
+            digest.update(&rest[8 + length..9 + length]);
+        }
+    }
+
+    if !declared_fits {
+        return None;
+    }
+    let chunk = Chunk::new(chunk_type, rest[8..8 + declared as usize].to_vec());
+    let fix = Fix::Crc {
+        index,
+        chunk_type,
+        found: crc_at(declared as usize),
+        expected: chunk.crc(),
+    };
+    Some((chunk, declared as usize + 12, Some(fix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use std::str::FromStr;
+
+    fn original() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("IDAT", b"image data"),
+            chunk("IEND", b""),
+        ])
+    }
+
+    #[test]
+    fn test_intact_file_needs_no_fixes() {
+        let (png, fixes) = repair(&original().as_bytes()).unwrap();
+        assert_eq!(png, original());
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn test_rewrites_bad_crc() {
+        let mut bytes = original().as_bytes();
+        bytes[22] ^= 0xff;
+        let (png, fixes) = repair(&bytes).unwrap();
+        assert_eq!(png, original());
+        assert!(matches!(fixes[..], [Fix::Crc { index: 0, .. }]));
+        assert!(fixes[0]
+            .to_string()
+            .starts_with("chunk #0 (IHDR): rewrote crc"));
+    }
+
+    #[test]
+    fn test_corrects_length_field() {
+        let mut bytes = original().as_bytes();
+        bytes[29] = 4;
+        let (png, fixes) = repair(&bytes).unwrap();
+        assert_eq!(png, original());
+        assert_eq!(
+            fixes,
+            [Fix::Length {
+                index: 1,
+                chunk_type: ChunkType::from_str("IDAT").unwrap(),
+                declared: 4,
+                actual: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_appends_missing_iend_and_drops_junk() {
+        let mut bytes = original().as_bytes();
+        bytes.truncate(bytes.len() - 12);
+        bytes.extend_from_slice(b"junk");
+        let (png, fixes) = repair(&bytes).unwrap();
+        assert_eq!(png, original());
+        assert!(matches!(
+            fixes[..],
+            [
+                Fix::DroppedTrailingData { length: 4, .. },
+                Fix::AppendedIend
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_needs_a_signature() {
+        assert!(repair(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_keeps_data_after_iend() {
+        let mut bytes = original().as_bytes();
+        bytes.extend_from_slice(b"appended");
+        let (png, fixes) = repair(&bytes).unwrap();
+        assert!(fixes.is_empty());
+        assert_eq!(png.trailing_data(), b"appended");
+        assert_eq!(png.as_bytes(), bytes);
+    }
+}
@@ -0,0 +1,199 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+
+use crate::chunk::Chunk;
+use crate::describe::{self, Context};
+use crate::error::{error_code, PngmeError};
+use crate::registry;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChunkReport {
+    pub index: usize,
+    pub offset: Option<u64>,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub length: u32,
+    pub crc: u32,
+    pub critical: bool,
+    pub public: bool,
+    pub safe_to_copy: bool,
+    pub entropy: f64,
+    pub known_as: Option<&'static str>,
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl ChunkReport {
+    pub fn new(index: usize, chunk: &Chunk, context: &Context, include_data: bool) -> Self {
+        let chunk_type = chunk.chunk_type();
+        Self {
+            index,
+            offset: chunk.offset(),
+            chunk_type: chunk_type.to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+            critical: chunk_type.is_critical(),
+            public: chunk_type.is_public(),
+            safe_to_copy: chunk_type.is_safe_to_copy(),
+            entropy: chunk.entropy(),
+            known_as: registry::lookup(chunk_type).map(|known| known.description),
+            details: describe::describe(chunk, context),
+            data: include_data.then(|| BASE64.encode(chunk.data())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedPayload {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub index: Option<usize>,
+    pub data: String,
+    pub text: Option<String>,
+}
+
+impl DecodedPayload {
+    pub fn new(chunk_type: impl Into<String>, index: Option<usize>, data: &[u8]) -> Self {
+        Self {
+            chunk_type: chunk_type.into(),
+            index,
+            data: BASE64.encode(data),
+            text: String::from_utf8(data.to_vec()).ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    pub path: Option<String>,
+    pub chunk: Option<usize>,
+    pub offset: Option<u64>,
+}
+
+impl ErrorReport {
+    pub fn new(error: &(dyn std::error::Error + 'static)) -> Self {
+        let (path, error) = match error.downcast_ref::<PngmeError>() {
+            Some(PngmeError::File { path, source }) => (
+                Some(path.display().to_string()),
+                source.as_ref() as &(dyn std::error::Error + 'static),
+            ),
+            _ => (None, error),
+        };
+        let location = error
+            .downcast_ref::<PngmeError>()
+            .and_then(PngmeError::location);
+        Self {
+            code: error_code(error),
+            message: error.to_string(),
+            path,
+            chunk: location.map(|(index, _)| index),
+            offset: location.map(|(_, offset)| offset),
+        }
+    }
+
+    // Scripts branch on these, so they must not change between releases:
+    // 0 success, 1 other failure, 2 usage, 3 I/O, 4 parse error,
+    // 5 CRC mismatch, 6 chunk not found, 7 lint or policy failure.
+    pub fn exit_code(&self) -> u8 {
+        match self.code {
+            "usage" => 2,
+            "io" => 3,
+            "invalid_signature" | "invalid_chunk_type" | "truncated" | "malformed"
+            | "invalid_png" | "spec_violation" | "utf8" | "json" => 4,
+            "crc_mismatch" => 5,
+            "chunk_not_found" => 6,
+            "lint_denied" | "policy_failed" | "hidden_chunks" | "suspicious_files" => 7,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+
+    #[test]
+    fn test_chunk_report_json() {
+        let report = ChunkReport::new(3, &chunk("sRGB", &[0]), &Context::default(), true);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["index"], 3);
+        assert_eq!(json["type"], "sRGB");
+        assert_eq!(json["offset"], serde_json::Value::Null);
+        assert_eq!(json["critical"], false);
+        assert_eq!(json["known_as"], "Standard RGB colour space");
+        assert_eq!(json["details"], "sRGB, perceptual rendering intent");
+        assert_eq!(json["data"], "AA==");
+    }
+
+    #[test]
+    fn test_chunk_report_without_data() {
+        let report = ChunkReport::new(0, &chunk("ruSt", b"hi"), &Context::default(), false);
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("data").is_none());
+        assert_eq!(json["known_as"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_decoded_payload() {
+        let text = DecodedPayload::new("ruSt", None, b"hello");
+        assert_eq!(text.data, "aGVsbG8=");
+        assert_eq!(text.text.as_deref(), Some("hello"));
+        assert_eq!(DecodedPayload::new("ruSt", Some(2), &[0xff]).text, None);
+    }
+
+    #[test]
+    fn test_error_report() {
+        let crc = PngmeError::CrcMismatch {
+            expected: 1,
+            found: 2,
+        };
+        let error = PngmeError::in_file("a.png", PngmeError::in_chunk(1, 33, crc));
+        let report = ErrorReport::new(&error);
+        assert_eq!(report.code, "crc_mismatch");
+        assert_eq!(
+            report.message,
+            "chunk #1 at offset 0x21: crc mismatch: expected 0x00000001, found 0x00000002"
+        );
+        assert_eq!(report.path.as_deref(), Some("a.png"));
+        assert_eq!(report.chunk, Some(1));
+        assert_eq!(report.offset, Some(33));
+
+        let error: Box<dyn std::error::Error + Send + Sync> = "no luck".into();
+        let report = ErrorReport::new(error.as_ref());
+        assert_eq!(report.code, "other");
+        assert_eq!(report.path, None);
+        assert_eq!(report.offset, None);
+    }
+
+    #[test]
+    fn test_error_report_exit_codes() {
+        let exit_code = |error: PngmeError| ErrorReport::new(&error).exit_code();
+        assert_eq!(
+            exit_code(PngmeError::CrcMismatch {
+                expected: 1,
+                found: 2
+            }),
+            5
+        );
+        assert_eq!(exit_code(PngmeError::ChunkNotFound("ruSt".into())), 6);
+        assert_eq!(exit_code(PngmeError::Usage("no".into())), 2);
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(exit_code(io.into()), 3);
+        assert_eq!(exit_code(PngmeError::InvalidSignature), 4);
+        assert_eq!(exit_code(PngmeError::Truncated("short".into())), 4);
+        assert_eq!(exit_code(PngmeError::LintDenied(2)), 7);
+        assert_eq!(
+            exit_code(PngmeError::PolicyFailed {
+                failed: 1,
+                total: 2
+            }),
+            7
+        );
+        assert_eq!(exit_code(PngmeError::Crypto("bad".into())), 1);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+
+use crate::info::is_pngme_chunk;
+use crate::png::Png;
+use crate::registry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Suspicion {
+    NonStandardChunk,
+    TrailingData,
+    OversizedChunk,
+    PngmeEnvelope,
+}
+
+impl std::fmt::Display for Suspicion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NonStandardChunk => "non-standard chunk",
+            Self::TrailingData => "data after IEND",
+            Self::OversizedChunk => "oversized chunk",
+            Self::PngmeEnvelope => "pngme payload",
+        })
+    }
+}
+
+// One place in a file where data could be hiding. `index` and `chunk_type`
+// are unset for data after IEND, which belongs to no chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub suspicion: Suspicion,
+    pub index: Option<usize>,
+    #[serde(rename = "type")]
+    pub chunk_type: Option<String>,
+    pub bytes: usize,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let (Some(index), Some(chunk_type)) = (self.index, &self.chunk_type) {
+            write!(f, "chunk #{} {}: ", index, chunk_type)?;
+        }
+        write!(f, "{} ({} bytes)", self.suspicion, self.bytes)
+    }
+}
+
+// Everything in `png` that could carry data a reviewer has not seen: chunk
+// types no specification defines, bytes after IEND, ancillary chunks larger
+// than `max_ancillary`, and chunks written by pngme itself. A chunk can be
+// reported for more than one reason.
+pub fn scan(png: &Png, max_ancillary: u32) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        let mut add = |suspicion| {
+            findings.push(Finding {
+                suspicion,
+                index: Some(index),
+                chunk_type: Some(chunk_type.to_string()),
+                bytes: chunk.length() as usize,
+            })
+        };
+        if is_pngme_chunk(chunk) {
+            add(Suspicion::PngmeEnvelope);
+        } else if registry::lookup(chunk_type).is_none() {
+            add(Suspicion::NonStandardChunk);
+        }
+        if !chunk_type.is_critical() && chunk.length() > max_ancillary {
+            add(Suspicion::OversizedChunk);
+        }
+    }
+    if !png.trailing_data().is_empty() {
+        findings.push(Finding {
+            suspicion: Suspicion::TrailingData,
+            index: None,
+            chunk_type: None,
+            bytes: png.trailing_data().len(),
+        });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use crate::envelope;
+
+    fn suspicions(findings: &[Finding]) -> Vec<(Option<usize>, Suspicion)> {
+        findings
+            .iter()
+            .map(|finding| (finding.index, finding.suspicion))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_file_has_no_findings() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Title\0x"),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ]);
+        assert!(scan(&png, 16).is_empty());
+    }
+
+    #[test]
+    fn test_scan_findings() {
+        let mut payload = envelope::MAGIC.to_vec();
+        payload.extend(b"secret");
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("ruSt", b"hidden"),
+            chunk("zTXt", &payload),
+            chunk("iTXt", &[0; 32]),
+            chunk("IDAT", &[0; 32]),
+            chunk("IEND", b""),
+        ])
+        .with_trailing_data(b"tail".to_vec());
+        let findings = scan(&png, 16);
+        assert_eq!(
+            suspicions(&findings),
+            [
+                (Some(1), Suspicion::NonStandardChunk),
+                (Some(2), Suspicion::PngmeEnvelope),
+                (Some(3), Suspicion::OversizedChunk),
+                (None, Suspicion::TrailingData),
+            ]
+        );
+        assert_eq!(
+            findings[0].to_string(),
+            "chunk #1 ruSt: non-standard chunk (6 bytes)"
+        );
+        assert_eq!(findings[3].to_string(), "data after IEND (4 bytes)");
+    }
+}
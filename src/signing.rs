@@ -0,0 +1,276 @@
+use aes_gcm::aead::OsRng;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, Verifier, SIGNATURE_LENGTH};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::crypto::decode_key;
+use crate::error::PngmeError;
+use crate::png::Png;
+
+pub const SIGNATURE_CHUNK_TYPE: &str = "siGn";
+
+#[derive(Clone)]
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        Self(ed25519_dalek::SigningKey::generate(&mut OsRng))
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.verifying_key())
+    }
+
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0.to_bytes())
+    }
+
+    pub fn sign_bytes(&self, data: &[u8]) -> [u8; SIGNATURE_LENGTH] {
+        self.0.sign(data).to_bytes()
+    }
+
+    pub fn sign_chunk(&self, chunk: &Chunk) -> Chunk {
+        let signature = self.0.sign(&signed_bytes(chunk));
+        let data = chunk
+            .chunk_type()
+            .bytes()
+            .iter()
+            .chain(signature.to_bytes().iter())
+            .copied()
+            .collect();
+        Chunk::new(SIGNATURE_CHUNK_TYPE.parse().unwrap(), data)
+    }
+}
+
+impl std::str::FromStr for SigningKey {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(Self(ed25519_dalek::SigningKey::from_bytes(&decode_key(
+            string,
+        )?)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyingKey(ed25519_dalek::VerifyingKey);
+
+impl VerifyingKey {
+    pub fn verify_chunk(&self, chunk: &Chunk, signature_chunk: &Chunk) -> Result<(), PngmeError> {
+        let (target, signature) = parse_signature_chunk(signature_chunk)?;
+        if &target != chunk.chunk_type() {
+            return Err(PngmeError::Crypto(format!(
+                "signature is for a {} chunk, not {}",
+                target,
+                chunk.chunk_type()
+            )));
+        }
+        self.0
+            .verify(&signed_bytes(chunk), &signature)
+            .map_err(|_| PngmeError::Crypto("signature does not match chunk data".into()))
+    }
+
+    pub fn verify_bytes(&self, data: &[u8], signature: &[u8]) -> Result<(), PngmeError> {
+        let signature = Signature::from_slice(signature)
+            .map_err(|_| PngmeError::Crypto("malformed ed25519 signature".into()))?;
+        self.0
+            .verify(data, &signature)
+            .map_err(|_| PngmeError::Crypto("signature does not match the signed data".into()))
+    }
+
+    pub fn verify_in_png(&self, png: &Png, chunk_type: &str) -> Result<(), PngmeError> {
+        let signatures: Vec<&Chunk> = png.chunks_by_type(SIGNATURE_CHUNK_TYPE).collect();
+        if signatures.is_empty() {
+            return Err(PngmeError::Crypto("no signature chunks found".into()));
+        }
+        let mut verified = 0;
+        for chunk in png.chunks_by_type(chunk_type) {
+            signatures
+                .iter()
+                .find(|signature| self.verify_chunk(chunk, signature).is_ok())
+                .ok_or_else(|| {
+                    PngmeError::Crypto(format!(
+                        "no valid signature for {} #{}",
+                        chunk_type, verified
+                    ))
+                })?;
+            verified += 1;
+        }
+        if verified == 0 {
+            return Err(PngmeError::Crypto(format!(
+                "no chunk of type {}",
+                chunk_type
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for VerifyingKey {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        ed25519_dalek::VerifyingKey::from_bytes(&decode_key(string)?)
+            .map(Self)
+            .map_err(|_| PngmeError::Crypto("invalid ed25519 public key".into()))
+    }
+}
+
+impl std::fmt::Display for VerifyingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", BASE64.encode(self.0.as_bytes()))
+    }
+}
+
+// Removes the siGn chunks that sign chunks of `chunk_type`, for when every
+// chunk of that type is being replaced.
+pub fn remove_signatures_for(png: &mut Png, chunk_type: &ChunkType) -> Vec<Chunk> {
+    png.remove_chunks_where(|chunk| signed_type(chunk).as_ref() == Some(chunk_type))
+}
+
+// Removes the siGn chunks for chunk types the file no longer has, since they
+// can only have signed chunks that are gone. Without the key there is no
+// telling which chunk of a type that is still present a signature belongs to,
+// so those stay.
+pub fn remove_orphaned_signatures(png: &mut Png) -> Vec<Chunk> {
+    let orphaned: Vec<bool> = png
+        .chunks()
+        .iter()
+        .map(|chunk| {
+            signed_type(chunk).is_some_and(|target| {
+                !png.chunks()
+                    .iter()
+                    .any(|other| other.chunk_type() == &target)
+            })
+        })
+        .collect();
+    let mut orphaned = orphaned.into_iter();
+    png.remove_chunks_where(|_| orphaned.next().unwrap_or(false))
+}
+
+fn signed_type(chunk: &Chunk) -> Option<ChunkType> {
+    if chunk.chunk_type().to_string() != SIGNATURE_CHUNK_TYPE {
+        return None;
+    }
+    parse_signature_chunk(chunk).ok().map(|(target, _)| target)
+}
+
+fn signed_bytes(chunk: &Chunk) -> Vec<u8> {
+    chunk
+        .chunk_type()
+        .bytes()
+        .iter()
+        .chain(chunk.data().iter())
+        .copied()
+        .collect()
+}
+
+fn parse_signature_chunk(chunk: &Chunk) -> Result<(ChunkType, Signature), PngmeError> {
+    let data = chunk.data();
+    if data.len() != 4 + SIGNATURE_LENGTH {
+        return Err(PngmeError::Crypto(format!(
+            "signature chunk should be {} bytes, got {}",
+            4 + SIGNATURE_LENGTH,
+            data.len()
+        )));
+    }
+    let target = ChunkType::try_from(<[u8; 4]>::try_from(&data[..4]).unwrap())
+        .map_err(|e| PngmeError::Crypto(e.to_string()))?;
+    let signature = Signature::from_bytes(data[4..].try_into().unwrap());
+    Ok((target, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_chunk() -> Chunk {
+        Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"signed message".to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::generate();
+        let chunk = testing_chunk();
+        let signature = key.sign_chunk(&chunk);
+        assert_eq!(&signature.chunk_type().to_string(), SIGNATURE_CHUNK_TYPE);
+        assert!(key.verifying_key().verify_chunk(&chunk, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_chunk_fails() {
+        let key = SigningKey::generate();
+        let signature = key.sign_chunk(&testing_chunk());
+        let tampered = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"tampered".to_vec());
+        assert!(key
+            .verifying_key()
+            .verify_chunk(&tampered, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let chunk = testing_chunk();
+        let signature = SigningKey::generate().sign_chunk(&chunk);
+        let other = SigningKey::generate().verifying_key();
+        assert!(other.verify_chunk(&chunk, &signature).is_err());
+    }
+
+    #[test]
+    fn test_verify_in_png() {
+        let key = SigningKey::generate();
+        let chunk = testing_chunk();
+        let signature = key.sign_chunk(&chunk);
+        let png = Png::from_chunks(vec![chunk, signature]);
+        assert!(key.verifying_key().verify_in_png(&png, "ruSt").is_ok());
+        assert!(key.verifying_key().verify_in_png(&png, "teSt").is_err());
+    }
+
+    #[test]
+    fn test_verify_in_png_checks_every_chunk() {
+        let key = SigningKey::generate();
+        let chunk = testing_chunk();
+        let signature = key.sign_chunk(&chunk);
+        let unsigned = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"extra".to_vec());
+        let png = Png::from_chunks(vec![chunk, signature, unsigned]);
+        assert!(key.verifying_key().verify_in_png(&png, "ruSt").is_err());
+    }
+
+    #[test]
+    fn test_remove_signatures() {
+        let key = SigningKey::generate();
+        let chunk = testing_chunk();
+        let other = Chunk::new(ChunkType::from_str("teSt").unwrap(), b"other".to_vec());
+        let signatures = [key.sign_chunk(&chunk), key.sign_chunk(&other)];
+        let mut png = Png::from_chunks(vec![
+            chunk.clone(),
+            signatures[0].clone(),
+            signatures[1].clone(),
+        ]);
+        assert_eq!(
+            remove_orphaned_signatures(&mut png),
+            [signatures[1].clone()]
+        );
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(
+            remove_signatures_for(&mut png, &chunk_type),
+            [signatures[0].clone()]
+        );
+        assert_eq!(png.chunks(), [chunk]);
+    }
+
+    #[test]
+    fn test_key_string_round_trip() {
+        let key = SigningKey::generate();
+        let parsed = SigningKey::from_str(&key.to_base64()).unwrap();
+        assert_eq!(parsed.verifying_key(), key.verifying_key());
+        let public = VerifyingKey::from_str(&key.verifying_key().to_string()).unwrap();
+        assert_eq!(public, key.verifying_key());
+    }
+}
@@ -0,0 +1,119 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Pdf,
+    Zip,
+    Gzip,
+    Zstd,
+    Tar,
+    Json,
+    Text,
+}
+
+impl FileType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Png => "PNG image",
+            Self::Jpeg => "JPEG image",
+            Self::Gif => "GIF image",
+            Self::Webp => "WebP image",
+            Self::Pdf => "PDF document",
+            Self::Zip => "ZIP archive",
+            Self::Gzip => "gzip data",
+            Self::Zstd => "zstd data",
+            Self::Tar => "tar archive",
+            Self::Json => "JSON text",
+            Self::Text => "UTF-8 text",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+            Self::Pdf => "pdf",
+            Self::Zip => "zip",
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+            Self::Tar => "tar",
+            Self::Json => "json",
+            Self::Text => "txt",
+        }
+    }
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub fn sniff(data: &[u8]) -> Option<FileType> {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => Some(FileType::Png),
+        [0xff, 0xd8, 0xff, ..] => Some(FileType::Jpeg),
+        [b'G', b'I', b'F', b'8', ..] => Some(FileType::Gif),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(FileType::Webp),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some(FileType::Pdf),
+        [b'P', b'K', 3 | 5 | 7, 4 | 6 | 8, ..] => Some(FileType::Zip),
+        [0x1f, 0x8b, ..] => Some(FileType::Gzip),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(FileType::Zstd),
+        _ if data.get(257..262) == Some(b"ustar") => Some(FileType::Tar),
+        _ => sniff_text(data),
+    }
+}
+
+fn sniff_text(data: &[u8]) -> Option<FileType> {
+    let text = std::str::from_utf8(data).ok()?;
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        let trimmed = trimmed.trim_end();
+        if trimmed.ends_with('}') || trimmed.ends_with(']') {
+            return Some(FileType::Json);
+        }
+    }
+    Some(FileType::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_magic() {
+        assert_eq!(sniff(&crate::Png::STANDARD_HEADER), Some(FileType::Png));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some(FileType::Pdf));
+        assert_eq!(sniff(b"PK\x03\x04rest"), Some(FileType::Zip));
+        assert_eq!(sniff(b"GIF89a"), Some(FileType::Gif));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(FileType::Webp));
+    }
+
+    #[test]
+    fn test_sniff_tar() {
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar), Some(FileType::Tar));
+    }
+
+    #[test]
+    fn test_sniff_text() {
+        assert_eq!(sniff(b"hello world\n"), Some(FileType::Text));
+        assert_eq!(sniff(b" {\"a\": 1}\n"), Some(FileType::Json));
+        assert_eq!(sniff(b"\x00\x01\x02binary"), None);
+        assert_eq!(sniff(&[0xc3, 0x28]), None);
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(FileType::Jpeg.extension(), "jpg");
+        assert_eq!(FileType::Text.to_string(), "UTF-8 text");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::error::{PngmeError, Result};
+
+pub const MAGIC: [u8; 3] = *b"PMS";
+pub const HEADER_LEN: usize = MAGIC.len() + 8;
+pub const MAX_CHUNK_LEN: usize = (1 << 31) - 1;
+pub const DEFAULT_MAX_CHUNK_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceHeader {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl SequenceHeader {
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..3].copy_from_slice(&MAGIC);
+        bytes[3..7].copy_from_slice(&self.index.to_be_bytes());
+        bytes[7..11].copy_from_slice(&self.count.to_be_bytes());
+        bytes
+    }
+
+    pub fn parse(data: &[u8]) -> Option<(Self, &[u8])> {
+        if data.len() < HEADER_LEN || data[..3] != MAGIC {
+            return None;
+        }
+        let index = u32::from_be_bytes(data[3..7].try_into().unwrap());
+        let count = u32::from_be_bytes(data[7..11].try_into().unwrap());
+        if count == 0 || index >= count {
+            return None;
+        }
+        Some((Self { index, count }, &data[HEADER_LEN..]))
+    }
+}
+
+pub fn split(data: &[u8], max_chunk_len: usize) -> Result<Vec<Vec<u8>>> {
+    if data.len() <= max_chunk_len {
+        return Ok(vec![data.to_vec()]);
+    }
+    if max_chunk_len <= HEADER_LEN || max_chunk_len > MAX_CHUNK_LEN {
+        return Err(PngmeError::Usage(format!(
+            "max chunk length must be between {} and {}, got {}",
+            HEADER_LEN + 1,
+            MAX_CHUNK_LEN,
+            max_chunk_len
+        )));
+    }
+    let parts: Vec<&[u8]> = data.chunks(max_chunk_len - HEADER_LEN).collect();
+    let count = u32::try_from(parts.len())
+        .map_err(|_| PngmeError::Usage("payload needs too many chunks".to_string()))?;
+    Ok(parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let header = SequenceHeader {
+                index: index as u32,
+                count,
+            };
+            header.to_bytes().iter().chain(part).copied().collect()
+        })
+        .collect())
+}
+
+pub fn join<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> Result<Option<Vec<u8>>> {
+    let mut sequence: Vec<(SequenceHeader, &[u8])> = Vec::new();
+    for part in parts {
+        match SequenceHeader::parse(part) {
+            Some(parsed) => sequence.push(parsed),
+            None if sequence.is_empty() => return Ok(None),
+            None => continue,
+        }
+    }
+    let count = match sequence.first() {
+        Some((header, _)) => header.count,
+        None => return Ok(None),
+    };
+    if let Some((header, _)) = sequence.iter().find(|(header, _)| header.count != count) {
+        return Err(PngmeError::Malformed(format!(
+            "split parts disagree on the number of parts: {} and {}",
+            count, header.count
+        )));
+    }
+    sequence.sort_by_key(|(header, _)| header.index);
+    // A part copied twice is harmless, but two different parts under one index
+    // leave no way to tell which belongs to the payload.
+    if let Some(pair) = sequence
+        .windows(2)
+        .find(|pair| pair[0].0.index == pair[1].0.index && pair[0].1 != pair[1].1)
+    {
+        return Err(PngmeError::Malformed(format!(
+            "split part {} appears twice with different data",
+            pair[0].0.index
+        )));
+    }
+    sequence.dedup_by_key(|(header, _)| header.index);
+    let complete = sequence.len() == count as usize
+        && sequence
+            .iter()
+            .enumerate()
+            .all(|(i, (header, _))| header.index == i as u32);
+    if !complete {
+        return Err(PngmeError::Malformed(format!(
+            "split payload is incomplete: found {} of {} parts",
+            sequence.len(),
+            count
+        )));
+    }
+    Ok(Some(
+        sequence
+            .into_iter()
+            .flat_map(|(_, part)| part.to_vec())
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_payload_is_not_split() {
+        let parts = split(b"small", 100).unwrap();
+        assert_eq!(parts, vec![b"small".to_vec()]);
+        assert_eq!(join(parts.iter().map(Vec::as_slice)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let parts = split(&data, 100).unwrap();
+        assert_eq!(parts.len(), 12);
+        assert!(parts.iter().all(|part| part.len() <= 100));
+        assert_eq!(
+            join(parts.iter().map(Vec::as_slice)).unwrap().unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_join_out_of_order() {
+        let data: Vec<u8> = (0..50).collect();
+        let mut parts = split(&data, 20).unwrap();
+        parts.reverse();
+        assert_eq!(
+            join(parts.iter().map(Vec::as_slice)).unwrap().unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_join_missing_part() {
+        let data: Vec<u8> = (0..50).collect();
+        let mut parts = split(&data, 20).unwrap();
+        parts.remove(1);
+        assert!(join(parts.iter().map(Vec::as_slice)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_max_len() {
+        assert!(split(&[0u8; 100], HEADER_LEN).is_err());
+    }
+
+    #[test]
+    fn test_join_inconsistent_parts() {
+        let data: Vec<u8> = (0..50).collect();
+        let parts = split(&data, 20).unwrap();
+
+        let mut duplicated = parts.clone();
+        duplicated.push(parts[1].clone());
+        assert_eq!(
+            join(duplicated.iter().map(Vec::as_slice)).unwrap().unwrap(),
+            data
+        );
+
+        let mut conflicting = parts.clone();
+        let mut other = parts[1].clone();
+        *other.last_mut().unwrap() ^= 1;
+        conflicting.push(other);
+        assert!(matches!(
+            join(conflicting.iter().map(Vec::as_slice)),
+            Err(PngmeError::Malformed(_))
+        ));
+
+        let mut recounted = parts.clone();
+        let other = split(&[0u8; 100], 20).unwrap();
+        recounted[2] = other[2].clone();
+        assert!(matches!(
+            join(recounted.iter().map(Vec::as_slice)),
+            Err(PngmeError::Malformed(_))
+        ));
+    }
+}
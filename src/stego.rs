@@ -0,0 +1,440 @@
+use serde::Serialize;
+
+use flate2::{Decompress, FlushDecompress, Status};
+
+use crate::error::{PngmeError, Result};
+use crate::ihdr::{ColourType, ImageHeader};
+use crate::pixels::{self, Pixels, IDAT_CHUNK_TYPE};
+use crate::png::Png;
+
+// Pixel payloads start with their length as a big-endian u32, since nothing
+// else marks where the hidden bits end.
+const LENGTH_LEN: usize = 4;
+// The most a deflate stored block can hold.
+const STORED_BLOCK_LEN: usize = 0xFFFF;
+
+// Where `encode` hides a payload and `decode` looks for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Chunk,
+    Lsb,
+    Alpha,
+    Stored,
+}
+
+impl std::str::FromStr for Method {
+    type Err = PngmeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "chunk" => Ok(Self::Chunk),
+            "lsb" => Ok(Self::Lsb),
+            "alpha" => Ok(Self::Alpha),
+            "stored" => Ok(Self::Stored),
+            _ => Err(PngmeError::Usage(format!(
+                "unknown method {:?}, expected chunk, lsb, alpha or stored",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Chunk => "chunk",
+            Self::Lsb => "lsb",
+            Self::Alpha => "alpha",
+            Self::Stored => "stored",
+        })
+    }
+}
+
+// Indices into the pixel data of the bytes whose lowest bit carries payload:
+// the low byte of every colour sample, skipping alpha. Packed and palette
+// samples are left alone, as flipping their low bit changes the colour
+// visibly.
+fn lsb_carriers(header: &ImageHeader) -> Result<Vec<usize>> {
+    if header.colour_type == ColourType::Indexed || header.bit_depth < 8 {
+        return Err(PngmeError::Usage(format!(
+            "LSB hiding needs 8 or 16-bit greyscale or truecolour samples, not {}-bit {}",
+            header.bit_depth, header.colour_type
+        )));
+    }
+    let channels = header.colour_type.channels();
+    let colour_channels = match header.colour_type {
+        ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha => channels - 1,
+        _ => channels,
+    };
+    let sample_len = usize::from(header.bit_depth / 8);
+    let pixels = header.width as usize * header.height as usize;
+    Ok((0..pixels)
+        .flat_map(|pixel| {
+            (0..colour_channels)
+                .map(move |channel| (pixel * channels + channel) * sample_len + sample_len - 1)
+        })
+        .collect())
+}
+
+// Indices of the low alpha byte of every opaque pixel. Embedding can leave
+// an opaque pixel one step short of opaque, so those count too, or the
+// payload could not be found again.
+fn alpha_carriers(pixels: &Pixels) -> Result<Vec<usize>> {
+    let header = pixels.header;
+    if !matches!(
+        header.colour_type,
+        ColourType::GreyscaleAlpha | ColourType::TruecolourAlpha
+    ) {
+        return Err(PngmeError::Usage(format!(
+            "alpha hiding needs an image with an alpha channel, not {}",
+            header.colour_type
+        )));
+    }
+    let sample_len = usize::from(header.bit_depth / 8);
+    let pixel_len = header.colour_type.channels() * sample_len;
+    Ok(pixels
+        .data
+        .chunks_exact(pixel_len)
+        .enumerate()
+        .filter(|(_, pixel)| {
+            let alpha = &pixel[pixel_len - sample_len..];
+            alpha[..sample_len - 1].iter().all(|&byte| byte == 0xFF)
+                && alpha[sample_len - 1] >= 0xFE
+        })
+        .map(|(index, _)| (index + 1) * pixel_len - 1)
+        .collect())
+}
+
+fn carriers(pixels: &Pixels, method: Method) -> Result<Vec<usize>> {
+    match method {
+        Method::Lsb => lsb_carriers(&pixels.header),
+        Method::Alpha => alpha_carriers(pixels),
+        Method::Chunk | Method::Stored => Err(PngmeError::Usage(format!(
+            "{} hiding does not use pixels",
+            method
+        ))),
+    }
+}
+
+// How many payload bytes a pixel method fits in this image.
+pub fn capacity(pixels: &Pixels, method: Method) -> Result<usize> {
+    Ok((carriers(pixels, method)?.len() / 8).saturating_sub(LENGTH_LEN))
+}
+
+// Room for a payload with one method. `bytes` is unset for chunks, which
+// have no fixed limit, and when the method does not work for the image, in
+// which case `unavailable` says why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Capacity {
+    pub method: Method,
+    pub bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unavailable: Option<String>,
+}
+
+pub fn capacities(png: &Png) -> Vec<Capacity> {
+    let pixels = png.decode_pixels();
+    let mut capacities: Vec<Capacity> = [Method::Chunk, Method::Stored]
+        .into_iter()
+        .map(|method| Capacity {
+            method,
+            bytes: None,
+            unavailable: None,
+        })
+        .collect();
+    for method in [Method::Lsb, Method::Alpha] {
+        let capacity = match &pixels {
+            Ok(pixels) => capacity(pixels, method).map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        capacities.push(match capacity {
+            Ok(bytes) => Capacity {
+                method,
+                bytes: Some(bytes),
+                unavailable: None,
+            },
+            Err(error) => Capacity {
+                method,
+                bytes: None,
+                unavailable: Some(error),
+            },
+        });
+    }
+    capacities
+}
+
+// Writes the length-prefixed payload into the low bits of the carrier bytes,
+// most significant bit first.
+pub fn embed(pixels: &mut Pixels, method: Method, payload: &[u8]) -> Result<()> {
+    let carriers = carriers(pixels, method)?;
+    let capacity = (carriers.len() / 8).saturating_sub(LENGTH_LEN);
+    if payload.len() > capacity {
+        return Err(PngmeError::Usage(format!(
+            "payload is {} bytes but {} hiding only has room for {} in this image",
+            payload.len(),
+            method,
+            capacity
+        )));
+    }
+    let length = u32::try_from(payload.len())
+        .map_err(|_| PngmeError::Usage("payload is larger than 4 GiB".to_string()))?;
+    let framed = length
+        .to_be_bytes()
+        .into_iter()
+        .chain(payload.iter().copied());
+    let bits = framed.flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1));
+    for (&carrier, bit) in carriers.iter().zip(bits) {
+        pixels.data[carrier] = (pixels.data[carrier] & !1) | bit;
+    }
+    Ok(())
+}
+
+pub fn extract(pixels: &Pixels, method: Method) -> Result<Vec<u8>> {
+    let carriers = carriers(pixels, method)?;
+    let mut bytes = carriers.chunks_exact(8).map(|byte| {
+        byte.iter().fold(0u8, |value, &carrier| {
+            (value << 1) | (pixels.data[carrier] & 1)
+        })
+    });
+    let length = u32::from_be_bytes([(); LENGTH_LEN].map(|_| bytes.next().unwrap_or(0))) as usize;
+    let capacity = (carriers.len() / 8).saturating_sub(LENGTH_LEN);
+    if length > capacity {
+        return Err(PngmeError::ChunkNotFound(format!(
+            "no hidden payload: its length would be {} bytes but the image only holds {}",
+            length, capacity
+        )));
+    }
+    Ok(bytes.take(length).collect())
+}
+
+// The length of the zlib stream at the start of `data`; decoders stop
+// reading IDAT data there.
+fn zlib_stream_len(data: &[u8]) -> Result<usize> {
+    let mut decompress = Decompress::new(true);
+    let mut output = vec![0; 1 << 16];
+    loop {
+        let (read, written) = (decompress.total_in(), decompress.total_out());
+        let status = decompress
+            .decompress(&data[read as usize..], &mut output, FlushDecompress::None)
+            .map_err(|e| PngmeError::Malformed(e.to_string()))?;
+        if status == Status::StreamEnd {
+            return Ok(decompress.total_in() as usize);
+        }
+        if decompress.total_in() == read && decompress.total_out() == written {
+            return Err(PngmeError::Truncated("IDAT stream ends early".to_string()));
+        }
+    }
+}
+
+fn idat_stream(png: &Png) -> Vec<u8> {
+    png.chunks_by_type(IDAT_CHUNK_TYPE)
+        .flat_map(|chunk| chunk.data().iter().copied())
+        .collect()
+}
+
+// Appends the payload to the IDAT data as deflate stored blocks after the end
+// of the zlib stream, replacing any payload hidden there before. Decoders
+// stop at the zlib checksum, so the pixels and their compression are
+// untouched; strict decoders may warn about the extra data.
+pub fn embed_stored(png: &mut Png, payload: &[u8]) -> Result<()> {
+    let mut stream = idat_stream(png);
+    stream.truncate(zlib_stream_len(&stream)?);
+    let mut blocks = payload.chunks(STORED_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        stream.push(u8::from(blocks.peek().is_none()));
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend(block);
+    }
+    pixels::replace_idat(png, &stream)
+}
+
+pub fn extract_stored(png: &Png) -> Result<Vec<u8>> {
+    let stream = idat_stream(png);
+    let mut rest = &stream[zlib_stream_len(&stream)?..];
+    if rest.is_empty() {
+        return Err(PngmeError::ChunkNotFound(
+            "no hidden payload after the IDAT stream".to_string(),
+        ));
+    }
+    let mut payload = Vec::new();
+    loop {
+        let Some((&[header, a, b, c, d], data)) = rest.split_first_chunk::<5>() else {
+            return Err(PngmeError::Truncated("stored block header".to_string()));
+        };
+        let len = u16::from_le_bytes([a, b]);
+        if header > 1 || len != !u16::from_le_bytes([c, d]) {
+            return Err(PngmeError::Malformed(
+                "data after the IDAT stream is not a stored block".to_string(),
+            ));
+        }
+        let (block, next) = data
+            .split_at_checked(usize::from(len))
+            .ok_or_else(|| PngmeError::Truncated(format!("stored block of {} bytes", len)))?;
+        payload.extend(block);
+        if header == 1 {
+            return Ok(payload);
+        }
+        rest = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn pixels(width: u32, depth: u8, colour_type: ColourType) -> Pixels {
+        let mut bytes = width.to_be_bytes().to_vec();
+        bytes.extend(4u32.to_be_bytes());
+        bytes.extend([depth, colour_type.code(), 0, 0, 0]);
+        let header = ImageHeader::from_bytes(&bytes).unwrap();
+        Pixels {
+            header,
+            data: vec![0x80; header.row_len(width) * 4],
+        }
+    }
+
+    #[test]
+    fn test_lsb_round_trip() {
+        let mut image = pixels(16, 8, ColourType::Truecolour);
+        assert_eq!(capacity(&image, Method::Lsb).unwrap(), 20);
+        let original = image.clone();
+        embed(&mut image, Method::Lsb, b"hidden").unwrap();
+        assert_eq!(extract(&image, Method::Lsb).unwrap(), b"hidden");
+        assert!(image
+            .data
+            .iter()
+            .zip(&original.data)
+            .all(|(a, b)| a & !1 == b & !1));
+        assert!(embed(&mut image, Method::Lsb, &[0; 21]).is_err());
+    }
+
+    #[test]
+    fn test_lsb_skips_alpha_and_high_bytes() {
+        let mut image = pixels(8, 16, ColourType::GreyscaleAlpha);
+        assert_eq!(&lsb_carriers(&image.header).unwrap()[..3], [1, 5, 9]);
+        assert_eq!(capacity(&image, Method::Lsb).unwrap(), 0);
+        assert!(embed(&mut image, Method::Lsb, b"x").is_err());
+
+        let mut image = pixels(8, 8, ColourType::TruecolourAlpha);
+        embed(&mut image, Method::Lsb, &[]).unwrap();
+        assert!(image.data.iter().skip(3).step_by(4).all(|&a| a == 0x80));
+    }
+
+    #[test]
+    fn test_unsupported_images() {
+        assert!(capacity(&pixels(8, 8, ColourType::Indexed), Method::Lsb).is_err());
+        assert!(capacity(&pixels(8, 4, ColourType::Greyscale), Method::Lsb).is_err());
+    }
+
+    #[test]
+    fn test_extract_without_payload() {
+        let image = Pixels {
+            data: vec![0xFF; 48 * 4 * 3],
+            ..pixels(48, 8, ColourType::Truecolour)
+        };
+        assert!(extract(&image, Method::Lsb).is_err());
+    }
+
+    #[test]
+    fn test_method_from_str() {
+        assert_eq!(Method::from_str("lsb").unwrap(), Method::Lsb);
+        assert_eq!(Method::default().to_string(), "chunk");
+        assert!(Method::from_str("dct").is_err());
+    }
+
+    #[test]
+    fn test_alpha_uses_opaque_pixels_only() {
+        let mut image = pixels(16, 8, ColourType::TruecolourAlpha);
+        for (index, pixel) in image.data.chunks_exact_mut(4).enumerate() {
+            pixel[3] = if index % 2 == 0 { 0xFF } else { 0x80 };
+        }
+        assert_eq!(capacity(&image, Method::Alpha).unwrap(), 0);
+        let mut wide = pixels(128, 8, ColourType::TruecolourAlpha);
+        wide.data
+            .iter_mut()
+            .skip(3)
+            .step_by(4)
+            .take(100)
+            .for_each(|alpha| *alpha = 0xFF);
+        assert_eq!(capacity(&wide, Method::Alpha).unwrap(), 8);
+        let original = wide.clone();
+        embed(&mut wide, Method::Alpha, b"opaque!!").unwrap();
+        assert_eq!(extract(&wide, Method::Alpha).unwrap(), b"opaque!!");
+        let changed: Vec<usize> = (0..wide.data.len())
+            .filter(|&i| wide.data[i] != original.data[i])
+            .collect();
+        assert!(changed.iter().all(|&i| i % 4 == 3 && i < 400));
+        assert!(embed(&mut wide, Method::Alpha, &[0; 9]).is_err());
+    }
+
+    #[test]
+    fn test_alpha_needs_an_alpha_channel() {
+        let image = pixels(16, 8, ColourType::Truecolour);
+        assert!(capacity(&image, Method::Alpha).is_err());
+        let mut image = pixels(4, 16, ColourType::GreyscaleAlpha);
+        image.data = [0x12, 0x34, 0xFF, 0xFF].repeat(16);
+        assert_eq!(alpha_carriers(&image).unwrap()[..2], [3, 7]);
+        assert!(capacity(&image, Method::Chunk).is_err());
+    }
+
+    #[test]
+    fn test_capacities() {
+        let png = Png::from_chunks(vec![]);
+        let capacities = capacities(&png);
+        assert_eq!(capacities.len(), 4);
+        assert_eq!(capacities[1].method, Method::Stored);
+        assert!(capacities[..2]
+            .iter()
+            .all(|capacity| capacity.unavailable.is_none()));
+        assert!(capacities[2..]
+            .iter()
+            .all(|capacity| capacity.bytes.is_none() && capacity.unavailable.is_some()));
+    }
+
+    #[test]
+    fn test_stored_round_trip() {
+        let mut png = crate::pixels::tests::image(4, 4, 8, 0, &[0; 20]);
+        let original = png.decode_pixels().unwrap();
+        let idat = idat_stream(&png);
+        assert!(extract_stored(&png).is_err());
+
+        let payload: Vec<u8> = (0..70_000u32).map(|i| i as u8).collect();
+        embed_stored(&mut png, &payload).unwrap();
+        assert_eq!(extract_stored(&png).unwrap(), payload);
+        assert_eq!(png.decode_pixels().unwrap(), original);
+        assert!(idat_stream(&png).starts_with(&idat));
+
+        embed_stored(&mut png, b"").unwrap();
+        assert_eq!(extract_stored(&png).unwrap(), b"");
+        embed_stored(&mut png, b"again").unwrap();
+        assert_eq!(extract_stored(&png).unwrap(), b"again");
+        assert_eq!(idat_stream(&png).len(), idat.len() + 5 + 5);
+    }
+
+    #[test]
+    fn test_stored_payload_over_one_chunk() {
+        let mut png = crate::pixels::tests::image(4, 4, 8, 0, &[0; 20]);
+        let payload: Vec<u8> = (0..1_200_000u32).map(|i| (i % 251) as u8).collect();
+        embed_stored(&mut png, &payload).unwrap();
+        assert_eq!(png.chunks_by_type(IDAT_CHUNK_TYPE).count(), 2);
+        assert_eq!(extract_stored(&png).unwrap(), payload);
+        assert_eq!(png.decode_pixels().unwrap().data, [0; 16]);
+    }
+
+    #[test]
+    fn test_extract_stored_rejects_junk() {
+        let mut png = crate::pixels::tests::image(1, 1, 8, 0, &[0, 0]);
+        let mut stream = idat_stream(&png);
+        stream.extend(b"junk!!");
+        pixels::replace_idat(&mut png, &stream).unwrap();
+        assert!(extract_stored(&png).is_err());
+    }
+}
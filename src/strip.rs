@@ -0,0 +1,134 @@
+use crate::chunk::Chunk;
+use crate::exif::EXIF_CHUNK_TYPE;
+use crate::pattern::ChunkPattern;
+use crate::png::Png;
+use crate::text::{COMPRESSED_TEXT_CHUNK_TYPE, INTERNATIONAL_TEXT_CHUNK_TYPE, TEXT_CHUNK_TYPE};
+use crate::timestamp::TIME_CHUNK_TYPE;
+
+// Chunks that can identify the author, camera, location or time of an image.
+// XMP packets live in iTXt chunks, so they are covered too.
+pub const METADATA_CHUNK_TYPES: [&str; 5] = [
+    TEXT_CHUNK_TYPE,
+    COMPRESSED_TEXT_CHUNK_TYPE,
+    INTERNATIONAL_TEXT_CHUNK_TYPE,
+    EXIF_CHUNK_TYPE,
+    TIME_CHUNK_TYPE,
+];
+
+// Removes every ancillary chunk except those matching `keep`. Critical chunks
+// are always kept since the image cannot be decoded without them.
+pub fn strip(png: &mut Png, keep: &[ChunkPattern]) -> Vec<Chunk> {
+    png.remove_chunks_where(|chunk| {
+        let chunk_type = chunk.chunk_type();
+        !chunk_type.is_critical() && !keep.iter().any(|pattern| pattern.matches(chunk_type))
+    })
+}
+
+// Removes only the metadata chunks, leaving colour management (gAMA, cHRM,
+// sRGB, iCCP) and everything else alone so the image renders the same.
+pub fn anonymize(png: &mut Png) -> Vec<Chunk> {
+    png.remove_chunks_where(|chunk| {
+        METADATA_CHUNK_TYPES.contains(&chunk.chunk_type().to_string().as_str())
+    })
+}
+
+// Removes ancillary chunks that are byte-identical to an earlier chunk, as
+// left behind by tools that append metadata without checking for it.
+pub fn dedupe(png: &mut Png) -> Vec<Chunk> {
+    let mut seen: Vec<Chunk> = Vec::new();
+    png.remove_chunks_where(|chunk| {
+        if chunk.chunk_type().is_critical() {
+            return false;
+        }
+        if seen.contains(chunk) {
+            return true;
+        }
+        seen.push(chunk.clone());
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png() -> Png {
+        Png::from_chunks(
+            [
+                "IHDR", "gAMA", "iCCP", "tEXt", "PLTE", "tRNS", "IDAT", "tIME", "eXIf", "ruSt",
+                "IEND",
+            ]
+            .iter()
+            .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), b"data".to_vec()))
+            .collect(),
+        )
+    }
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_strip_everything_ancillary() {
+        let mut png = png();
+        let removed = strip(&mut png, &[]);
+        assert_eq!(types(&png), ["IHDR", "PLTE", "IDAT", "IEND"]);
+        assert_eq!(removed.len(), 7);
+    }
+
+    #[test]
+    fn test_strip_with_safelist() {
+        let mut png = png();
+        strip(
+            &mut png,
+            &["tRNS".parse().unwrap(), "gAMA".parse().unwrap()],
+        );
+        assert_eq!(
+            types(&png),
+            ["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_anonymize_keeps_colour_management() {
+        let mut png = png();
+        let removed = anonymize(&mut png);
+        assert_eq!(
+            types(&png),
+            ["IHDR", "gAMA", "iCCP", "PLTE", "tRNS", "IDAT", "ruSt", "IEND"]
+        );
+        let removed: Vec<String> = removed
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(removed, ["tEXt", "tIME", "eXIf"]);
+    }
+
+    #[test]
+    fn test_dedupe_identical_ancillary_chunks() {
+        let chunk =
+            |t: &str, data: &[u8]| Chunk::new(ChunkType::from_str(t).unwrap(), data.to_vec());
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR", b"header"),
+            chunk("tEXt", b"Title\0a"),
+            chunk("tEXt", b"Title\0a"),
+            chunk("tEXt", b"Title\0b"),
+            chunk("IDAT", b"same"),
+            chunk("IDAT", b"same"),
+            chunk("tEXt", b"Title\0a"),
+            chunk("IEND", b""),
+        ]);
+        let removed = dedupe(&mut png);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(
+            types(&png),
+            ["IHDR", "tEXt", "tEXt", "IDAT", "IDAT", "IEND"]
+        );
+        assert!(dedupe(&mut png).is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("unknown colour choice {:?}", string)),
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+const BOLD_MAGENTA: &str = "\x1b[1;35m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[1;31m";
+const DIM: &str = "\x1b[2m";
+
+// Colours are applied after padding, so callers format columns first and then
+// wrap the padded text; escape codes would otherwise count towards the width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    pub fn new(choice: ColorChoice) -> Self {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        };
+        Self { enabled }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn critical(&self, text: &str) -> String {
+        self.paint(BOLD_CYAN, text)
+    }
+
+    pub fn payload(&self, text: &str) -> String {
+        self.paint(BOLD_MAGENTA, text)
+    }
+
+    pub fn unknown(&self, text: &str) -> String {
+        self.paint(YELLOW, text)
+    }
+
+    pub fn good(&self, text: &str) -> String {
+        self.paint(GREEN, text)
+    }
+
+    pub fn bad(&self, text: &str) -> String {
+        self.paint(RED, text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(DIM, text)
+    }
+}
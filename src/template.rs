@@ -0,0 +1,191 @@
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field { name: String, spec: Spec },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spec {
+    Plain,
+    Hex,
+    Precision(usize),
+}
+
+// An output line shaped like `{offset}\t{type}\t{length}`. Placeholders name
+// the fields of the JSON report, `{name:x}` prints an integer in hex and
+// `{name:.N}` rounds a number to N decimals; `{{`, `}}`, `\t`, `\n` and `\\`
+// are escapes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn render(&self, value: &impl Serialize) -> Result<String, String> {
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => output.push_str(text),
+                Part::Field { name, spec } => {
+                    let field = value
+                        .get(name)
+                        .ok_or_else(|| format!("unknown template placeholder {{{}}}", name))?;
+                    output.push_str(&format_field(name, field, *spec)?);
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn format_field(name: &str, field: &Value, spec: Spec) -> Result<String, String> {
+    match (field, spec) {
+        (Value::Null, _) => Ok(String::new()),
+        (Value::String(text), Spec::Plain) => Ok(text.clone()),
+        (Value::Number(number), Spec::Hex) => number
+            .as_u64()
+            .map(|n| format!("{:#x}", n))
+            .ok_or_else(|| format!("{{{}:x}} needs a whole number", name)),
+        (Value::Number(number), Spec::Precision(digits)) => Ok(format!(
+            "{:.*}",
+            digits,
+            number.as_f64().unwrap_or_default()
+        )),
+        (_, Spec::Plain) => Ok(field.to_string()),
+        _ => Err(format!("{{{}}} cannot be formatted that way", name)),
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = string.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("unclosed placeholder {{{}", placeholder)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err("unmatched } in template, write }} for a literal one".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        None => (placeholder, Spec::Plain),
+        Some((name, "x")) => (name, Spec::Hex),
+        Some((name, precision)) => {
+            let digits = precision
+                .strip_prefix('.')
+                .and_then(|digits| digits.parse().ok())
+                .ok_or_else(|| format!("unknown format {:?} in {{{}}}", precision, placeholder))?;
+            (name, Spec::Precision(digits))
+        }
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid placeholder {{{}}}", placeholder));
+    }
+    Ok(Part::Field {
+        name: name.to_string(),
+        spec,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        #[serde(rename = "type")]
+        chunk_type: &'static str,
+        offset: u64,
+        entropy: f64,
+        details: Option<&'static str>,
+    }
+
+    fn row() -> Row {
+        Row {
+            chunk_type: "ruSt",
+            offset: 33,
+            entropy: 1.23456,
+            details: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template: Template = r"{offset}\t{type}\t{details}|".parse().unwrap();
+        assert_eq!(template.render(&row()).unwrap(), "33\truSt\t|");
+    }
+
+    #[test]
+    fn test_render_specs() {
+        let template: Template = "{offset:x} {entropy:.2} {{literal}}".parse().unwrap();
+        assert_eq!(template.render(&row()).unwrap(), "0x21 1.23 {literal}");
+    }
+
+    #[test]
+    fn test_unknown_placeholder() {
+        let template: Template = "{nope}".parse().unwrap();
+        assert_eq!(
+            template.render(&row()).unwrap_err(),
+            "unknown template placeholder {nope}"
+        );
+        assert!("{type:x}"
+            .parse::<Template>()
+            .unwrap()
+            .render(&row())
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!("{offset".parse::<Template>().is_err());
+        assert!("offset}".parse::<Template>().is_err());
+        assert!("{}".parse::<Template>().is_err());
+        assert!("{offset:q}".parse::<Template>().is_err());
+    }
+}
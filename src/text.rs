@@ -0,0 +1,425 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::compression::Codec;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const TEXT_CHUNK_TYPE: &str = "tEXt";
+pub const COMPRESSED_TEXT_CHUNK_TYPE: &str = "zTXt";
+pub const INTERNATIONAL_TEXT_CHUNK_TYPE: &str = "iTXt";
+const COMPRESSION_METHOD_DEFLATE: u8 = 0;
+// Text is decompressed whenever a file is inspected, so a small zTXt or iTXt
+// chunk must not be able to inflate into gigabytes.
+pub const MAX_TEXT_LEN: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyword(String);
+
+impl Keyword {
+    pub const MAX_LEN: usize = 79;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Keyword {
+    type Err = PngmeError;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = |reason: &str| {
+            Err(PngmeError::Malformed(format!(
+                "invalid text keyword {:?}: {}",
+                string, reason
+            )))
+        };
+        let len = string.chars().count();
+        if len == 0 || len > Self::MAX_LEN {
+            return invalid("must be 1 to 79 characters long");
+        }
+        if let Some(c) = string
+            .chars()
+            .find(|&c| !matches!(u32::from(c), 32..=126 | 161..=255))
+        {
+            return invalid(&format!("{:?} is not a printable Latin-1 character", c));
+        }
+        if string.starts_with(' ') || string.ends_with(' ') {
+            return invalid("must not start or end with a space");
+        }
+        if string.contains("  ") {
+            return invalid("must not contain consecutive spaces");
+        }
+        Ok(Self(string.to_string()))
+    }
+}
+
+impl std::fmt::Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEntry {
+    pub keyword: Keyword,
+    pub text: String,
+    pub compressed: bool,
+    pub international: Option<International>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct International {
+    pub language: String,
+    pub translated_keyword: String,
+}
+
+impl TextEntry {
+    pub fn new(keyword: Keyword, text: impl Into<String>) -> Self {
+        Self {
+            keyword,
+            text: text.into(),
+            compressed: false,
+            international: None,
+        }
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = to_latin1(self.keyword.as_str())?;
+        data.push(0);
+        // tEXt and zTXt are Latin-1 only, so anything else goes in an iTXt chunk.
+        let international = match &self.international {
+            None if to_latin1(&self.text).is_err() => Some(International::default()),
+            international => international.clone(),
+        };
+        if let Some(international) = international {
+            if !international.language.is_ascii() {
+                return Err(PngmeError::Malformed(format!(
+                    "language tag {:?} must be ASCII",
+                    international.language
+                )));
+            }
+            data.push(u8::from(self.compressed));
+            data.push(COMPRESSION_METHOD_DEFLATE);
+            data.extend_from_slice(international.language.as_bytes());
+            data.push(0);
+            data.extend_from_slice(international.translated_keyword.as_bytes());
+            data.push(0);
+            match self.compressed {
+                true => data.extend(deflate(self.text.as_bytes())?),
+                false => data.extend_from_slice(self.text.as_bytes()),
+            }
+            return Ok(Chunk::new(INTERNATIONAL_TEXT_CHUNK_TYPE.parse()?, data));
+        }
+        if !self.compressed {
+            data.extend(to_latin1(&self.text)?);
+            return Ok(Chunk::new(TEXT_CHUNK_TYPE.parse()?, data));
+        }
+        data.push(COMPRESSION_METHOD_DEFLATE);
+        data.extend(deflate(&to_latin1(&self.text)?)?);
+        Ok(Chunk::new(COMPRESSED_TEXT_CHUNK_TYPE.parse()?, data))
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Self> {
+        let chunk_type = chunk.chunk_type().to_string();
+        let data = chunk.data();
+        let separator = data.iter().position(|&byte| byte == 0).ok_or_else(|| {
+            PngmeError::Malformed(format!("{} chunk has no keyword separator", chunk_type))
+        })?;
+        let keyword: Keyword = from_latin1(&data[..separator]).parse()?;
+        let rest = &data[separator + 1..];
+        match chunk_type.as_str() {
+            TEXT_CHUNK_TYPE => Ok(Self::new(keyword, from_latin1(rest))),
+            COMPRESSED_TEXT_CHUNK_TYPE => {
+                let (method, compressed) = rest.split_first().ok_or_else(|| {
+                    PngmeError::Malformed("zTXt chunk has no compression method".into())
+                })?;
+                Ok(Self {
+                    compressed: true,
+                    ..Self::new(keyword, from_latin1(&inflate(*method, compressed)?))
+                })
+            }
+            INTERNATIONAL_TEXT_CHUNK_TYPE => {
+                let truncated = || PngmeError::Malformed("iTXt chunk is truncated".into());
+                let [flag, method, rest @ ..] = rest else {
+                    return Err(truncated());
+                };
+                let mut fields = rest.splitn(3, |&byte| byte == 0);
+                let language = fields.next().ok_or_else(truncated)?;
+                let translated_keyword = fields.next().ok_or_else(truncated)?;
+                let text = fields.next().ok_or_else(truncated)?;
+                let text = match flag {
+                    0 => text.to_vec(),
+                    _ => inflate(*method, text)?,
+                };
+                Ok(Self {
+                    compressed: *flag != 0,
+                    international: Some(International {
+                        language: String::from_utf8(language.to_vec())?,
+                        translated_keyword: String::from_utf8(translated_keyword.to_vec())?,
+                    }),
+                    ..Self::new(keyword, String::from_utf8(text)?)
+                })
+            }
+            _ => Err(PngmeError::Malformed(format!(
+                "{} is not a text chunk",
+                chunk_type
+            ))),
+        }
+    }
+}
+
+pub fn entries(png: &Png) -> Result<Vec<TextEntry>> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| is_text_chunk(chunk.chunk_type()))
+        .map(TextEntry::from_chunk)
+        .collect()
+}
+
+pub fn get(png: &Png, keyword: &str) -> Result<Option<String>> {
+    Ok(entries(png)?
+        .into_iter()
+        .find(|entry| entry.keyword.as_str() == keyword)
+        .map(|entry| entry.text))
+}
+
+pub fn set(png: &mut Png, entry: &TextEntry) -> Result<()> {
+    let chunk = entry.to_chunk()?;
+    let existing = png.chunks().iter().position(|candidate| {
+        is_text_chunk(candidate.chunk_type())
+            && TextEntry::from_chunk(candidate).is_ok_and(|e| e.keyword == entry.keyword)
+    });
+    match existing {
+        Some(index) => {
+            png.remove_chunk_at(index)?;
+            png.insert_chunk(index, chunk)
+        }
+        None => png.append_chunk(chunk),
+    }
+}
+
+pub fn remove(png: &mut Png, keyword: &str) -> Vec<Chunk> {
+    png.remove_chunks_where(|chunk| {
+        is_text_chunk(chunk.chunk_type())
+            && TextEntry::from_chunk(chunk).is_ok_and(|entry| entry.keyword.as_str() == keyword)
+    })
+}
+
+// The keyword bytes of a text chunk without decoding or validating them.
+pub(crate) fn raw_keyword(chunk: &Chunk) -> Option<&[u8]> {
+    if !is_text_chunk(chunk.chunk_type()) {
+        return None;
+    }
+    let data = chunk.data();
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    Some(&data[..end])
+}
+
+pub(crate) fn is_text_chunk(chunk_type: &ChunkType) -> bool {
+    [
+        TEXT_CHUNK_TYPE,
+        COMPRESSED_TEXT_CHUNK_TYPE,
+        INTERNATIONAL_TEXT_CHUNK_TYPE,
+    ]
+    .contains(&chunk_type.to_string().as_str())
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    Codec::Deflate.compress_raw(Codec::Deflate.default_level(), data)
+}
+
+fn inflate(method: u8, data: &[u8]) -> Result<Vec<u8>> {
+    if method != COMPRESSION_METHOD_DEFLATE {
+        return Err(PngmeError::Malformed(format!(
+            "unknown text compression method {}",
+            method
+        )));
+    }
+    Codec::Deflate
+        .decompress_raw(data, MAX_TEXT_LEN)
+        .map_err(|error| match error {
+            PngmeError::Malformed(_) => PngmeError::Malformed(format!(
+                "compressed text is longer than {} bytes",
+                MAX_TEXT_LEN
+            )),
+            error => error,
+        })
+}
+
+fn to_latin1(string: &str) -> Result<Vec<u8>> {
+    string
+        .chars()
+        .map(|c| {
+            u8::try_from(u32::from(c)).map_err(|_| {
+                PngmeError::Malformed(format!("{:?} cannot be stored in a Latin-1 tEXt chunk", c))
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn from_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyword(keyword: &str) -> Keyword {
+        keyword.parse().unwrap()
+    }
+
+    #[test]
+    fn test_keyword_validation() {
+        assert!("Title".parse::<Keyword>().is_ok());
+        assert!("Creation Time".parse::<Keyword>().is_ok());
+        assert!("Caf\u{e9}".parse::<Keyword>().is_ok());
+        assert!("".parse::<Keyword>().is_err());
+        assert!("k".repeat(80).parse::<Keyword>().is_err());
+        assert!(" Title".parse::<Keyword>().is_err());
+        assert!("Title ".parse::<Keyword>().is_err());
+        assert!("Two  Spaces".parse::<Keyword>().is_err());
+        assert!("Tab\there".parse::<Keyword>().is_err());
+        assert!("\u{1F980}".parse::<Keyword>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_keyword_in_chunk() {
+        let chunk = Chunk::new(TEXT_CHUNK_TYPE.parse().unwrap(), b" Bad\0value".to_vec());
+        assert!(TextEntry::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_chunk_layout() {
+        let chunk = TextEntry::new(keyword("Title"), "Caf\u{e9}")
+            .to_chunk()
+            .unwrap();
+        assert_eq!(chunk.data(), b"Title\0Caf\xe9");
+        assert_eq!(
+            TextEntry::from_chunk(&chunk).unwrap(),
+            TextEntry::new(keyword("Title"), "Caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn test_non_latin1_uses_itxt() {
+        let chunk = TextEntry::new(keyword("Title"), "\u{1F980}")
+            .to_chunk()
+            .unwrap();
+        assert_eq!(
+            chunk.chunk_type().to_string(),
+            INTERNATIONAL_TEXT_CHUNK_TYPE
+        );
+        assert_eq!(TextEntry::from_chunk(&chunk).unwrap().text, "\u{1F980}");
+    }
+
+    #[test]
+    fn test_international_chunk() {
+        for compressed in [false, true] {
+            let entry = TextEntry {
+                compressed,
+                international: Some(International {
+                    language: "ja".to_string(),
+                    translated_keyword: "\u{984c}\u{540d}".to_string(),
+                }),
+                ..TextEntry::new(keyword("Title"), "\u{732b}")
+            };
+            let chunk = entry.to_chunk().unwrap();
+            assert_eq!(
+                chunk.chunk_type().to_string(),
+                INTERNATIONAL_TEXT_CHUNK_TYPE
+            );
+            assert_eq!(
+                &chunk.data()[..10],
+                [b"Title\0" as &[u8], &[compressed as u8, 0], b"ja"].concat()
+            );
+            assert_eq!(TextEntry::from_chunk(&chunk).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    fn test_truncated_international_chunk() {
+        let chunk = Chunk::new(
+            INTERNATIONAL_TEXT_CHUNK_TYPE.parse().unwrap(),
+            b"Title\0\0\0en".to_vec(),
+        );
+        assert!(TextEntry::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_missing_separator() {
+        let chunk = Chunk::new(TEXT_CHUNK_TYPE.parse().unwrap(), b"Title".to_vec());
+        assert!(TextEntry::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_compressed_chunk() {
+        let entry = TextEntry {
+            compressed: true,
+            ..TextEntry::new(keyword("Comment"), "a long comment ".repeat(20))
+        };
+        let chunk = entry.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), COMPRESSED_TEXT_CHUNK_TYPE);
+        assert_eq!(&chunk.data()[..9], b"Comment\0\0");
+        assert!(chunk.data().len() < 100);
+        assert_eq!(TextEntry::from_chunk(&chunk).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_compressed_text_is_bounded() {
+        let bomb = Codec::Deflate
+            .compress_raw(9, &vec![b'a'; MAX_TEXT_LEN as usize + 1])
+            .unwrap();
+        let data = b"Comment\0\0".iter().chain(&bomb).copied().collect();
+        let chunk = Chunk::new(COMPRESSED_TEXT_CHUNK_TYPE.parse().unwrap(), data);
+        assert!(matches!(
+            TextEntry::from_chunk(&chunk),
+            Err(PngmeError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_compression_method() {
+        let chunk = Chunk::new(
+            COMPRESSED_TEXT_CHUNK_TYPE.parse().unwrap(),
+            b"Comment\0\x01data".to_vec(),
+        );
+        assert!(TextEntry::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_set_replaces_across_kinds() {
+        let mut png = Png::from_chunks(Vec::new());
+        set(&mut png, &TextEntry::new(keyword("Title"), "Plain")).unwrap();
+        let compressed = TextEntry {
+            compressed: true,
+            ..TextEntry::new(keyword("Title"), "Packed")
+        };
+        set(&mut png, &compressed).unwrap();
+        assert_eq!(png.chunks().len(), 1);
+        assert_eq!(entries(&png).unwrap(), [compressed]);
+    }
+
+    #[test]
+    fn test_set_and_get() {
+        let mut png = Png::from_chunks(Vec::new());
+        set(&mut png, &TextEntry::new(keyword("Title"), "First")).unwrap();
+        set(&mut png, &TextEntry::new(keyword("Author"), "Me")).unwrap();
+        set(&mut png, &TextEntry::new(keyword("Title"), "Second")).unwrap();
+        assert_eq!(get(&png, "Title").unwrap().unwrap(), "Second");
+        assert_eq!(get(&png, "Comment").unwrap(), None);
+        let keywords: Vec<String> = entries(&png)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.keyword.to_string())
+            .collect();
+        assert_eq!(keywords, ["Title", "Author"]);
+
+        assert_eq!(remove(&mut png, "Title").len(), 1);
+        assert_eq!(get(&png, "Title").unwrap(), None);
+        assert!(remove(&mut png, "Title").is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const TIME_CHUNK_TYPE: &str = "tIME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self::from_unix(seconds)
+    }
+
+    pub fn from_unix(seconds: u64) -> Self {
+        let (days, time) = (seconds / 86_400, seconds % 86_400);
+        // Civil-from-days, see https://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 7] {
+        let [year_high, year_low] = self.year.to_be_bytes();
+        [
+            year_high,
+            year_low,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ]
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let [year_high, year_low, month, day, hour, minute, second] = bytes else {
+            return Err(PngmeError::Malformed(format!(
+                "tIME chunk should be 7 bytes, got {}",
+                bytes.len()
+            )));
+        };
+        Self {
+            year: u16::from_be_bytes([*year_high, *year_low]),
+            month: *month,
+            day: *day,
+            hour: *hour,
+            minute: *minute,
+            second: *second,
+        }
+        .validated()
+    }
+
+    fn validated(self) -> Result<Self> {
+        // The spec allows a leap second of 60.
+        if !(1..=12).contains(&self.month)
+            || !(1..=31).contains(&self.day)
+            || self.hour > 23
+            || self.minute > 59
+            || self.second > 60
+        {
+            return Err(PngmeError::Malformed(format!("invalid timestamp {}", self)));
+        }
+        Ok(self)
+    }
+}
+
+impl std::str::FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        if string == "now" {
+            return Ok(Self::now());
+        }
+        let invalid = || format!("expected YYYY-MM-DDTHH:MM:SS or now, got {:?}", string);
+        let string = string.strip_suffix('Z').unwrap_or(string);
+        let (date, time) = string.split_once(['T', ' ']).ok_or_else(invalid)?;
+        let date: Vec<&str> = date.split('-').collect();
+        let time: Vec<&str> = time.split(':').collect();
+        let ([year, month, day], [hour, minute, second]) = (date.as_slice(), time.as_slice())
+        else {
+            return Err(invalid());
+        };
+        let number = |part: &str| part.parse::<u8>().map_err(|_| invalid());
+        Self {
+            year: year.parse().map_err(|_| invalid())?,
+            month: number(month)?,
+            day: number(day)?,
+            hour: number(hour)?,
+            minute: number(minute)?,
+            second: number(second)?,
+        }
+        .validated()
+        .map_err(|e| e.to_string())
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+pub fn get(png: &Png) -> Result<Option<Timestamp>> {
+    png.chunk_by_type(TIME_CHUNK_TYPE)
+        .map(|chunk| Timestamp::from_bytes(chunk.data()))
+        .transpose()
+}
+
+pub fn set(png: &mut Png, timestamp: Timestamp) -> Result<()> {
+    let chunk = Chunk::new(TIME_CHUNK_TYPE.parse()?, timestamp.to_bytes().to_vec());
+    let index = png
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.chunk_type().to_string() == TIME_CHUNK_TYPE)
+        .unwrap_or_else(|| png.append_index());
+    png.remove_chunks(TIME_CHUNK_TYPE);
+    png.insert_chunk(index, chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_from_unix() {
+        assert_eq!(Timestamp::from_unix(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            Timestamp::from_unix(951_782_400).to_string(),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            Timestamp::from_unix(1_700_000_000).to_string(),
+            "2023-11-14T22:13:20Z"
+        );
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let timestamp = Timestamp::from_str("2024-06-01T12:34:56").unwrap();
+        assert_eq!(timestamp.to_bytes(), [0x07, 0xe8, 6, 1, 12, 34, 56]);
+        assert_eq!(
+            Timestamp::from_bytes(&timestamp.to_bytes()).unwrap(),
+            timestamp
+        );
+        assert!(Timestamp::from_bytes(&[0x07, 0xe8, 13, 1, 0, 0, 0]).is_err());
+        assert!(Timestamp::from_bytes(&[0x07]).is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert!(Timestamp::from_str("2024-06-01 12:34:56Z").is_ok());
+        assert!(Timestamp::from_str("now").is_ok());
+        assert!(Timestamp::from_str("2024-06-01").is_err());
+        assert!(Timestamp::from_str("2024-06-01T25:00:00").is_err());
+    }
+
+    #[test]
+    fn test_set_replaces() {
+        let mut png = Png::from_chunks(vec![Chunk::new("IEND".parse().unwrap(), Vec::new())]);
+        assert_eq!(get(&png).unwrap(), None);
+        let first = Timestamp::from_unix(0);
+        let second = Timestamp::from_unix(1_700_000_000);
+        set(&mut png, first).unwrap();
+        set(&mut png, second).unwrap();
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), TIME_CHUNK_TYPE);
+        assert_eq!(get(&png).unwrap(), Some(second));
+    }
+}
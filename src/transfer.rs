@@ -0,0 +1,281 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::{PngmeError, Result};
+use crate::ihdr::IHDR_CHUNK_TYPE;
+use crate::palette::PALETTE_CHUNK_TYPE;
+use crate::pattern::ChunkPattern;
+use crate::png::Png;
+use crate::text;
+use crate::validate;
+
+// What `clone-metadata` copies when no chunk types are given.
+pub const METADATA_CHUNK_TYPES: [&str; 6] = ["tEXt", "zTXt", "iTXt", "iCCP", "pHYs", "eXIf"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transfer {
+    pub copied: Vec<ChunkType>,
+    pub skipped_unsafe: Vec<ChunkType>,
+}
+
+// Which side wins when a merged chunk fills a slot the base already has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefer {
+    Base,
+    Other,
+}
+
+impl std::str::FromStr for Prefer {
+    type Err = PngmeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "base" => Ok(Self::Base),
+            "other" => Ok(Self::Other),
+            _ => Err(PngmeError::Usage(format!(
+                "unknown preference {:?}, expected base or other",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Merge {
+    pub added: Vec<ChunkType>,
+    pub replaced: Vec<ChunkType>,
+    pub conflicts: Vec<ChunkType>,
+    pub skipped_unsafe: Vec<ChunkType>,
+}
+
+fn same_image(a: &Png, b: &Png) -> bool {
+    [IHDR_CHUNK_TYPE, PALETTE_CHUNK_TYPE]
+        .iter()
+        .all(|chunk_type| a.chunk_by_type(chunk_type) == b.chunk_by_type(chunk_type))
+}
+
+// Adds the ancillary chunks of `other` that `base` lacks. A chunk that fills a
+// slot `base` already has with different data is a conflict, settled by
+// `prefer`; chunks `base` already has byte for byte are skipped.
+pub fn merge(base: &mut Png, other: &Png, prefer: Prefer) -> Result<Merge> {
+    let same_image = same_image(base, other);
+    let mut merge = Merge::default();
+    for chunk in other.chunks() {
+        let chunk_type = *chunk.chunk_type();
+        if chunk_type.is_critical() || base.chunks().contains(chunk) {
+            continue;
+        }
+        if !chunk_type.is_safe_to_copy() && !same_image {
+            merge.skipped_unsafe.push(chunk_type);
+            continue;
+        }
+        let conflict = base
+            .chunks()
+            .iter()
+            .any(|existing| same_slot(existing, chunk));
+        match (conflict, prefer) {
+            (false, _) => merge.added.push(chunk_type),
+            (true, Prefer::Base) => {
+                merge.conflicts.push(chunk_type);
+                continue;
+            }
+            (true, Prefer::Other) => {
+                base.remove_chunks_where(|existing| same_slot(existing, chunk));
+                merge.replaced.push(chunk_type);
+            }
+        }
+        insert_in_order(base, chunk.clone())?;
+    }
+    Ok(merge)
+}
+
+// Copies the ancillary chunks of `source` that match `select` (or the usual
+// metadata chunks) into `destination`, replacing chunks that fill the same
+// slot there. Chunks that are not safe to copy depend on the image data, so
+// they are only copied when both images have the same IHDR and PLTE, or when
+// `force` is set.
+pub fn clone_metadata(
+    source: &Png,
+    destination: &mut Png,
+    select: &[ChunkPattern],
+    force: bool,
+) -> Result<Transfer> {
+    let same_image = same_image(source, destination);
+    let mut transfer = Transfer::default();
+    for chunk in source.chunks() {
+        let chunk_type = chunk.chunk_type();
+        let selected = match select {
+            [] => METADATA_CHUNK_TYPES.contains(&chunk_type.to_string().as_str()),
+            patterns => patterns.iter().any(|pattern| pattern.matches(chunk_type)),
+        };
+        if !selected || chunk_type.is_critical() {
+            continue;
+        }
+        if !chunk_type.is_safe_to_copy() && !same_image && !force {
+            transfer.skipped_unsafe.push(*chunk_type);
+            continue;
+        }
+        destination.remove_chunks_where(|existing| same_slot(existing, chunk));
+        insert_in_order(destination, chunk.clone())?;
+        transfer.copied.push(*chunk_type);
+    }
+    Ok(transfer)
+}
+
+// The Nth chunk of a type, for copying it verbatim into another file.
+pub fn nth_chunk<'a>(png: &'a Png, chunk_type: &'a str, nth: usize) -> Result<&'a Chunk> {
+    png.chunks_by_type(chunk_type).nth(nth).ok_or_else(|| {
+        PngmeError::ChunkNotFound(format!("no {} chunk #{} to copy", chunk_type, nth))
+    })
+}
+
+// Whether `b` would replace `a`: text chunks are keyed by keyword, other
+// ancillary chunks by type.
+fn same_slot(a: &Chunk, b: &Chunk) -> bool {
+    a.chunk_type() == b.chunk_type() && text::raw_keyword(a) == text::raw_keyword(b)
+}
+
+// Inserts a chunk at the first position its canonical rank allows, keeping
+// the destination's layout valid.
+pub fn insert_in_order(png: &mut Png, chunk: Chunk) -> Result<()> {
+    let rank = validate::canonical_rank(&chunk.chunk_type().to_string(), false);
+    let first_idat = png
+        .chunks()
+        .iter()
+        .position(|existing| &existing.chunk_type().bytes() == b"IDAT")
+        .unwrap_or(png.chunks().len());
+    let index = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .position(|(index, existing)| {
+            validate::canonical_rank(&existing.chunk_type().to_string(), index > first_idat) > rank
+        })
+        .unwrap_or(png.chunks().len());
+    png.insert_chunk(index, chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::chunk;
+    use std::str::FromStr;
+
+    fn png(chunks: Vec<Chunk>) -> Png {
+        let mut all = vec![chunk("IHDR", b"header")];
+        all.extend(chunks);
+        all.push(chunk("IDAT", b"pixels"));
+        all.push(chunk("IEND", b""));
+        Png::from_chunks(all)
+    }
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_clone_metadata_replaces_matching_slots() {
+        let source = png(vec![
+            chunk("iCCP", b"profile"),
+            chunk("pHYs", b"new"),
+            chunk("tEXt", b"Title\0new"),
+            chunk("ruSt", b"secret"),
+        ]);
+        let mut destination = png(vec![
+            chunk("pHYs", b"old"),
+            chunk("tEXt", b"Title\0old"),
+            chunk("tEXt", b"Author\0me"),
+        ]);
+        let transfer = clone_metadata(&source, &mut destination, &[], false).unwrap();
+        assert_eq!(transfer.copied.len(), 3);
+        assert!(transfer.skipped_unsafe.is_empty());
+        assert_eq!(
+            types(&destination),
+            ["IHDR", "iCCP", "pHYs", "tEXt", "tEXt", "IDAT", "IEND"]
+        );
+        assert_eq!(destination.chunk_by_type("pHYs").unwrap().data(), b"new");
+        assert_eq!(destination.chunks()[3].data(), b"Author\0me");
+        assert_eq!(destination.chunks()[4].data(), b"Title\0new");
+        assert!(validate::violations(&destination).is_empty());
+    }
+
+    #[test]
+    fn test_unsafe_chunks_need_the_same_image() {
+        let source = png(vec![chunk("iCCP", b"profile"), chunk("tEXt", b"a\0b")]);
+        let mut destination = Png::from_chunks(vec![
+            chunk("IHDR", b"other"),
+            chunk("IDAT", b"pixels"),
+            chunk("IEND", b""),
+        ]);
+        let transfer = clone_metadata(&source, &mut destination, &[], false).unwrap();
+        assert_eq!(
+            transfer.skipped_unsafe,
+            [ChunkType::from_str("iCCP").unwrap()]
+        );
+        assert_eq!(types(&destination), ["IHDR", "tEXt", "IDAT", "IEND"]);
+
+        let transfer = clone_metadata(&source, &mut destination, &[], true).unwrap();
+        assert!(transfer.skipped_unsafe.is_empty());
+        assert_eq!(
+            types(&destination),
+            ["IHDR", "iCCP", "tEXt", "IDAT", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_clone_selected_types() {
+        let source = png(vec![chunk("pHYs", b"dpi"), chunk("tEXt", b"a\0b")]);
+        let mut destination = png(vec![]);
+        let select = ["pHYs".parse().unwrap()];
+        clone_metadata(&source, &mut destination, &select, false).unwrap();
+        assert_eq!(types(&destination), ["IHDR", "pHYs", "IDAT", "IEND"]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = png(vec![chunk("tEXt", b"Title\0base"), chunk("pHYs", b"dpi")]);
+        let other = png(vec![
+            chunk("gAMA", b"gamma"),
+            chunk("pHYs", b"dpi"),
+            chunk("tEXt", b"Title\0other"),
+            chunk("tEXt", b"Author\0me"),
+        ]);
+        let merge = merge(&mut base, &other, Prefer::Base).unwrap();
+        assert_eq!(merge.added.len(), 2);
+        assert_eq!(merge.conflicts, [ChunkType::from_str("tEXt").unwrap()]);
+        assert_eq!(
+            types(&base),
+            ["IHDR", "gAMA", "tEXt", "pHYs", "tEXt", "IDAT", "IEND"]
+        );
+        assert_eq!(base.chunks()[2].data(), b"Title\0base");
+        assert_eq!(base.chunks()[4].data(), b"Author\0me");
+    }
+
+    #[test]
+    fn test_merge_preferring_other() {
+        let mut base = png(vec![chunk("tEXt", b"Title\0base")]);
+        let other = png(vec![chunk("tEXt", b"Title\0other")]);
+        let merged = merge(&mut base, &other, Prefer::Other).unwrap();
+        assert_eq!(merged.replaced.len(), 1);
+        assert_eq!(base.chunks()[1].data(), b"Title\0other");
+        assert_eq!(Prefer::from_str("base").unwrap(), Prefer::Base);
+        assert!(Prefer::from_str("both").is_err());
+    }
+
+    #[test]
+    fn test_nth_chunk_and_insert_in_order() {
+        let source = png(vec![chunk("tEXt", b"a\0one"), chunk("tEXt", b"b\0two")]);
+        assert_eq!(nth_chunk(&source, "tEXt", 1).unwrap().data(), b"b\0two");
+        assert!(nth_chunk(&source, "tEXt", 2).is_err());
+        assert!(nth_chunk(&source, "zTXt", 0).is_err());
+
+        let mut destination = png(vec![chunk("tEXt", b"c\0three")]);
+        insert_in_order(&mut destination, chunk("gAMA", b"gamma")).unwrap();
+        assert_eq!(
+            types(&destination),
+            ["IHDR", "gAMA", "tEXt", "IDAT", "IEND"]
+        );
+    }
+}
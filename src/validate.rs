@@ -0,0 +1,232 @@
+use crate::error::PngmeError;
+use crate::png::Png;
+
+const SINGLE_INSTANCE: [&str; 17] = [
+    "IHDR", "PLTE", "IEND", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "bKGD", "hIST", "tRNS", "pHYs",
+    "tIME", "eXIf", "oFFs", "pCAL", "sCAL",
+];
+const BEFORE_PLTE: [&str; 5] = ["cHRM", "gAMA", "iCCP", "sBIT", "sRGB"];
+const AFTER_PLTE: [&str; 3] = ["bKGD", "hIST", "tRNS"];
+const BEFORE_IDAT: [&str; 15] = [
+    "PLTE", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "bKGD", "hIST", "tRNS", "pHYs", "sPLT", "eXIf",
+    "oFFs", "pCAL", "sCAL",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+impl Violation {
+    pub(crate) fn at(index: usize, message: impl Into<String>) -> Self {
+        Self {
+            index: Some(index),
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn file(message: impl Into<String>) -> Self {
+        Self {
+            index: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "chunk #{}: {}", index, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// Checks the chunk layout rules of the PNG specification: IHDR first and IEND
+// last, single-instance chunks, ordering relative to PLTE and IDAT,
+// consecutive IDATs and the reserved bit of every chunk type.
+pub fn violations(png: &Png) -> Vec<Violation> {
+    let types: Vec<String> = png
+        .chunks()
+        .iter()
+        .map(|chunk| chunk.chunk_type().to_string())
+        .collect();
+    let mut violations = Vec::new();
+    match types.first().map(String::as_str) {
+        Some("IHDR") => {}
+        Some(other) => violations.push(Violation::at(
+            0,
+            format!("IHDR must be the first chunk, found {}", other),
+        )),
+        None => violations.push(Violation::file("the file has no chunks")),
+    }
+    match types.last().map(String::as_str) {
+        Some("IEND") | None => {}
+        Some(other) => violations.push(Violation::at(
+            types.len() - 1,
+            format!("IEND must be the last chunk, found {}", other),
+        )),
+    }
+
+    let first = |name: &str| types.iter().position(|t| t == name);
+    let plte = first("PLTE");
+    let idat = first("IDAT");
+    if idat.is_none() {
+        violations.push(Violation::file("the file has no IDAT chunk"));
+    }
+    for (index, chunk_type) in types.iter().enumerate() {
+        let name = chunk_type.as_str();
+        if !png.chunks()[index].chunk_type().is_reserved_bit_valid() {
+            violations.push(Violation::at(
+                index,
+                format!("{} has the reserved bit set", name),
+            ));
+        }
+        if SINGLE_INSTANCE.contains(&name) && first(name) != Some(index) {
+            violations.push(Violation::at(
+                index,
+                format!("{} may only appear once", name),
+            ));
+        }
+        if BEFORE_PLTE.contains(&name) && plte.is_some_and(|plte| index > plte) {
+            violations.push(Violation::at(
+                index,
+                format!("{} must come before PLTE", name),
+            ));
+        }
+        if AFTER_PLTE.contains(&name) && plte.is_some_and(|plte| index < plte) {
+            violations.push(Violation::at(
+                index,
+                format!("{} must come after PLTE", name),
+            ));
+        }
+        if BEFORE_IDAT.contains(&name) && idat.is_some_and(|idat| index > idat) {
+            violations.push(Violation::at(
+                index,
+                format!("{} must come before IDAT", name),
+            ));
+        }
+        if name == "IDAT" && index > 0 && idat != Some(index) && types[index - 1] != "IDAT" {
+            violations.push(Violation::at(index, "IDAT chunks must be consecutive"));
+        }
+    }
+    violations
+}
+
+// Position of a chunk type in the canonical order: IHDR, chunks that must
+// precede PLTE, PLTE, chunks that must follow it, the rest of the chunks that
+// must precede IDAT, unconstrained chunks, IDAT, unconstrained chunks that
+// followed the image data, IEND.
+pub(crate) fn canonical_rank(chunk_type: &str, after_idat: bool) -> u8 {
+    match chunk_type {
+        "IHDR" => 0,
+        name if BEFORE_PLTE.contains(&name) => 1,
+        "PLTE" => 2,
+        name if AFTER_PLTE.contains(&name) => 3,
+        name if BEFORE_IDAT.contains(&name) => 4,
+        "IDAT" => 6,
+        "IEND" => 8,
+        _ if after_idat => 7,
+        _ => 5,
+    }
+}
+
+pub fn check(png: &Png) -> Result<(), PngmeError> {
+    check_violations(violations(png))
+}
+
+pub fn check_violations(violations: Vec<Violation>) -> Result<(), PngmeError> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(PngmeError::SpecViolations(
+        violations.iter().map(Violation::to_string).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png(types: &[&str]) -> Png {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), vec![]))
+                .collect(),
+        )
+    }
+
+    fn messages(types: &[&str]) -> Vec<String> {
+        violations(&png(types))
+            .iter()
+            .map(Violation::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_layout() {
+        let layout = [
+            "IHDR", "gAMA", "PLTE", "tRNS", "pHYs", "IDAT", "IDAT", "tEXt", "IEND",
+        ];
+        assert!(messages(&layout).is_empty());
+        assert!(check(&png(&layout)).is_ok());
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        assert_eq!(
+            messages(&["IDAT", "IHDR"]),
+            [
+                "chunk #0: IHDR must be the first chunk, found IDAT",
+                "chunk #1: IEND must be the last chunk, found IHDR",
+            ]
+        );
+        assert_eq!(
+            messages(&[]),
+            ["the file has no chunks", "the file has no IDAT chunk"]
+        );
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert_eq!(
+            messages(&["IHDR", "tRNS", "PLTE", "gAMA", "IDAT", "pHYs", "IEND"]),
+            [
+                "chunk #1: tRNS must come after PLTE",
+                "chunk #3: gAMA must come before PLTE",
+                "chunk #5: pHYs must come before IDAT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consecutive_idat_and_single_instance() {
+        assert_eq!(
+            messages(&["IHDR", "IDAT", "tEXt", "IDAT", "tIME", "tIME", "IEND"]),
+            [
+                "chunk #3: IDAT chunks must be consecutive",
+                "chunk #5: tIME may only appear once",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reserved_bit() {
+        let violations = messages(&["IHDR", "IDAT", "rust", "IEND"]);
+        assert_eq!(violations, ["chunk #2: rust has the reserved bit set"]);
+    }
+
+    #[test]
+    fn test_check_lists_violations() {
+        let error = check(&png(&["IHDR", "IEND"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1 spec violation(s): the file has no IDAT chunk"
+        );
+    }
+}
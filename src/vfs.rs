@@ -0,0 +1,196 @@
+use crate::chunk::Chunk;
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+
+pub const INDEX_CHUNK_TYPE: &str = "fsIx";
+pub const DATA_CHUNK_TYPE: &str = "fsDt";
+const ID_LEN: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: u32,
+    pub name: String,
+    pub len: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSystem {
+    entries: Vec<Entry>,
+}
+
+impl FileSystem {
+    pub fn load(png: &Png) -> Result<Self> {
+        match png.chunk_by_type(INDEX_CHUNK_TYPE) {
+            Some(index) => Self::parse(index.data()),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn read(&self, png: &Png, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| PngmeError::ChunkNotFound(format!("no file named {:?}", name)))?;
+        let data = png
+            .chunks_by_type(DATA_CHUNK_TYPE)
+            .map(Chunk::data)
+            .find(|data| data_id(data) == Some(entry.id))
+            .ok_or_else(|| {
+                PngmeError::ChunkNotFound(format!("data chunk for {:?} is missing", name))
+            })?;
+        Ok(data[ID_LEN..].to_vec())
+    }
+
+    pub fn add(&mut self, png: &mut Png, name: &str, data: &[u8], overwrite: bool) -> Result<()> {
+        if name.is_empty() || name.len() > u16::MAX as usize {
+            return Err(PngmeError::Usage(format!(
+                "file name must be 1 to {} bytes",
+                u16::MAX
+            )));
+        }
+        if self.entry(name).is_some() {
+            if !overwrite {
+                return Err(PngmeError::Usage(format!(
+                    "{:?} already exists, pass --force to replace it",
+                    name
+                )));
+            }
+            self.remove(png, name)?;
+        }
+        let id = self
+            .entries
+            .iter()
+            .map(|entry| entry.id + 1)
+            .max()
+            .unwrap_or(0);
+        let chunk_data = id.to_be_bytes().iter().chain(data).copied().collect();
+        png.append_chunk(Chunk::new(DATA_CHUNK_TYPE.parse()?, chunk_data))?;
+        self.entries.push(Entry {
+            id,
+            name: name.to_string(),
+            len: data.len() as u64,
+        });
+        self.save(png)
+    }
+
+    pub fn remove(&mut self, png: &mut Png, name: &str) -> Result<Entry> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| PngmeError::ChunkNotFound(format!("no file named {:?}", name)))?;
+        let entry = self.entries.remove(position);
+        png.remove_chunks_where(|chunk| {
+            chunk.chunk_type().to_string() == DATA_CHUNK_TYPE
+                && data_id(chunk.data()) == Some(entry.id)
+        });
+        self.save(png)?;
+        Ok(entry)
+    }
+
+    fn save(&self, png: &mut Png) -> Result<()> {
+        png.remove_chunks_where(|chunk| chunk.chunk_type().to_string() == INDEX_CHUNK_TYPE);
+        if !self.entries.is_empty() {
+            png.append_chunk(Chunk::new(INDEX_CHUNK_TYPE.parse()?, self.to_bytes()))?;
+        }
+        Ok(())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.entries.len() as u32).to_be_bytes().to_vec();
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.id.to_be_bytes());
+            bytes.extend_from_slice(&entry.len.to_be_bytes());
+            bytes.extend_from_slice(&(entry.name.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(entry.name.as_bytes());
+        }
+        bytes
+    }
+
+    fn parse(mut data: &[u8]) -> Result<Self> {
+        let mut take = |len: usize| -> Result<&[u8]> {
+            if data.len() < len {
+                return Err(PngmeError::Truncated("file system index".to_string()));
+            }
+            let (taken, rest) = data.split_at(len);
+            data = rest;
+            Ok(taken)
+        };
+        let count = u32::from_be_bytes(take(4)?.try_into().unwrap());
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let id = u32::from_be_bytes(take(4)?.try_into().unwrap());
+            let len = u64::from_be_bytes(take(8)?.try_into().unwrap());
+            let name_len = u16::from_be_bytes(take(2)?.try_into().unwrap());
+            let name = String::from_utf8(take(name_len as usize)?.to_vec())?;
+            entries.push(Entry { id, name, len });
+        }
+        Ok(Self { entries })
+    }
+}
+
+fn data_id(data: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(..ID_LEN)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_png() -> Png {
+        Png::from_chunks(Vec::new())
+    }
+
+    #[test]
+    fn test_add_and_read() {
+        let mut png = empty_png();
+        let mut fs = FileSystem::load(&png).unwrap();
+        fs.add(&mut png, "a.txt", b"alpha", false).unwrap();
+        fs.add(&mut png, "b.bin", &[0, 1, 2], false).unwrap();
+
+        let fs = FileSystem::load(&png).unwrap();
+        let names: Vec<&str> = fs.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.bin"]);
+        assert_eq!(fs.read(&png, "a.txt").unwrap(), b"alpha");
+        assert_eq!(fs.read(&png, "b.bin").unwrap(), [0, 1, 2]);
+        assert_eq!(png.chunks_by_type(INDEX_CHUNK_TYPE).count(), 1);
+    }
+
+    #[test]
+    fn test_add_existing_requires_overwrite() {
+        let mut png = empty_png();
+        let mut fs = FileSystem::default();
+        fs.add(&mut png, "a.txt", b"alpha", false).unwrap();
+        assert!(fs.add(&mut png, "a.txt", b"beta", false).is_err());
+        fs.add(&mut png, "a.txt", b"beta", true).unwrap();
+        assert_eq!(fs.read(&png, "a.txt").unwrap(), b"beta");
+        assert_eq!(png.chunks_by_type(DATA_CHUNK_TYPE).count(), 1);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut png = empty_png();
+        let mut fs = FileSystem::default();
+        fs.add(&mut png, "a.txt", b"alpha", false).unwrap();
+        fs.add(&mut png, "b.txt", b"beta", false).unwrap();
+        assert_eq!(fs.remove(&mut png, "a.txt").unwrap().len, 5);
+        assert!(fs.read(&png, "a.txt").is_err());
+        assert_eq!(fs.read(&png, "b.txt").unwrap(), b"beta");
+        assert!(fs.remove(&mut png, "a.txt").is_err());
+
+        fs.remove(&mut png, "b.txt").unwrap();
+        assert!(png.chunks().is_empty());
+    }
+
+    #[test]
+    fn test_truncated_index() {
+        assert!(FileSystem::parse(&[0, 0, 0, 1, 0]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+
+use crate::error::{PngmeError, Result};
+use crate::png::Png;
+use crate::text::{self, International, TextEntry};
+
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+pub fn get(png: &Png) -> Result<Option<String>> {
+    text::get(png, XMP_KEYWORD)
+}
+
+pub fn set(png: &mut Png, packet: &str) -> Result<()> {
+    pretty(packet)?;
+    // XMP in PNG lives in an uncompressed iTXt chunk with empty language fields.
+    let entry = TextEntry {
+        international: Some(International::default()),
+        ..TextEntry::new(XMP_KEYWORD.parse()?, packet)
+    };
+    text::set(png, &entry)
+}
+
+pub fn strip(png: &mut Png) -> bool {
+    !text::remove(png, XMP_KEYWORD).is_empty()
+}
+
+pub fn pretty(packet: &str) -> Result<String> {
+    let mut reader = Reader::from_str(packet);
+    reader.config_mut().trim_text(true);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    loop {
+        match reader
+            .read_event()
+            .map_err(|e| PngmeError::Malformed(format!("XMP: {}", e)))?
+        {
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+    }
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::INTERNATIONAL_TEXT_CHUNK_TYPE;
+
+    const PACKET: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about=""/></rdf:RDF></x:xmpmeta>"#;
+
+    #[test]
+    fn test_set_get_strip() {
+        let mut png = Png::from_chunks(Vec::new());
+        assert_eq!(get(&png).unwrap(), None);
+        set(&mut png, PACKET).unwrap();
+        assert_eq!(
+            png.chunks()[0].chunk_type().to_string(),
+            INTERNATIONAL_TEXT_CHUNK_TYPE
+        );
+        assert_eq!(get(&png).unwrap().unwrap(), PACKET);
+        set(&mut png, PACKET).unwrap();
+        assert_eq!(png.chunks().len(), 1);
+        assert!(strip(&mut png));
+        assert!(!strip(&mut png));
+        assert!(png.chunks().is_empty());
+    }
+
+    #[test]
+    fn test_set_rejects_malformed_xml() {
+        let mut png = Png::from_chunks(Vec::new());
+        assert!(set(&mut png, "<x:xmpmeta><unclosed></x:xmpmeta>").is_err());
+    }
+
+    #[test]
+    fn test_pretty() {
+        let pretty = pretty(PACKET).unwrap();
+        assert_eq!(pretty.lines().count(), 5);
+        assert!(pretty
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with("    <rdf:Description"));
+    }
+}